@@ -0,0 +1,240 @@
+//! Articulation states of rover mechanisms such as the remote sensing mast, the arm and the
+//! chassis, from the *_ARTICULATION_STATE_PARMS groups of MSL labels, the
+//! *_ARTICULATION_STATE groups of MER labels and the matching VICAR properties.
+use crate::camera::Vector3;
+use crate::label::Label;
+use crate::pvl::{Error, FromValue, KeyValuePair, Value, ValueType, ValueUnits};
+
+/// Temperatures at or above this are placeholders for sensors which gave no reading
+const MISSING_TEMPERATURE: f64 = 1.0e30;
+
+/// One angle of a device, e.g. a joint encoder reading or a requested azimuth
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointAngle {
+    pub name: String,
+    /// The angle in radians
+    pub angle: f64,
+    /// Temperature of the joint in degrees Celsius, where the label gives one
+    pub temperature: Option<f64>,
+}
+
+impl JointAngle {
+    pub fn degrees(&self) -> f64 {
+        self.angle.to_degrees()
+    }
+}
+
+/// The state of one articulated device
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArticulationState {
+    /// ARTICULATION_DEVICE_ID, e.g. `RSM` or `ARM`
+    pub device_id: String,
+    pub device_name: Option<String>,
+    pub mode: Option<String>,
+    pub angles: Vec<JointAngle>,
+    /// Named temperatures in degrees Celsius, including sensors with no reading
+    pub temperatures: Vec<(String, Option<f64>)>,
+    /// The instrument at the end of the device, for arms
+    pub instrument_id: Option<String>,
+    /// Named vectors measured on the device, such as the gravity vector
+    pub vectors: Vec<(String, Vector3)>,
+    /// Named contact switch states
+    pub contact_sensors: Vec<(String, String)>,
+    /// The label group or VICAR property the state was read from
+    pub block: String,
+}
+
+/// Finds a property of a block by its exact key
+fn find<'a>(properties: &[&'a KeyValuePair], key: &str) -> Option<&'a Value> {
+    properties
+        .iter()
+        .find(|kvp| kvp.key.is_key(key))
+        .map(|kvp| &kvp.value)
+}
+
+/// Reads a property which may be a single value or an array as a list
+fn list<T: FromValue>(properties: &[&KeyValuePair], key: &str) -> Result<Vec<T>, Error> {
+    match find(properties, key) {
+        Some(v) if v.value_type() == ValueType::Array => Vec::from_value(v),
+        Some(v) => Ok(vec![T::from_value(v)?]),
+        None => Ok(vec![]),
+    }
+}
+
+/// Reads a list of measurements in the requested units. PDS3 labels attach units to each value,
+/// while VICAR labels give them in a parallel `KEY__UNIT` array. Values without units are
+/// taken to be in the requested units already.
+fn measurements(
+    properties: &[&KeyValuePair],
+    key: &str,
+    to: &ValueUnits,
+) -> Result<Vec<f64>, Error> {
+    let values: Vec<Value> = list(properties, key)?;
+    let units: Vec<String> = list(properties, &format!("{}__UNIT", key))?;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| match (v.units(), units.get(i)) {
+            (Some(_), _) => v.parse_f64_as(to),
+            (None, Some(u)) => {
+                crate::units::convert(f64::from_value(v)?, &ValueUnits::from_string(u), to)
+            }
+            (None, None) => f64::from_value(v),
+        })
+        .collect()
+}
+
+/// Words of a name, less joint numbering, for matching temperature sensors with angles
+fn name_words(name: &str) -> Vec<String> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty() && *w != "JOINT" && !w.chars().all(|c| c.is_ascii_digit()))
+        .map(|w| w.to_owned())
+        .collect()
+}
+
+/// Pairs each name with its value, failing if the lists are of different lengths
+fn named<T>(block: &str, names: Vec<String>, values: Vec<T>) -> Result<Vec<(String, T)>, Error> {
+    if names.len() != values.len() {
+        Err(Error::General(format!(
+            "{} has {} names for {} values",
+            block,
+            names.len(),
+            values.len()
+        )))
+    } else {
+        Ok(names.into_iter().zip(values).collect())
+    }
+}
+
+impl ArticulationState {
+    /// Reads a device state from the properties of one label block. Returns None for blocks
+    /// which don't describe a device.
+    pub fn from_properties(
+        block: &str,
+        properties: &[&KeyValuePair],
+    ) -> Result<Option<ArticulationState>, Error> {
+        let device_id = match find(properties, "ARTICULATION_DEVICE_ID") {
+            Some(v) => String::from_value(v)?,
+            None => return Ok(None),
+        };
+        let optional = |key: &str| find(properties, key).map(String::from_value).transpose();
+
+        let temperatures = named(
+            block,
+            list(properties, "ARTICULATION_DEVICE_TEMP_NAME")?,
+            measurements(properties, "ARTICULATION_DEVICE_TEMP", &ValueUnits::Celcius)?
+                .into_iter()
+                .map(|t| {
+                    if t >= MISSING_TEMPERATURE {
+                        None
+                    } else {
+                        Some(t)
+                    }
+                })
+                .collect(),
+        )?;
+
+        let angles = named(
+            block,
+            list(properties, "ARTICULATION_DEVICE_ANGLE_NAME")?,
+            measurements(
+                properties,
+                "ARTICULATION_DEVICE_ANGLE",
+                &ValueUnits::Radians,
+            )?,
+        )?
+        .into_iter()
+        .map(|(name, angle)| {
+            let words = name_words(&name);
+            let temperature = temperatures
+                .iter()
+                .find(|(t, _)| {
+                    let sensor = name_words(t);
+                    !sensor.is_empty() && sensor.iter().all(|w| words.contains(w))
+                })
+                .and_then(|(_, t)| *t);
+            JointAngle {
+                name,
+                angle,
+                temperature,
+            }
+        })
+        .collect();
+
+        let mut vectors = vec![];
+        for (name, v) in named(
+            block,
+            list(properties, "ARTICULATION_DEV_VECTOR_NAME")?,
+            match find(properties, "ARTICULATION_DEV_VECTOR") {
+                Some(v) => vec![Vec::<f64>::from_value(v)?],
+                None => vec![],
+            },
+        )? {
+            match v[..] {
+                [x, y, z] => vectors.push((name, [x, y, z])),
+                _ => {
+                    return Err(Error::General(format!(
+                        "{} vector {} has {} elements, expected 3",
+                        block,
+                        name,
+                        v.len()
+                    )))
+                }
+            }
+        }
+
+        Ok(Some(ArticulationState {
+            device_id,
+            device_name: optional("ARTICULATION_DEVICE_NAME")?,
+            mode: optional("ARTICULATION_DEVICE_MODE")?,
+            angles,
+            temperatures,
+            instrument_id: optional("ARTICULATION_DEV_INSTRUMENT_ID")?,
+            vectors,
+            contact_sensors: named(
+                block,
+                list(properties, "CONTACT_SENSOR_STATE_NAME")?,
+                list(properties, "CONTACT_SENSOR_STATE")?,
+            )?,
+            block: block.to_owned(),
+        }))
+    }
+
+    /// Reads the state of every device described by a PDS3 or VICAR label
+    /// # Example
+    /// ```
+    /// use vicar::articulation::ArticulationState;
+    /// use vicar::pvl::Pvl;
+    ///
+    /// let p = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+    /// let devices = ArticulationState::from_label(&Pvl::load(std::path::Path::new(p)).unwrap()).unwrap();
+    /// let rsm = devices.iter().find(|d| d.device_id == "RSM").unwrap();
+    /// assert_eq!(rsm.angle("AZIMUTH-MEASURED"), Some(2.91281));
+    /// ```
+    pub fn from_label<L: Label>(label: &L) -> Result<Vec<ArticulationState>, L::Error> {
+        let mut devices = vec![];
+        for (path, properties) in label.blocks().iter() {
+            if let Some(device) = ArticulationState::from_properties(path, properties)? {
+                devices.push(device);
+            }
+        }
+        Ok(devices)
+    }
+
+    pub fn joint(&self, name: &str) -> Option<&JointAngle> {
+        self.angles.iter().find(|a| a.name == name)
+    }
+
+    /// The named angle in radians
+    pub fn angle(&self, name: &str) -> Option<f64> {
+        self.joint(name).map(|a| a.angle)
+    }
+
+    /// The named temperature in degrees Celsius, if it was read
+    pub fn temperature(&self, name: &str) -> Option<f64> {
+        self.temperatures
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, t)| *t)
+    }
+}
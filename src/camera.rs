@@ -0,0 +1,629 @@
+//! Typed CAHV, CAHVOR and CAHVORE camera models, as carried by the GEOMETRIC_CAMERA_MODEL
+//! group of MSL and MER PDS3 labels and the property of the same name in their VICAR labels.
+//! Projections follow the JPL formulations of the models.
+use crate::frame::{frame_index, FrameId, Transform};
+use crate::label::Label;
+use crate::pvl::{self, FromValue};
+use crate::vicar::VicarError;
+use std::fmt;
+
+/// A point or direction in the model's reference frame
+pub type Vector3 = [f64; 3];
+
+/// A pixel location as (sample, line)
+pub type Pixel = [f64; 2];
+
+const MAX_ITERATIONS: usize = 20;
+const CONVERGENCE: f64 = 1.0e-8;
+const EPSILON: f64 = 1.0e-15;
+
+/// Errors reading or applying a camera model
+#[derive(Debug)]
+pub enum Error {
+    /// The label couldn't be read, or a value of the model converted
+    Label(String),
+    /// The model is malformed, or can't project the point or pixel given
+    Model(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Label(s) => write!(f, "Camera model label error: {}", s),
+            Error::Model(s) => write!(f, "Camera model error: {}", s),
+        }
+    }
+}
+
+impl From<pvl::Error> for Error {
+    fn from(value: pvl::Error) -> Self {
+        Error::Label(t!(value))
+    }
+}
+
+impl From<VicarError> for Error {
+    fn from(value: VicarError) -> Self {
+        Error::Label(t!(value))
+    }
+}
+
+pub(crate) fn add(a: &Vector3, b: &Vector3) -> Vector3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub(a: &Vector3, b: &Vector3) -> Vector3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn scale(s: f64, a: &Vector3) -> Vector3 {
+    [s * a[0], s * a[1], s * a[2]]
+}
+
+pub(crate) fn dot(a: &Vector3, b: &Vector3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: &Vector3, b: &Vector3) -> Vector3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn magnitude(a: &Vector3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+pub(crate) fn unit(a: &Vector3) -> Vector3 {
+    scale(1.0 / magnitude(a), a)
+}
+
+/// A ray leaving the camera through a pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3,
+    /// Unit vector along the ray
+    pub direction: Vector3,
+}
+
+/// Projection between points in the model's reference frame and image pixels
+pub trait CameraProjection {
+    /// Projects a point onto the image. Fails for points which can't be imaged, such as those
+    /// on the plane of the camera center.
+    fn project(&self, point: &Vector3) -> Result<Pixel, Error>;
+
+    /// The ray of points which project onto a pixel
+    fn unproject(&self, pixel: &Pixel) -> Result<Ray, Error>;
+}
+
+/// The linear CAHV model: center, axis, and horizontal and vertical image plane vectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cahv {
+    pub c: Vector3,
+    pub a: Vector3,
+    pub h: Vector3,
+    pub v: Vector3,
+}
+
+impl Cahv {
+    /// The unit ray direction through a pixel, before any lens distortion is removed
+    fn ray_direction(&self, pixel: &Pixel) -> Vector3 {
+        let f = sub(&self.v, &scale(pixel[1], &self.a));
+        let g = sub(&self.h, &scale(pixel[0], &self.a));
+        let direction = unit(&cross(&f, &g));
+        // Left handed image axes flip the cross product
+        if dot(&cross(&self.v, &self.h), &self.a) < 0.0 {
+            scale(-1.0, &direction)
+        } else {
+            direction
+        }
+    }
+}
+
+impl CameraProjection for Cahv {
+    fn project(&self, point: &Vector3) -> Result<Pixel, Error> {
+        let d = sub(point, &self.c);
+        let range = dot(&d, &self.a);
+        if range.abs() < EPSILON {
+            return Err(Error::Model(
+                "Point is on the plane of the camera center".to_owned(),
+            ));
+        }
+        Ok([dot(&d, &self.h) / range, dot(&d, &self.v) / range])
+    }
+
+    fn unproject(&self, pixel: &Pixel) -> Result<Ray, Error> {
+        Ok(Ray {
+            origin: self.c,
+            direction: self.ray_direction(pixel),
+        })
+    }
+}
+
+/// CAHV with radial lens distortion about the optical axis O, with coefficients R
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cahvor {
+    pub c: Vector3,
+    pub a: Vector3,
+    pub h: Vector3,
+    pub v: Vector3,
+    pub o: Vector3,
+    pub r: Vector3,
+}
+
+impl Cahvor {
+    fn cahv(&self) -> Cahv {
+        Cahv {
+            c: self.c,
+            a: self.a,
+            h: self.h,
+            v: self.v,
+        }
+    }
+}
+
+impl CameraProjection for Cahvor {
+    fn project(&self, point: &Vector3) -> Result<Pixel, Error> {
+        let p_c = sub(point, &self.c);
+        let omega = dot(&p_c, &self.o);
+        if omega.abs() < EPSILON {
+            return Err(Error::Model(
+                "Point is on the plane of the camera center".to_owned(),
+            ));
+        }
+        let lambda = sub(&p_c, &scale(omega, &self.o));
+        let tau = dot(&lambda, &lambda) / (omega * omega);
+        let mu = self.r[0] + self.r[1] * tau + self.r[2] * tau * tau;
+        let distorted = add(point, &scale(mu, &lambda));
+        self.cahv().project(&distorted)
+    }
+
+    fn unproject(&self, pixel: &Pixel) -> Result<Ray, Error> {
+        let rr = self.cahv().ray_direction(pixel);
+
+        // The distortion is removed by solving k5*u^5 + k3*u^3 + k1*u = 1 for u with
+        // Newton's method, using terms from the distorted ray
+        let omega = dot(&rr, &self.o);
+        let lambda = sub(&rr, &scale(omega, &self.o));
+        let tau = dot(&lambda, &lambda) / (omega * omega);
+        let k1 = 1.0 + self.r[0];
+        let k3 = self.r[1] * tau;
+        let k5 = self.r[2] * tau * tau;
+        let mut u = 1.0 - (self.r[0] + k3 + k5);
+        let mut converged = false;
+        for _ in 0..MAX_ITERATIONS {
+            let u2 = u * u;
+            let poly = ((k5 * u2 + k3) * u2 + k1) * u - 1.0;
+            let deriv = (5.0 * k5 * u2 + 3.0 * k3) * u2 + k1;
+            if deriv <= EPSILON {
+                break;
+            }
+            let du = poly / deriv;
+            u -= du;
+            if du.abs() < CONVERGENCE {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            return Err(Error::Model(format!(
+                "Lens distortion did not converge for pixel {:?}",
+                pixel
+            )));
+        }
+
+        let mu = 1.0 - u;
+        Ok(Ray {
+            origin: self.c,
+            direction: unit(&sub(&rr, &scale(mu, &lambda))),
+        })
+    }
+}
+
+/// How a CAHVORE model maps incoming ray angles onto the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Linearity {
+    Perspective,
+    Fisheye,
+    /// Between perspective (1) and fisheye (0), or beyond either
+    General(f64),
+}
+
+impl Linearity {
+    /// From the model type and parameter, MODEL_COMPONENT_8 and MODEL_COMPONENT_9
+    pub fn from_parameters(model_type: i64, parameter: f64) -> Result<Linearity, Error> {
+        match model_type {
+            1 => Ok(Linearity::Perspective),
+            2 => Ok(Linearity::Fisheye),
+            3 => Ok(Linearity::General(parameter)),
+            _ => Err(Error::Model(format!(
+                "Invalid CAHVORE model type: {}",
+                model_type
+            ))),
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        match self {
+            Linearity::Perspective => 1.0,
+            Linearity::Fisheye => 0.0,
+            Linearity::General(l) => *l,
+        }
+    }
+}
+
+/// CAHVOR with a moving entrance pupil, E, for wide angle and fisheye lenses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cahvore {
+    pub c: Vector3,
+    pub a: Vector3,
+    pub h: Vector3,
+    pub v: Vector3,
+    pub o: Vector3,
+    pub r: Vector3,
+    pub e: Vector3,
+    pub linearity: Linearity,
+}
+
+impl Cahvore {
+    /// Shift of the entrance pupil along O for a ray at angle theta to the axis
+    fn pupil_shift(&self, theta: f64) -> f64 {
+        let theta2 = theta * theta;
+        ((theta / theta.sin()) - 1.0)
+            * (self.e[0] + self.e[1] * theta2 + self.e[2] * theta2 * theta2)
+    }
+}
+
+impl CameraProjection for Cahvore {
+    fn project(&self, point: &Vector3) -> Result<Pixel, Error> {
+        let p_c = sub(point, &self.c);
+        let zetap = dot(&p_c, &self.o);
+        if zetap.abs() < EPSILON {
+            return Err(Error::Model(
+                "Point is on the plane of the camera center".to_owned(),
+            ));
+        }
+        let lambdap3 = sub(&p_c, &scale(zetap, &self.o));
+        let lambdap = magnitude(&lambdap3);
+        let chip = lambdap / zetap;
+        let linearity = self.linearity.value();
+        let e = &self.e;
+
+        let rp = if chip < 1.0e-8 {
+            p_c
+        } else {
+            // Find the angle of the incoming ray, allowing for the entrance pupil shift
+            let mut theta = chip.atan();
+            let mut converged = false;
+            for _ in 0..MAX_ITERATIONS {
+                let (sinth, costh) = theta.sin_cos();
+                let theta2 = theta * theta;
+                let theta3 = theta * theta2;
+                let theta4 = theta * theta3;
+                let upsilon = zetap * costh + lambdap * sinth
+                    - (1.0 - costh) * (e[0] + e[1] * theta2 + e[2] * theta4)
+                    - (theta - sinth) * (2.0 * e[1] * theta + 4.0 * e[2] * theta3);
+                let dtheta = (zetap * sinth
+                    - lambdap * costh
+                    - (theta - sinth) * (e[0] + e[1] * theta2 + e[2] * theta4))
+                    / upsilon;
+                theta -= dtheta;
+                if dtheta.abs() < CONVERGENCE {
+                    converged = true;
+                    break;
+                }
+            }
+            if !converged || theta * linearity.abs() > std::f64::consts::FRAC_PI_2 {
+                return Err(Error::Model(format!(
+                    "Point {:?} can't be projected",
+                    point
+                )));
+            }
+
+            if theta < 1.0e-8 {
+                p_c
+            } else {
+                let linth = linearity * theta;
+                let chi = if linearity < -EPSILON {
+                    linth.sin() / linearity
+                } else if linearity > EPSILON {
+                    linth.tan() / linearity
+                } else {
+                    theta
+                };
+                let chi2 = chi * chi;
+                let mu = self.r[0] + self.r[1] * chi2 + self.r[2] * chi2 * chi2;
+                add(&scale(lambdap / chi, &self.o), &scale(1.0 + mu, &lambdap3))
+            }
+        };
+
+        let alpha = dot(&rp, &self.a);
+        Ok([dot(&rp, &self.h) / alpha, dot(&rp, &self.v) / alpha])
+    }
+
+    fn unproject(&self, pixel: &Pixel) -> Result<Ray, Error> {
+        let f = sub(&self.v, &scale(pixel[1], &self.a));
+        let g = sub(&self.h, &scale(pixel[0], &self.a));
+        let rp = scale(1.0 / dot(&self.a, &cross(&self.v, &self.h)), &cross(&f, &g));
+        let zetap = dot(&rp, &self.o);
+        let lambdap3 = sub(&rp, &scale(zetap, &self.o));
+        let lambdap = magnitude(&lambdap3);
+        let chip = lambdap / zetap;
+        if chip < 1.0e-8 {
+            return Ok(Ray {
+                origin: self.c,
+                direction: unit(&rp),
+            });
+        }
+
+        // Remove the radial distortion with Newton's method
+        let r = &self.r;
+        let mut chi = chip;
+        let mut converged = false;
+        for _ in 0..MAX_ITERATIONS {
+            let chi2 = chi * chi;
+            let chi4 = chi2 * chi2;
+            let deriv = (1.0 + r[0]) + 3.0 * r[1] * chi2 + 5.0 * r[2] * chi4;
+            let dchi = ((1.0 + r[0]) * chi + r[1] * chi * chi2 + r[2] * chi * chi4 - chip) / deriv;
+            chi -= dchi;
+            if dchi.abs() < CONVERGENCE {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            return Err(Error::Model(format!(
+                "Lens distortion did not converge for pixel {:?}",
+                pixel
+            )));
+        }
+
+        let linearity = self.linearity.value();
+        let linchi = linearity * chi;
+        let theta = if linearity < -EPSILON {
+            linchi.asin() / linearity
+        } else if linearity > EPSILON {
+            linchi.atan() / linearity
+        } else {
+            chi
+        };
+
+        let (sinth, costh) = theta.sin_cos();
+        Ok(Ray {
+            origin: add(&self.c, &scale(self.pupil_shift(theta), &self.o)),
+            direction: unit(&add(
+                &scale(sinth, &unit(&lambdap3)),
+                &scale(costh, &self.o),
+            )),
+        })
+    }
+}
+
+/// One of the supported camera models
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Model {
+    Cahv(Cahv),
+    Cahvor(Cahvor),
+    Cahvore(Cahvore),
+}
+
+impl Model {
+    /// The MODEL_TYPE name of the model
+    pub fn model_type(&self) -> &'static str {
+        match self {
+            Model::Cahv(_) => "CAHV",
+            Model::Cahvor(_) => "CAHVOR",
+            Model::Cahvore(_) => "CAHVORE",
+        }
+    }
+
+    /// The camera center
+    pub fn c(&self) -> Vector3 {
+        match self {
+            Model::Cahv(m) => m.c,
+            Model::Cahvor(m) => m.c,
+            Model::Cahvore(m) => m.c,
+        }
+    }
+
+    /// The model expressed in another frame. The center moves with the frame, while the
+    /// direction vectors are only rotated.
+    pub fn transformed(&self, transform: &Transform) -> Model {
+        let point = |v: &Vector3| transform.apply(v);
+        let vector = |v: &Vector3| transform.rotate(v);
+        match self {
+            Model::Cahv(m) => Model::Cahv(Cahv {
+                c: point(&m.c),
+                a: vector(&m.a),
+                h: vector(&m.h),
+                v: vector(&m.v),
+            }),
+            Model::Cahvor(m) => Model::Cahvor(Cahvor {
+                c: point(&m.c),
+                a: vector(&m.a),
+                h: vector(&m.h),
+                v: vector(&m.v),
+                o: vector(&m.o),
+                ..*m
+            }),
+            Model::Cahvore(m) => Model::Cahvore(Cahvore {
+                c: point(&m.c),
+                a: vector(&m.a),
+                h: vector(&m.h),
+                v: vector(&m.v),
+                o: vector(&m.o),
+                ..*m
+            }),
+        }
+    }
+}
+
+impl CameraProjection for Model {
+    fn project(&self, point: &Vector3) -> Result<Pixel, Error> {
+        match self {
+            Model::Cahv(m) => m.project(point),
+            Model::Cahvor(m) => m.project(point),
+            Model::Cahvore(m) => m.project(point),
+        }
+    }
+
+    fn unproject(&self, pixel: &Pixel) -> Result<Ray, Error> {
+        match self {
+            Model::Cahv(m) => m.unproject(pixel),
+            Model::Cahvor(m) => m.unproject(pixel),
+            Model::Cahvore(m) => m.unproject(pixel),
+        }
+    }
+}
+
+/// A camera model read from a label, along with the frame it's expressed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraModel {
+    pub model: Model,
+    /// REFERENCE_COORD_SYSTEM_NAME, e.g. `ROVER_NAV_FRAME`
+    pub reference_frame: Option<String>,
+    /// REFERENCE_COORD_SYSTEM_INDEX, the site, drive and pose counters of the frame
+    pub reference_index: Vec<i64>,
+    pub calibration_source_id: Option<String>,
+    /// INTERPOLATION_METHOD, how the model was derived for the camera's pointing
+    pub interpolation_method: Option<String>,
+    pub interpolation_value: Option<f64>,
+}
+
+/// Paths of the group or VICAR property holding the model. MSL PDS3 labels name it
+/// GEOMETRIC_CAMERA_MODEL_PARMS, while MER and VICAR labels use GEOMETRIC_CAMERA_MODEL.
+const MODEL_BLOCK: &str = "GEOMETRIC_CAMERA_MODEL*";
+
+/// The first value found for a keyword of the model, if any
+fn model_value<L: Label, T: FromValue>(label: &L, key: &str) -> Result<Option<T>, Error>
+where
+    Error: From<L::Error>,
+{
+    Ok(label
+        .query::<T>(&format!("{}/{}", MODEL_BLOCK, key))?
+        .into_iter()
+        .next())
+}
+
+/// A keyword which may carry the mission namespace, e.g. `MSL:INTERPOLATION_METHOD`
+fn namespaced_value<L: Label, T: FromValue>(label: &L, key: &str) -> Result<Option<T>, Error>
+where
+    Error: From<L::Error>,
+{
+    match model_value(label, key)? {
+        Some(v) => Ok(Some(v)),
+        None => model_value(label, &format!("*:{}", key)),
+    }
+}
+
+fn component<L: Label>(label: &L, n: usize) -> Result<Vector3, Error>
+where
+    Error: From<L::Error>,
+{
+    let v: Vec<f64> = label.query_one(&format!("{}/MODEL_COMPONENT_{}", MODEL_BLOCK, n))?;
+    match v[..] {
+        [x, y, z] => Ok([x, y, z]),
+        _ => Err(Error::Model(format!(
+            "MODEL_COMPONENT_{} has {} elements, expected 3",
+            n,
+            v.len()
+        ))),
+    }
+}
+
+impl CameraModel {
+    /// Reads the camera model of a PDS3 or VICAR label
+    /// # Example
+    /// ```
+    /// use vicar::camera::{CameraModel, CameraProjection};
+    /// use vicar::pvl::Pvl;
+    ///
+    /// let p = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+    /// let pvl = Pvl::load(std::path::Path::new(p)).unwrap();
+    /// let camera = CameraModel::from_label(&pvl).unwrap();
+    /// assert_eq!(camera.model.model_type(), "CAHVOR");
+    /// assert_eq!(camera.reference_frame.as_deref(), Some("ROVER_NAV_FRAME"));
+    ///
+    /// let ray = camera.model.unproject(&[511.5, 511.5]).unwrap();
+    /// let point = [ray.origin[0] + ray.direction[0], ray.origin[1] + ray.direction[1], ray.origin[2] + ray.direction[2]];
+    /// let pixel = camera.model.project(&point).unwrap();
+    /// assert!((pixel[0] - 511.5).abs() < 1.0e-3 && (pixel[1] - 511.5).abs() < 1.0e-3);
+    /// ```
+    pub fn from_label<L: Label>(label: &L) -> Result<CameraModel, Error>
+    where
+        Error: From<L::Error>,
+    {
+        let model_type: String = label.query_one(&format!("{}/MODEL_TYPE", MODEL_BLOCK))?;
+        let (c, a, h, v) = (
+            component(label, 1)?,
+            component(label, 2)?,
+            component(label, 3)?,
+            component(label, 4)?,
+        );
+        let model = match model_type.to_uppercase().as_str() {
+            "CAHV" => Model::Cahv(Cahv { c, a, h, v }),
+            "CAHVOR" => Model::Cahvor(Cahvor {
+                c,
+                a,
+                h,
+                v,
+                o: component(label, 5)?,
+                r: component(label, 6)?,
+            }),
+            "CAHVORE" => {
+                let model_type: f64 =
+                    label.query_one(&format!("{}/MODEL_COMPONENT_8", MODEL_BLOCK))?;
+                let parameter: f64 =
+                    label.query_one(&format!("{}/MODEL_COMPONENT_9", MODEL_BLOCK))?;
+                Model::Cahvore(Cahvore {
+                    c,
+                    a,
+                    h,
+                    v,
+                    o: component(label, 5)?,
+                    r: component(label, 6)?,
+                    e: component(label, 7)?,
+                    linearity: Linearity::from_parameters(model_type.round() as i64, parameter)?,
+                })
+            }
+            t => {
+                return Err(Error::Model(format!(
+                    "Unsupported camera model type: {}",
+                    t
+                )))
+            }
+        };
+
+        Ok(CameraModel {
+            model,
+            reference_frame: model_value(label, "REFERENCE_COORD_SYSTEM_NAME")?,
+            reference_index: match model_value(label, "REFERENCE_COORD_SYSTEM_INDEX")? {
+                Some(v) => frame_index(&v)?,
+                None => vec![],
+            },
+            calibration_source_id: model_value(label, "CALIBRATION_SOURCE_ID")?,
+            interpolation_method: namespaced_value(label, "INTERPOLATION_METHOD")?,
+            interpolation_value: namespaced_value(label, "INTERPOLATION_VALUE")?,
+        })
+    }
+
+    /// The frame the model is expressed in, if the label names it
+    pub fn frame(&self) -> Option<FrameId> {
+        self.reference_frame
+            .as_ref()
+            .map(|name| FrameId::new(name, &self.reference_index))
+    }
+
+    /// The model moved into another frame by a transform
+    pub fn transformed(&self, transform: &Transform, frame: &FrameId) -> CameraModel {
+        CameraModel {
+            model: self.model.transformed(transform),
+            reference_frame: Some(frame.name.to_owned()),
+            reference_index: frame.index.to_owned(),
+            ..self.clone()
+        }
+    }
+}
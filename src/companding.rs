@@ -0,0 +1,223 @@
+//! Reversal of the lookup tables with which cameras compand 12 bit samples to 8 bits for
+//! downlink. MSL and MER labels name the table in SAMPLE_BIT_MODE_ID, and Cassini ISS labels
+//! give DATA_CONVERSION_TYPE = "TABLE" for companded images. The inverse tables are published
+//! by each mission alongside its data; a registry holds the tables loaded from those files and
+//! finds the one a label calls for. `CompandingRegistry::published` holds the tables embedded
+//! in the crate, and `CompandingRegistry::from_dir` loads others from a directory.
+use crate::label::Label;
+use crate::observation::{Mission, Profile};
+use crate::pvl::Error;
+use std::fs;
+use std::path::Path;
+
+/// Missions which compand, and the subdirectory of the tables directory holding their tables
+const MISSION_DIRS: [(Mission, &str); 3] = [
+    (Mission::MarsExplorationRover, "mer"),
+    (Mission::MarsScienceLaboratory, "msl"),
+    (Mission::Cassini, "cassini"),
+];
+
+/// The published tables embedded in the crate, by mission and the name labels give them, with
+/// the text of each as published, e.g.
+/// `(Mission::MarsExplorationRover, "LUT3", include_str!("companding/mer/LUT3.txt"))`
+const PUBLISHED: &[(Mission, &str, &str)] = &[];
+
+/// Identifies a companding table by mission and the name its labels give it, e.g. `LUT3` of
+/// the MER Pancams or `MMM_LUT0` of the MSL Mastcams
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableId {
+    pub mission: Mission,
+    pub name: String,
+}
+
+impl TableId {
+    pub fn new(mission: Mission, name: &str) -> Self {
+        TableId {
+            mission,
+            name: name.to_uppercase(),
+        }
+    }
+
+    /// Determines the table a PDS3 or VICAR label's samples were companded with. Returns None
+    /// for products which weren't companded.
+    /// # Example
+    /// ```
+    /// use vicar::companding::TableId;
+    /// use vicar::observation::Mission;
+    /// use vicar::pvl::Pvl;
+    ///
+    /// let p = "tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img.lbl";
+    /// let id = TableId::from_label(&Pvl::load(std::path::Path::new(p)).unwrap());
+    /// assert_eq!(id, Some(TableId::new(Mission::MarsExplorationRover, "LUT3")));
+    /// ```
+    pub fn from_label<L: Label>(label: &L) -> Option<TableId> {
+        let mission = Profile::detect(label).mission();
+        let name: Option<String> = match mission {
+            Mission::Cassini => ["DATA_CONVERSION_TYPE", "IMAGE/DATA_CONVERSION_TYPE"]
+                .iter()
+                .find_map(|p| label.query_one::<String>(p).ok())
+                .filter(|t| t.eq_ignore_ascii_case("TABLE")),
+            _ => [
+                "IMAGE/SAMPLE_BIT_MODE_ID",
+                "INSTRUMENT_STATE_PARMS/SAMPLE_BIT_MODE_ID",
+            ]
+            .iter()
+            .find_map(|p| label.query_one::<String>(p).ok())
+            .filter(|m| !m.eq_ignore_ascii_case("NONE") && !m.eq_ignore_ascii_case("N/A")),
+        };
+        name.map(|n| TableId::new(mission, &n))
+    }
+}
+
+/// An inverse companding table, giving the linear value of each companded value
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompandingTable {
+    pub values: Vec<f64>,
+}
+
+impl CompandingTable {
+    pub fn new(values: &[f64]) -> Self {
+        CompandingTable {
+            values: values.to_vec(),
+        }
+    }
+
+    /// Parses a table as published, one entry per line. Lines of one number give the linear
+    /// values in order of companded value, and lines of two give the companded value and its
+    /// linear value. Commas and tabs separate numbers as well as spaces, and lines which
+    /// aren't numeric, such as headers, are skipped.
+    pub fn from_string(text: &str) -> Result<Self, Error> {
+        let mut values: Vec<f64> = vec![];
+        for line in text.lines() {
+            let numbers: Result<Vec<f64>, _> = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse::<f64>())
+                .collect();
+            match numbers.as_deref() {
+                Ok([value]) => values.push(*value),
+                Ok([index, value]) => {
+                    let i = *index as usize;
+                    if *index < 0.0 || index.fract() != 0.0 || i > 65535 {
+                        return Err(Error::General(format!(
+                            "Invalid companded value in table: {}",
+                            line
+                        )));
+                    }
+                    if values.len() <= i {
+                        values.resize(i + 1, f64::NAN);
+                    }
+                    values[i] = *value;
+                }
+                _ => {}
+            }
+        }
+        if values.is_empty() {
+            Err(Error::General("Companding table has no entries".to_owned()))
+        } else if values.iter().any(|v| v.is_nan()) {
+            Err(Error::General(
+                "Companding table is missing entries".to_owned(),
+            ))
+        } else {
+            Ok(CompandingTable { values })
+        }
+    }
+
+    pub fn load(file_path: &Path) -> Result<Self, Error> {
+        match fs::read_to_string(file_path) {
+            Ok(text) => CompandingTable::from_string(&text),
+            Err(why) => Err(Error::General(t!(why))),
+        }
+    }
+
+    /// The linear value of a companded value. Returns None for values outside the table.
+    pub fn linearize(&self, value: f64) -> Option<f64> {
+        if value < 0.0 || value.fract() != 0.0 {
+            None
+        } else {
+            self.values.get(value as usize).copied()
+        }
+    }
+}
+
+/// The companding tables available for reversal
+#[derive(Debug, Clone, Default)]
+pub struct CompandingRegistry {
+    tables: Vec<(TableId, CompandingTable)>,
+}
+
+impl CompandingRegistry {
+    pub fn new() -> Self {
+        CompandingRegistry { tables: vec![] }
+    }
+
+    /// Adds a table, replacing any previous table with the same id
+    pub fn register(&mut self, id: TableId, table: CompandingTable) {
+        self.tables.retain(|(i, _)| *i != id);
+        self.tables.push((id, table));
+    }
+
+    /// Loads and adds a published table file
+    pub fn load(&mut self, id: TableId, file_path: &Path) -> Result<(), Error> {
+        self.register(id, CompandingTable::load(file_path)?);
+        Ok(())
+    }
+
+    /// Loads every table in a directory laid out as `<mission>/<NAME>.txt`, where mission is
+    /// `mer`, `msl` or `cassini` and NAME is the name labels give the table, e.g.
+    /// `mer/LUT3.txt`, `msl/MMM_LUT0.txt` or `cassini/TABLE.txt`. Other files are skipped.
+    pub fn from_dir(dir: &Path) -> Result<Self, Error> {
+        let mut registry = CompandingRegistry::new();
+        for (mission, name) in MISSION_DIRS {
+            let mission_dir = dir.join(name);
+            if !mission_dir.is_dir() {
+                continue;
+            }
+            let entries = fs::read_dir(&mission_dir).map_err(|why| Error::General(t!(why)))?;
+            for entry in entries {
+                let path = entry.map_err(|why| Error::General(t!(why)))?.path();
+                let is_table = path
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("txt"));
+                match path.file_stem().and_then(|s| s.to_str()) {
+                    Some(stem) if is_table => registry.load(TableId::new(mission, stem), &path)?,
+                    _ => {}
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    /// The published tables embedded in the crate. Tables which aren't among them can be
+    /// loaded with `from_dir` or `load`, and given to readers through
+    /// `VicarReader::set_read_options_with_registry`.
+    pub fn published() -> Result<Self, Error> {
+        let mut registry = CompandingRegistry::new();
+        for (mission, name, text) in PUBLISHED {
+            registry.register(
+                TableId::new(*mission, name),
+                CompandingTable::from_string(text)?,
+            );
+        }
+        Ok(registry)
+    }
+
+    pub fn find(&self, id: &TableId) -> Option<&CompandingTable> {
+        self.tables.iter().find(|(i, _)| i == id).map(|(_, t)| t)
+    }
+
+    /// The table a label's samples were companded with. Returns None for products which
+    /// weren't companded, and fails for products companded with a table not registered.
+    pub fn for_label<L: Label>(&self, label: &L) -> Result<Option<&CompandingTable>, Error> {
+        match TableId::from_label(label) {
+            Some(id) => match self.find(&id) {
+                Some(table) => Ok(Some(table)),
+                None => Err(Error::General(format!(
+                    "No companding table registered for {:?} {}",
+                    id.mission, id.name
+                ))),
+            },
+            None => Ok(None),
+        }
+    }
+}
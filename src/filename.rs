@@ -0,0 +1,523 @@
+//! Decoders for the product ids of the MSL engineering cameras and the MER cameras, which encode
+//! the instrument, clock count, product type, rover position and sequence of a product. Decoded
+//! ids can be built back into names, and checked against the observation metadata of a label.
+use crate::observation::{MissionExtras, ObservationMetadata, RoverExtras};
+use regex::Regex;
+use std::fmt;
+
+lazy_static! {
+    // e.g. NRB_701384494RAD_F0933408NCAM00200M1
+    static ref MSL_ECAM: Regex = Regex::new(
+        "^([NFR])([LR])([AB])([_A-Z0-9])([0-9]{9})([A-Z0-9]{3})([_A-Z0-9])([A-Z])([0-9A-Z]{3})([0-9A-Z]{4})([A-Z]{4}[0-9]{5})([A-Z0-9])([A-Z0-9])$"
+    )
+    .unwrap();
+    // e.g. 1N579700548FFLD2FCP1981L0M1
+    static ref MER: Regex = Regex::new(
+        "^([12])([FRNPME])([0-9]{9})([A-Z0-9]{3})([0-9A-Z]{2})([0-9A-Z]{2})([A-Z][0-9]{4})([LRMN])([0-9A-Z])([A-Z])([0-9A-Z])$"
+    )
+    .unwrap();
+}
+
+/// Errors decoding or encoding product ids
+#[derive(Debug)]
+pub enum Error {
+    /// The text isn't a product id of a supported format, or a field of it is invalid
+    Decode(String),
+    /// A field doesn't fit in the id, e.g. a site beyond the counters' range
+    Encode(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Decode(s) => write!(f, "Invalid product id: {}", s),
+            Error::Encode(s) => write!(f, "Product id can't be encoded: {}", s),
+        }
+    }
+}
+
+/// Decodes a site or drive counter. Counters are written in decimal while they fit the field,
+/// then as a letter followed by base 36 digits, so `D2` of a two character field is 210.
+fn decode_counter(s: &str) -> Option<u32> {
+    if s.chars().all(|c| c.is_ascii_digit()) {
+        return s.parse::<u32>().ok();
+    }
+    let mut chars = s.chars();
+    let first = chars.next()?;
+    if !first.is_ascii_uppercase() {
+        return None;
+    }
+    let rest = chars.try_fold(0, |n, c| Some(n * 36 + c.to_digit(36)?))?;
+    let width = s.len() as u32;
+    Some(10u32.pow(width) + (first as u32 - 'A' as u32) * 36u32.pow(width - 1) + rest)
+}
+
+/// Encodes a site or drive counter into a field of the given width, the inverse of
+/// `decode_counter`. Returns None for counters too large for the field.
+fn encode_counter(n: u32, width: usize) -> Option<String> {
+    let decimal = 10u32.pow(width as u32);
+    if n < decimal {
+        return Some(format!("{:0width$}", n, width = width));
+    }
+    let place = 36u32.pow(width as u32 - 1);
+    let letter = (n - decimal) / place;
+    if letter >= 26 {
+        return None;
+    }
+    let mut rest = (n - decimal) % place;
+    let mut digits = vec![];
+    for _ in 1..width {
+        digits.push(std::char::from_digit(rest % 36, 36)?.to_ascii_uppercase());
+        rest /= 36;
+    }
+    digits.push((b'A' + letter as u8) as char);
+    Some(digits.iter().rev().collect())
+}
+
+fn eye_name(eye: char) -> &'static str {
+    match eye {
+        'L' => "LEFT",
+        'R' => "RIGHT",
+        _ => "MONO",
+    }
+}
+
+fn capture_char(caps: &regex::Captures, i: usize) -> char {
+    caps[i].chars().next().unwrap_or('_')
+}
+
+fn counter(caps: &regex::Captures, i: usize, name: &str) -> Result<u32, Error> {
+    decode_counter(&caps[i]).ok_or_else(|| Error::Decode(format!("Invalid {}: {}", name, &caps[i])))
+}
+
+/// A field of a product id which disagrees with the label
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub field: String,
+    /// The value encoded in the product id
+    pub product_id: String,
+    pub label: String,
+}
+
+/// Records a mismatch where the label gives a value which differs from the product id. Values
+/// are compared without regard to case, as MER file names are lower case.
+fn compare(mismatches: &mut Vec<Mismatch>, field: &str, expected: &str, label: Option<&str>) {
+    if let Some(label) = label {
+        if !label.eq_ignore_ascii_case(expected) {
+            mismatches.push(Mismatch {
+                field: field.to_owned(),
+                product_id: expected.to_owned(),
+                label: label.to_owned(),
+            });
+        }
+    }
+}
+
+/// The checks common to the rover cameras of both missions
+fn compare_rover<'a>(
+    mismatches: &mut Vec<Mismatch>,
+    metadata: &'a ObservationMetadata,
+    sclk: u64,
+    site: u32,
+    drive: u32,
+    eye: char,
+    sequence: &str,
+) -> Option<&'a RoverExtras> {
+    compare(
+        mismatches,
+        "SPACECRAFT_CLOCK_START_COUNT",
+        &sclk.to_string(),
+        metadata
+            .spacecraft_clock_start
+            .as_ref()
+            .map(|c| c.ticks().to_string())
+            .as_deref(),
+    );
+    let extras = match &metadata.extras {
+        MissionExtras::MarsRover(extras) => extras,
+        _ => return None,
+    };
+    let counters: Vec<String> = extras
+        .rover_motion_counter
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    compare(
+        mismatches,
+        "SITE",
+        &site.to_string(),
+        counters.first().map(|s| s.as_str()),
+    );
+    compare(
+        mismatches,
+        "DRIVE",
+        &drive.to_string(),
+        counters.get(1).map(|s| s.as_str()),
+    );
+    compare(
+        mismatches,
+        "FRAME_ID",
+        eye_name(eye),
+        extras.frame_id.as_deref(),
+    );
+    compare(
+        mismatches,
+        "SEQUENCE_ID",
+        sequence,
+        extras.sequence_id.as_deref(),
+    );
+    Some(extras)
+}
+
+/// The product id of an MSL Navcam or Hazcam product, e.g.
+/// `NRB_701384494RAD_F0933408NCAM00200M1`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MslEcamProductId {
+    /// `N` for the Navcams, `F` or `R` for the front or rear Hazcams
+    pub camera: char,
+    /// `L` or `R`
+    pub eye: char,
+    /// The compute element the camera was read through, `A` or `B`
+    pub config: char,
+    /// Special processing flag, `_` for none
+    pub special: char,
+    pub sclk: u64,
+    /// e.g. `EDR`, `RAD` or `ILT`
+    pub product_type: String,
+    /// `_` for raw geometry, `L` for linearized
+    pub geometry: char,
+    /// `F` for full frames, `S` subframes, `D` downsampled and `T` thumbnails
+    pub sample_type: char,
+    pub site: u32,
+    pub drive: u32,
+    /// e.g. `NCAM00200`
+    pub sequence: String,
+    /// The producer, `M` for the mission's operations pipeline
+    pub venue: char,
+    pub version: char,
+}
+
+impl MslEcamProductId {
+    /// Parses a product id or file name, with or without its extension
+    pub fn from_string(s: &str) -> Result<Self, Error> {
+        let name = base_name(s);
+        match MSL_ECAM.captures(&name) {
+            Some(caps) => Ok(MslEcamProductId {
+                camera: capture_char(&caps, 1),
+                eye: capture_char(&caps, 2),
+                config: capture_char(&caps, 3),
+                special: capture_char(&caps, 4),
+                sclk: caps[5].parse::<u64>().unwrap_or(0),
+                product_type: caps[6].to_owned(),
+                geometry: capture_char(&caps, 7),
+                sample_type: capture_char(&caps, 8),
+                site: counter(&caps, 9, "site")?,
+                drive: counter(&caps, 10, "drive")?,
+                sequence: caps[11].to_owned(),
+                venue: capture_char(&caps, 12),
+                version: capture_char(&caps, 13),
+            }),
+            None => Err(Error::Decode(format!(
+                "Not an MSL engineering camera product id: {}",
+                s
+            ))),
+        }
+    }
+
+    /// Builds the product id. Fails if the site or drive is too large for its field.
+    pub fn to_product_id(&self) -> Result<String, Error> {
+        match (encode_counter(self.site, 3), encode_counter(self.drive, 4)) {
+            (Some(site), Some(drive)) => Ok(format!(
+                "{}{}{}{}{:09}{}{}{}{}{}{}{}{}",
+                self.camera,
+                self.eye,
+                self.config,
+                self.special,
+                self.sclk,
+                self.product_type,
+                self.geometry,
+                self.sample_type,
+                site,
+                drive,
+                self.sequence,
+                self.venue,
+                self.version
+            )),
+            _ => Err(Error::Encode(format!(
+                "Site {} or drive {} out of range",
+                self.site, self.drive
+            ))),
+        }
+    }
+
+    /// The INSTRUMENT_ID of the camera, e.g. `NAV_RIGHT_B`
+    pub fn instrument_id(&self) -> String {
+        let camera = match self.camera {
+            'N' => "NAV",
+            'F' => "FHAZ",
+            _ => "RHAZ",
+        };
+        format!("{}_{}_{}", camera, eye_name(self.eye), self.config)
+    }
+
+    /// Compares the fields of the product id with the metadata of its label
+    pub fn check(&self, metadata: &ObservationMetadata) -> Vec<Mismatch> {
+        let mut mismatches = vec![];
+        if let Ok(id) = self.to_product_id() {
+            compare(
+                &mut mismatches,
+                "PRODUCT_ID",
+                &id,
+                metadata.product_id.as_deref(),
+            );
+        }
+        compare(
+            &mut mismatches,
+            "INSTRUMENT_HOST_ID",
+            "MSL",
+            metadata.spacecraft.as_deref(),
+        );
+        compare(
+            &mut mismatches,
+            "INSTRUMENT_ID",
+            &self.instrument_id(),
+            Some(&metadata.instrument_id),
+        );
+        compare_rover(
+            &mut mismatches,
+            metadata,
+            self.sclk,
+            self.site,
+            self.drive,
+            self.eye,
+            &self.sequence,
+        );
+        mismatches
+    }
+}
+
+/// The product id of a MER camera product, e.g. `1N579700548FFLD2FCP1981L0M1`. File names
+/// carry the same id in lower case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerProductId {
+    /// 1 for MER-1 (Opportunity), 2 for MER-2 (Spirit)
+    pub spacecraft: u8,
+    /// `F` or `R` for the front or rear Hazcams, `N` Navcam, `P` Pancam, `M` Microscopic
+    /// Imager and `E` the descent camera
+    pub camera: char,
+    pub sclk: u64,
+    /// e.g. `EFF`, `FFL` or `RSD`
+    pub product_type: String,
+    pub site: u32,
+    pub drive: u32,
+    /// e.g. `P1981`
+    pub sequence: String,
+    /// `L` or `R`, or `M` for mono cameras
+    pub eye: char,
+    /// The filter position, `0` for cameras without filters
+    pub filter: char,
+    /// The producer, `M` for the mission's operations pipeline
+    pub producer: char,
+    pub version: char,
+}
+
+impl MerProductId {
+    /// Parses a product id or file name, with or without its extension
+    pub fn from_string(s: &str) -> Result<Self, Error> {
+        let name = base_name(s);
+        match MER.captures(&name) {
+            Some(caps) => Ok(MerProductId {
+                spacecraft: caps[1].parse::<u8>().unwrap_or(0),
+                camera: capture_char(&caps, 2),
+                sclk: caps[3].parse::<u64>().unwrap_or(0),
+                product_type: caps[4].to_owned(),
+                site: counter(&caps, 5, "site")?,
+                drive: counter(&caps, 6, "drive")?,
+                sequence: caps[7].to_owned(),
+                eye: capture_char(&caps, 8),
+                filter: capture_char(&caps, 9),
+                producer: capture_char(&caps, 10),
+                version: capture_char(&caps, 11),
+            }),
+            None => Err(Error::Decode(format!("Not a MER camera product id: {}", s))),
+        }
+    }
+
+    /// Builds the product id, in upper case as given by PRODUCT_ID. Fails if the site or drive
+    /// is too large for its field.
+    pub fn to_product_id(&self) -> Result<String, Error> {
+        match (encode_counter(self.site, 2), encode_counter(self.drive, 2)) {
+            (Some(site), Some(drive)) => Ok(format!(
+                "{}{}{:09}{}{}{}{}{}{}{}{}",
+                self.spacecraft,
+                self.camera,
+                self.sclk,
+                self.product_type,
+                site,
+                drive,
+                self.sequence,
+                self.eye,
+                self.filter,
+                self.producer,
+                self.version
+            )),
+            _ => Err(Error::Encode(format!(
+                "Site {} or drive {} out of range",
+                self.site, self.drive
+            ))),
+        }
+    }
+
+    /// The file name of the product, without extension
+    pub fn to_file_name(&self) -> Result<String, Error> {
+        Ok(self.to_product_id()?.to_lowercase())
+    }
+
+    /// The INSTRUMENT_ID of the camera, e.g. `NAVCAM_LEFT` or `MI`
+    pub fn instrument_id(&self) -> String {
+        let camera = match self.camera {
+            'F' => "FRONT_HAZCAM",
+            'R' => "REAR_HAZCAM",
+            'N' => "NAVCAM",
+            'P' => "PANCAM",
+            'M' => "MI",
+            _ => "DESCENT_CAMERA",
+        };
+        match self.eye {
+            'L' | 'R' => format!("{}_{}", camera, eye_name(self.eye)),
+            _ => camera.to_owned(),
+        }
+    }
+
+    /// Compares the fields of the product id with the metadata of its label. The filter is
+    /// only compared for the Pancams and the Microscopic Imager, the engineering camera labels
+    /// giving the filter state of another instrument.
+    pub fn check(&self, metadata: &ObservationMetadata) -> Vec<Mismatch> {
+        let mut mismatches = vec![];
+        if let Ok(id) = self.to_product_id() {
+            compare(
+                &mut mismatches,
+                "PRODUCT_ID",
+                &id,
+                metadata.product_id.as_deref(),
+            );
+        }
+        compare(
+            &mut mismatches,
+            "INSTRUMENT_HOST_ID",
+            &format!("MER{}", self.spacecraft),
+            metadata.spacecraft.as_deref(),
+        );
+        compare(
+            &mut mismatches,
+            "INSTRUMENT_ID",
+            &self.instrument_id(),
+            Some(&metadata.instrument_id),
+        );
+        let extras = compare_rover(
+            &mut mismatches,
+            metadata,
+            self.sclk,
+            self.site,
+            self.drive,
+            self.eye,
+            &self.sequence,
+        );
+        if let (Some(extras), 'P' | 'M') = (extras, self.camera) {
+            compare(
+                &mut mismatches,
+                "FILTER_NUMBER",
+                &self.filter.to_string(),
+                extras.filter_number.as_deref(),
+            );
+        }
+        mismatches
+    }
+}
+
+/// The file name of a path, less any extensions, in upper case
+fn base_name(s: &str) -> String {
+    let name = s.trim().rsplit(['/', '\\']).next().unwrap_or("");
+    name.split('.').next().unwrap_or("").to_uppercase()
+}
+
+/// A decoded product id of one of the supported missions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProductId {
+    MslEcam(MslEcamProductId),
+    Mer(MerProductId),
+}
+
+impl ProductId {
+    /// Decodes a product id or file name of any of the supported formats
+    /// # Example
+    /// ```
+    /// use vicar::filename::ProductId;
+    ///
+    /// let id = ProductId::from_string("1n579700548ffld2fcp1981l0m1.img").unwrap();
+    /// assert_eq!(id.sclk(), 579700548);
+    /// assert_eq!(id.site(), 210);
+    /// assert_eq!(id.to_product_id().unwrap(), "1N579700548FFLD2FCP1981L0M1");
+    /// ```
+    pub fn from_string(s: &str) -> Result<Self, Error> {
+        if let Ok(id) = MslEcamProductId::from_string(s) {
+            Ok(ProductId::MslEcam(id))
+        } else if let Ok(id) = MerProductId::from_string(s) {
+            Ok(ProductId::Mer(id))
+        } else {
+            Err(Error::Decode(format!("Unrecognized product id: {}", s)))
+        }
+    }
+
+    pub fn sclk(&self) -> u64 {
+        match self {
+            ProductId::MslEcam(id) => id.sclk,
+            ProductId::Mer(id) => id.sclk,
+        }
+    }
+
+    pub fn site(&self) -> u32 {
+        match self {
+            ProductId::MslEcam(id) => id.site,
+            ProductId::Mer(id) => id.site,
+        }
+    }
+
+    pub fn drive(&self) -> u32 {
+        match self {
+            ProductId::MslEcam(id) => id.drive,
+            ProductId::Mer(id) => id.drive,
+        }
+    }
+
+    pub fn product_type(&self) -> &str {
+        match self {
+            ProductId::MslEcam(id) => &id.product_type,
+            ProductId::Mer(id) => &id.product_type,
+        }
+    }
+
+    pub fn instrument_id(&self) -> String {
+        match self {
+            ProductId::MslEcam(id) => id.instrument_id(),
+            ProductId::Mer(id) => id.instrument_id(),
+        }
+    }
+
+    pub fn to_product_id(&self) -> Result<String, Error> {
+        match self {
+            ProductId::MslEcam(id) => id.to_product_id(),
+            ProductId::Mer(id) => id.to_product_id(),
+        }
+    }
+
+    /// Compares the fields of the product id with the metadata of its label
+    pub fn check(&self, metadata: &ObservationMetadata) -> Vec<Mismatch> {
+        match self {
+            ProductId::MslEcam(id) => id.check(metadata),
+            ProductId::Mer(id) => id.check(metadata),
+        }
+    }
+}
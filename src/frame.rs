@@ -0,0 +1,330 @@
+//! Coordinate frames described by the *_COORD_SYSTEM_PARMS groups of MSL labels, the
+//! *_COORDINATE_SYSTEM groups of MER labels and the matching VICAR properties. Each frame gives
+//! its origin and orientation within a reference frame, and together they form a graph through
+//! which points and camera models can be moved between frames.
+use crate::camera::{add, CameraModel, Vector3};
+use crate::label::Label;
+use crate::pvl::{self, FromValue, KeyValuePair, Value, ValueType};
+use std::fmt;
+
+/// Errors reading frames or moving between them
+#[derive(Debug)]
+pub enum Error {
+    /// The label couldn't be read, or a value of a frame converted
+    Label(String),
+    /// A frame is malformed, or frames aren't connected
+    Frame(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Label(s) => write!(f, "Coordinate frame label error: {}", s),
+            Error::Frame(s) => write!(f, "Coordinate frame error: {}", s),
+        }
+    }
+}
+
+impl From<pvl::Error> for Error {
+    fn from(value: pvl::Error) -> Self {
+        Error::Label(t!(value))
+    }
+}
+
+/// A rotation as a unit quaternion, scalar first: (w, x, y, z)
+pub type Quaternion = [f64; 4];
+
+/// The values of a COORDINATE_SYSTEM_INDEX, which may be a single site number or a tuple of
+/// motion counters
+pub(crate) fn frame_index(value: &Value) -> Result<Vec<i64>, pvl::Error> {
+    if value.value_type() == ValueType::Array {
+        Vec::from_value(value)
+    } else {
+        Ok(vec![i64::from_value(value)?])
+    }
+}
+
+fn multiply(a: &Quaternion, b: &Quaternion) -> Quaternion {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+fn conjugate(q: &Quaternion) -> Quaternion {
+    [q[0], -q[1], -q[2], -q[3]]
+}
+
+/// Identifies a frame by name and index. Frames are matched on their name and site, the first
+/// index value; the other motion counters advance with mechanisms which don't move every frame,
+/// so aren't compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameId {
+    pub name: String,
+    pub index: Vec<i64>,
+}
+
+impl FrameId {
+    pub fn new(name: &str, index: &[i64]) -> Self {
+        FrameId {
+            name: name.to_owned(),
+            index: index.to_vec(),
+        }
+    }
+
+    /// Determines if two ids refer to the same frame. An id without an index matches any frame
+    /// of that name.
+    pub fn matches(&self, other: &FrameId) -> bool {
+        self.name == other.name
+            && match (self.index.first(), other.index.first()) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+    }
+}
+
+/// A rigid transform taking points from one frame into another: rotation, then offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub rotation: Quaternion,
+    pub offset: Vector3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            rotation: [1.0, 0.0, 0.0, 0.0],
+            offset: [0.0, 0.0, 0.0],
+        }
+    }
+
+    /// Rotates a direction, leaving it unaffected by the offset
+    pub fn rotate(&self, vector: &Vector3) -> Vector3 {
+        let v = [0.0, vector[0], vector[1], vector[2]];
+        let r = multiply(&multiply(&self.rotation, &v), &conjugate(&self.rotation));
+        [r[1], r[2], r[3]]
+    }
+
+    pub fn apply(&self, point: &Vector3) -> Vector3 {
+        add(&self.rotate(point), &self.offset)
+    }
+
+    /// The transform in the opposite direction
+    pub fn inverse(&self) -> Transform {
+        let inverse = Transform {
+            rotation: conjugate(&self.rotation),
+            offset: [0.0, 0.0, 0.0],
+        };
+        let offset = inverse.rotate(&self.offset);
+        Transform {
+            offset: [-offset[0], -offset[1], -offset[2]],
+            ..inverse
+        }
+    }
+
+    /// This transform followed by another
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            rotation: multiply(&next.rotation, &self.rotation),
+            offset: next.apply(&self.offset),
+        }
+    }
+}
+
+/// A frame and its placement within its reference frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinateFrame {
+    pub id: FrameId,
+    pub reference: FrameId,
+    /// Takes points in this frame into the reference frame
+    pub to_reference: Transform,
+    /// The label group or VICAR property the frame was read from
+    pub block: String,
+}
+
+impl CoordinateFrame {
+    /// Reads a frame from the properties of one label block. Returns None for blocks which don't
+    /// describe a frame.
+    pub fn from_properties(
+        block: &str,
+        properties: &[&KeyValuePair],
+    ) -> Result<Option<CoordinateFrame>, Error> {
+        let find = |key: &str| {
+            properties
+                .iter()
+                .find(|kvp| kvp.key.is_key(key))
+                .map(|kvp| &kvp.value)
+        };
+        let (name, offset, rotation) = match (
+            find("COORDINATE_SYSTEM_NAME"),
+            find("ORIGIN_OFFSET_VECTOR"),
+            find("ORIGIN_ROTATION_QUATERNION"),
+        ) {
+            (Some(n), Some(o), Some(r)) => (n, o, r),
+            _ => return Ok(None),
+        };
+        let index = match find("COORDINATE_SYSTEM_INDEX") {
+            Some(v) => frame_index(v)?,
+            None => vec![],
+        };
+        let reference = match find("REFERENCE_COORD_SYSTEM_NAME") {
+            Some(v) => FrameId {
+                name: String::from_value(v)?,
+                index: match find("REFERENCE_COORD_SYSTEM_INDEX") {
+                    Some(v) => frame_index(v)?,
+                    None => vec![],
+                },
+            },
+            None => {
+                return Err(Error::Frame(format!(
+                    "{} has no REFERENCE_COORD_SYSTEM_NAME",
+                    block
+                )))
+            }
+        };
+
+        let offset: Vec<f64> = Vec::from_value(offset)?;
+        let rotation: Vec<f64> = Vec::from_value(rotation)?;
+        match (&offset[..], &rotation[..]) {
+            ([x, y, z], [w, i, j, k]) => {
+                let norm = (w * w + i * i + j * j + k * k).sqrt();
+                Ok(Some(CoordinateFrame {
+                    id: FrameId {
+                        name: String::from_value(name)?,
+                        index,
+                    },
+                    reference,
+                    to_reference: Transform {
+                        rotation: [w / norm, i / norm, j / norm, k / norm],
+                        offset: [*x, *y, *z],
+                    },
+                    block: block.to_owned(),
+                }))
+            }
+            _ => Err(Error::Frame(format!(
+                "{} has an invalid origin offset or rotation",
+                block
+            ))),
+        }
+    }
+}
+
+/// The frames described by a label, linked by their references
+#[derive(Debug, Clone, Default)]
+pub struct FrameGraph {
+    pub frames: Vec<CoordinateFrame>,
+}
+
+impl FrameGraph {
+    pub fn new() -> Self {
+        FrameGraph { frames: vec![] }
+    }
+
+    /// Reads every frame described by a PDS3 or VICAR label
+    /// # Example
+    /// ```
+    /// use vicar::frame::{FrameGraph, FrameId};
+    /// use vicar::pvl::Pvl;
+    ///
+    /// let p = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+    /// let frames = FrameGraph::from_label(&Pvl::load(std::path::Path::new(p)).unwrap()).unwrap();
+    /// let rover = FrameId::new("ROVER_NAV_FRAME", &[93]);
+    /// let site = FrameId::new("SITE_FRAME", &[93]);
+    /// let origin = frames.transform_point(&rover, &site, &[0.0, 0.0, 0.0]).unwrap();
+    /// assert_eq!(origin, [151.271, -156.017, 22.3253]);
+    /// ```
+    pub fn from_label<L: Label>(label: &L) -> Result<FrameGraph, Error> {
+        let mut graph = FrameGraph::new();
+        for (path, properties) in label.blocks().iter() {
+            if let Some(frame) = CoordinateFrame::from_properties(path, properties)? {
+                graph.add(frame);
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Adds a frame, replacing any previous frame with the same id
+    pub fn add(&mut self, frame: CoordinateFrame) {
+        self.frames.retain(|f| f.id != frame.id);
+        self.frames.push(frame);
+    }
+
+    pub fn find(&self, id: &FrameId) -> Option<&CoordinateFrame> {
+        self.frames.iter().find(|f| f.id.matches(id))
+    }
+
+    /// The frames from the given one through each reference in turn, ending at one whose
+    /// reference isn't in the graph
+    pub fn chain(&self, id: &FrameId) -> Vec<&CoordinateFrame> {
+        let mut chain: Vec<&CoordinateFrame> = vec![];
+        let mut next = self.find(id);
+        while let Some(frame) = next {
+            if chain.iter().any(|f| f.id == frame.id) {
+                break;
+            }
+            chain.push(frame);
+            next = self.find(&frame.reference);
+        }
+        chain
+    }
+
+    /// Follows references from a frame to the root of its chain, returning the root and the
+    /// transform into it
+    fn to_root(&self, id: &FrameId) -> (FrameId, Transform) {
+        let chain = self.chain(id);
+        let transform = chain
+            .iter()
+            .fold(Transform::identity(), |t, f| t.then(&f.to_reference));
+        match chain.last() {
+            Some(f) => (f.reference.clone(), transform),
+            None => (id.clone(), transform),
+        }
+    }
+
+    /// The transform taking points in one frame into another. Fails if they're not connected.
+    pub fn transform(&self, from: &FrameId, to: &FrameId) -> Result<Transform, Error> {
+        let (from_root, from_transform) = self.to_root(from);
+        let (to_root, to_transform) = self.to_root(to);
+        if from_root.matches(&to_root) {
+            Ok(from_transform.then(&to_transform.inverse()))
+        } else {
+            Err(Error::Frame(format!(
+                "No path from {} {:?} to {} {:?}",
+                from.name, from.index, to.name, to.index
+            )))
+        }
+    }
+
+    pub fn transform_point(
+        &self,
+        from: &FrameId,
+        to: &FrameId,
+        point: &Vector3,
+    ) -> Result<Vector3, Error> {
+        Ok(self.transform(from, to)?.apply(point))
+    }
+
+    /// Moves a camera model into another frame. Fails if the model doesn't name its frame.
+    pub fn transform_camera(
+        &self,
+        camera: &CameraModel,
+        to: &FrameId,
+    ) -> Result<CameraModel, Error> {
+        match camera.frame() {
+            Some(from) => Ok(camera.transformed(&self.transform(&from, to)?, to)),
+            None => Err(Error::Frame(
+                "Camera model has no reference frame".to_owned(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! The label model shared by PDS3/PVL and VICAR labels. Both are made of the same `Value`s and
+//! `KeyValuePair`s, so values are typed, queried and serialized the same way regardless of
+//! which kind of label they came from.
+use crate::pvl::{self, Pvl};
+use crate::vicar::{VicarError, VicarLabel};
+
+pub use crate::pvl::{FromValue, KeyValuePair, QueryPath, Symbol, Value, ValueType};
+
+/// Access common to every kind of label, for code which doesn't care where a keyword came from
+/// # Example
+/// ```
+/// use vicar::label::Label;
+/// use vicar::pvl::Pvl;
+/// use vicar::vicar::VicarLabel;
+///
+/// fn exposure<L: Label>(label: &L) -> Result<f64, L::Error> {
+///     label.query_one("**/EXPOSURE_DURATION")
+/// }
+///
+/// let pvl = Pvl::from_string(
+///     "GROUP = INSTRUMENT_STATE_PARMS\n  EXPOSURE_DURATION = 12.5 <ms>\nEND_GROUP = INSTRUMENT_STATE_PARMS\nEND\n",
+/// )
+/// .unwrap();
+/// let vic = VicarLabel::from_string(
+///     "LBLSIZE=64  PROPERTY='INSTRUMENT_STATE_PARMS'  EXPOSURE_DURATION=12.5",
+/// )
+/// .unwrap();
+/// assert_eq!(exposure(&pvl).unwrap(), exposure(&vic).unwrap());
+/// ```
+pub trait Label {
+    type Error: std::error::Error + From<pvl::Error>;
+
+    /// Retrieves and converts every value matching a query path, in label order. See
+    /// `QueryPath` for the path syntax.
+    fn query<T: FromValue>(&self, path: &str) -> Result<Vec<T>, Self::Error>;
+
+    /// Retrieves and converts the first value matching a query path. Fails if there is no match.
+    fn query_one<T: FromValue>(&self, path: &str) -> Result<T, Self::Error>;
+
+    /// Every block of properties, along with its path, e.g. `IMAGE/STATISTICS` or the name of a
+    /// VICAR label section. Top level properties and VICAR system items have an empty path.
+    /// Blocks which share a name, such as repeated groups or history tasks, are kept apart.
+    /// VICAR sections are given in label order. PVL labels are held as properties, groups and
+    /// objects apart, so give the top level properties, then each group, then each object
+    /// along with the blocks nested in it, each in label order.
+    fn blocks(&self) -> Vec<(String, Vec<&KeyValuePair>)>;
+
+    /// Every property, along with the path of the block it's in, in the order of `blocks`
+    fn entries(&self) -> Vec<(String, &KeyValuePair)> {
+        self.blocks()
+            .into_iter()
+            .flat_map(|(path, properties)| {
+                properties
+                    .into_iter()
+                    .map(move |kvp| (path.to_owned(), kvp))
+            })
+            .collect()
+    }
+}
+
+impl Label for Pvl {
+    type Error = pvl::Error;
+
+    fn query<T: FromValue>(&self, path: &str) -> Result<Vec<T>, pvl::Error> {
+        Pvl::query(self, path)
+    }
+
+    fn query_one<T: FromValue>(&self, path: &str) -> Result<T, pvl::Error> {
+        Pvl::query_one(self, path)
+    }
+
+    fn blocks(&self) -> Vec<(String, Vec<&KeyValuePair>)> {
+        let mut blocks: Vec<(String, Vec<&KeyValuePair>)> = vec![];
+        push_block(&mut blocks, "", &self.properties);
+        self.groups
+            .iter()
+            .for_each(|g| push_block(&mut blocks, &g.name, &g.properties));
+        self.objects
+            .iter()
+            .for_each(|o| object_blocks(o, &o.name, &mut blocks));
+        blocks
+    }
+}
+
+/// Adds a block, unless it has no properties
+fn push_block<'a>(
+    blocks: &mut Vec<(String, Vec<&'a KeyValuePair>)>,
+    path: &str,
+    properties: &'a [KeyValuePair],
+) {
+    if !properties.is_empty() {
+        blocks.push((path.to_owned(), properties.iter().collect()));
+    }
+}
+
+/// Collects the blocks of an object and everything nested within it
+fn object_blocks<'a>(
+    object: &'a pvl::Object,
+    path: &str,
+    blocks: &mut Vec<(String, Vec<&'a KeyValuePair>)>,
+) {
+    push_block(blocks, path, &object.properties);
+    object
+        .groups
+        .iter()
+        .for_each(|g| push_block(blocks, &format!("{}/{}", path, g.name), &g.properties));
+    object
+        .objects
+        .iter()
+        .for_each(|o| object_blocks(o, &format!("{}/{}", path, o.name), blocks));
+}
+
+impl Label for VicarLabel {
+    type Error = VicarError;
+
+    fn query<T: FromValue>(&self, path: &str) -> Result<Vec<T>, VicarError> {
+        VicarLabel::query(self, path)
+    }
+
+    fn query_one<T: FromValue>(&self, path: &str) -> Result<T, VicarError> {
+        VicarLabel::query_one(self, path)
+    }
+
+    fn blocks(&self) -> Vec<(String, Vec<&KeyValuePair>)> {
+        let mut blocks: Vec<(String, Vec<&KeyValuePair>)> = vec![];
+        push_block(&mut blocks, "", &self.system);
+        self.properties
+            .iter()
+            .chain(self.history.iter())
+            .for_each(|s| push_block(&mut blocks, &s.name, &s.items));
+        blocks
+    }
+}
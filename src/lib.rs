@@ -8,5 +8,18 @@ macro_rules! t {
     };
 }
 
+pub mod articulation;
+pub mod camera;
+pub mod companding;
+pub mod filename;
+pub mod frame;
+pub mod label;
+pub mod mask;
+pub mod metadata;
+pub mod observation;
 pub mod pvl;
+pub mod radiometry;
+pub mod statistics;
+pub mod time;
+pub mod units;
 pub mod vicar;
@@ -0,0 +1,170 @@
+//! Classification of pixels by the special values and valid ranges labels define: the CORE_NULL
+//! and saturation codes of qubes, MISSING_CONSTANT and INVALID_CONSTANT of PDS3 images,
+//! VALID_MINIMUM and VALID_MAXIMUM, and the MISSING_LINES of Cassini images.
+use crate::label::Label;
+use crate::pvl::{FromValue, Value, ValueType};
+
+/// The validity of one pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelClass {
+    Valid,
+    /// No value, or an invalid one
+    Null,
+    LowSaturation,
+    HighSaturation,
+    /// Lost in transmission
+    Missing,
+}
+
+/// The special values of a product, compared against values as stored, before linearizing or
+/// scaling
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SpecialValues {
+    pub null: Vec<f64>,
+    pub missing: Vec<f64>,
+    pub low_saturation: Vec<f64>,
+    pub high_saturation: Vec<f64>,
+    /// Values below are low saturated
+    pub valid_minimum: Option<f64>,
+    /// Values above are high saturated
+    pub valid_maximum: Option<f64>,
+    /// Values at or above are high saturated. Cassini ISS labels give the full well and
+    /// converter saturation levels as a pair of VALID_MAXIMUM values; the smaller applies.
+    pub saturation_level: Option<f64>,
+    /// The number of lines lost in transmission, which Cassini fills with zeros
+    pub missing_lines: usize,
+}
+
+/// The first of the paths with a numeric value
+fn number<L: Label>(label: &L, key: &str) -> Option<f64> {
+    [format!("IMAGE/{}", key), format!("**/{}", key)]
+        .iter()
+        .find_map(|p| label.query_one::<f64>(p).ok())
+}
+
+impl SpecialValues {
+    /// Reads the special values of a PDS3 or VICAR label. Keywords of the IMAGE object are
+    /// preferred to those elsewhere in the label.
+    /// # Example
+    /// ```
+    /// use vicar::mask::{PixelClass, SpecialValues};
+    /// use vicar::pvl::Pvl;
+    ///
+    /// let p = "tests/testdata/cassini/vims/v1883935188_1.lbl";
+    /// let special = SpecialValues::from_label(&Pvl::load(std::path::Path::new(p)).unwrap());
+    /// assert_eq!(special.classify(-8192.0), PixelClass::Null);
+    /// assert_eq!(special.classify(-32765.0), PixelClass::HighSaturation);
+    /// assert_eq!(special.classify(100.0), PixelClass::Valid);
+    /// ```
+    pub fn from_label<L: Label>(label: &L) -> SpecialValues {
+        let numbers =
+            |keys: &[&str]| -> Vec<f64> { keys.iter().filter_map(|k| number(label, k)).collect() };
+
+        let (valid_maximum, saturation_level) = match ["IMAGE/VALID_MAXIMUM", "**/VALID_MAXIMUM"]
+            .iter()
+            .find_map(|p| label.query_one::<Value>(p).ok())
+        {
+            Some(v) if v.value_type() == ValueType::Array => (
+                None,
+                Vec::<f64>::from_value(&v)
+                    .ok()
+                    .and_then(|m| m.into_iter().reduce(f64::min)),
+            ),
+            Some(v) => (f64::from_value(&v).ok(), None),
+            None => (None, None),
+        };
+
+        SpecialValues {
+            null: numbers(&["CORE_NULL", "INVALID_CONSTANT"]),
+            missing: numbers(&["MISSING_CONSTANT"]),
+            low_saturation: numbers(&["CORE_LOW_REPR_SATURATION", "CORE_LOW_INSTR_SATURATION"]),
+            high_saturation: numbers(&["CORE_HIGH_REPR_SATURATION", "CORE_HIGH_INSTR_SATURATION"]),
+            valid_minimum: numbers(&["CORE_VALID_MINIMUM", "VALID_MINIMUM"])
+                .first()
+                .copied(),
+            valid_maximum,
+            saturation_level,
+            missing_lines: number(label, "MISSING_LINES").map_or(0, |n| n.max(0.0) as usize),
+        }
+    }
+
+    /// Classifies a value as stored. Special values take precedence over the valid range, and
+    /// missing over invalid where a label gives the same value for both.
+    pub fn classify(&self, value: f64) -> PixelClass {
+        self.classify_linearized(value, value)
+    }
+
+    /// Classifies a value of a companded product, matching special values against the value
+    /// as stored and the valid range against its linear value, as Cassini gives its saturation
+    /// levels in 12 bit values
+    pub fn classify_linearized(&self, stored: f64, linear: f64) -> PixelClass {
+        if stored.is_nan() {
+            PixelClass::Null
+        } else if self.missing.contains(&stored) {
+            PixelClass::Missing
+        } else if self.null.contains(&stored) {
+            PixelClass::Null
+        } else if self.low_saturation.contains(&stored) {
+            PixelClass::LowSaturation
+        } else if self.high_saturation.contains(&stored) {
+            PixelClass::HighSaturation
+        } else if self.valid_minimum.is_some_and(|m| linear < m) {
+            PixelClass::LowSaturation
+        } else if self.valid_maximum.is_some_and(|m| linear > m)
+            || self.saturation_level.is_some_and(|m| linear >= m)
+        {
+            PixelClass::HighSaturation
+        } else {
+            PixelClass::Valid
+        }
+    }
+
+    /// Marks lines lost in transmission as missing, given a band's values as stored and their
+    /// classes. Lost lines are those entirely zero, up to the number the label gives.
+    pub fn mark_missing_lines(&self, values: &[f64], mask: &mut [PixelClass], samples: usize) {
+        if self.missing_lines == 0 || samples == 0 {
+            return;
+        }
+        let mut remaining = self.missing_lines;
+        for (line, chunk) in values.chunks(samples).enumerate().rev() {
+            if remaining == 0 {
+                break;
+            }
+            if chunk.iter().all(|v| *v == 0.0) {
+                mask[line * samples..(line + 1) * samples]
+                    .iter_mut()
+                    .for_each(|c| *c = PixelClass::Missing);
+                remaining -= 1;
+            }
+        }
+    }
+}
+
+/// The values of one band, line by line, along with the class of each
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaskedBand {
+    pub values: Vec<f64>,
+    pub mask: Vec<PixelClass>,
+    pub lines: usize,
+    pub samples: usize,
+}
+
+impl MaskedBand {
+    pub fn class(&self, line: usize, sample: usize) -> Option<PixelClass> {
+        self.mask.get(line * self.samples + sample).copied()
+    }
+
+    /// The values of valid pixels
+    pub fn valid_values(&self) -> impl Iterator<Item = f64> + '_ {
+        self.values
+            .iter()
+            .zip(self.mask.iter())
+            .filter(|(_, c)| **c == PixelClass::Valid)
+            .map(|(v, _)| *v)
+    }
+
+    /// The number of pixels of a class
+    pub fn count(&self, class: PixelClass) -> usize {
+        self.mask.iter().filter(|c| **c == class).count()
+    }
+}
@@ -0,0 +1,330 @@
+//! A merged view of the metadata of one product, combining its attached and detached PDS3
+//! labels and its VICAR label. Each keyword takes its value from the highest precedence source
+//! that has it, and values given differently by other sources are reported as conflicts.
+use crate::label::Label;
+use crate::pvl::{Error, FromValue, KeyValuePair, Pvl, Value, ValueType};
+use crate::vicar::{VicarLabel, VicarReader};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The label a metadata value was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// A PDS3 label at the start of the product file
+    AttachedPds,
+    /// A PDS3 label in its own `.LBL` file
+    DetachedPds,
+    /// The system items of a VICAR label, LBLSIZE through to the first section
+    VicarSystem,
+    /// A PROPERTY section of a VICAR label
+    VicarProperty,
+    /// A history TASK section of a VICAR label
+    VicarHistory,
+}
+
+/// How the sources of a product are combined
+#[derive(Debug, Clone)]
+pub struct MergeOptions {
+    /// Sources in order of precedence, highest first. Sources not listed are left out.
+    pub precedence: Vec<Source>,
+    /// VICAR PROPERTY sections whose items correspond to the top level of a PDS3 label
+    pub top_level_sections: Vec<String>,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        MergeOptions {
+            precedence: vec![
+                Source::DetachedPds,
+                Source::AttachedPds,
+                Source::VicarProperty,
+                Source::VicarSystem,
+                Source::VicarHistory,
+            ],
+            top_level_sections: vec!["IDENTIFICATION".to_owned()],
+        }
+    }
+}
+
+/// A value along with where it was found
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourcedValue {
+    pub value: Value,
+    pub source: Source,
+    /// Path of the block within its label, e.g. `IMAGE/STATISTICS` or a VICAR section name
+    pub path: String,
+}
+
+/// A keyword of the merged view. The block is the GROUP, OBJECT or VICAR section it belongs to,
+/// which is empty at the top level.
+#[derive(Debug, Clone)]
+pub struct MergedEntry {
+    pub block: String,
+    pub key: String,
+    /// Every value found for the keyword, highest precedence first
+    pub candidates: Vec<SourcedValue>,
+}
+
+impl MergedEntry {
+    /// The value from the highest precedence source
+    pub fn value(&self) -> &SourcedValue {
+        &self.candidates[0]
+    }
+
+    /// Values from lower precedence sources which disagree with the chosen value. Pointers
+    /// locate data relative to their own label, so aren't compared.
+    pub fn conflicts(&self) -> Vec<&SourcedValue> {
+        if self.key.starts_with('^') {
+            return vec![];
+        }
+        self.candidates[1..]
+            .iter()
+            .filter(|c| !agrees(&c.value, &self.candidates[0].value))
+            .collect()
+    }
+}
+
+/// Determines if two values are equivalent. VICAR labels carry no units and don't distinguish
+/// numbers and symbols from strings, so values are compared by their text where they differ in
+/// those respects.
+fn agrees(a: &Value, b: &Value) -> bool {
+    if a == b {
+        return true;
+    }
+    match (a.value_type(), b.value_type()) {
+        (ValueType::Array, ValueType::Array) => match (a.parse_array(), b.parse_array()) {
+            (Ok(x), Ok(y)) => {
+                x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| agrees(x, y))
+            }
+            _ => false,
+        },
+        _ if a.units().is_some() && b.units().is_some() => false,
+        _ => {
+            let (x, y) = (a.unquoted(), b.unquoted());
+            match (x.trim().parse::<f64>(), y.trim().parse::<f64>()) {
+                (Ok(x), Ok(y)) => x == y,
+                _ => x == y,
+            }
+        }
+    }
+}
+
+/// The merged metadata of one product
+#[derive(Debug, Clone, Default)]
+pub struct ProductMetadata {
+    pub options: MergeOptions,
+    /// The sources which have been added
+    pub sources: Vec<Source>,
+    entries: Vec<MergedEntry>,
+    lookup: HashMap<(String, String), usize>,
+}
+
+impl ProductMetadata {
+    pub fn new(options: MergeOptions) -> Self {
+        ProductMetadata {
+            options,
+            sources: vec![],
+            entries: vec![],
+            lookup: HashMap::new(),
+        }
+    }
+
+    /// Loads every label found for a product with the default options. See `load_with_options`
+    /// # Example
+    /// ```
+    /// use vicar::metadata::{ProductMetadata, Source};
+    ///
+    /// let p = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG";
+    /// let metadata = ProductMetadata::load(p).unwrap();
+    /// let exposure = metadata.get("INSTRUMENT_STATE_PARMS/EXPOSURE_DURATION").unwrap();
+    /// assert_eq!(exposure.source, Source::DetachedPds);
+    /// assert_eq!(metadata.get("LBLSIZE").unwrap().source, Source::VicarSystem);
+    /// ```
+    pub fn load<S: AsRef<Path> + ?Sized>(file_path: &S) -> Result<Self, Error> {
+        ProductMetadata::load_with_options(file_path, MergeOptions::default())
+    }
+
+    /// Loads every label found for a product: a PDS3 label attached to the start of the file, a
+    /// VICAR label within it, and a detached label of the same name with a `.LBL` extension.
+    /// The path may be either the product file or its detached label. Fails if none are found.
+    pub fn load_with_options<S: AsRef<Path> + ?Sized>(
+        file_path: &S,
+        options: MergeOptions,
+    ) -> Result<Self, Error> {
+        let mut metadata = ProductMetadata::new(options);
+        for path in product_files(file_path.as_ref()) {
+            let is_detached = path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("LBL"));
+            if is_detached {
+                metadata.add_pvl(&Pvl::load(&path)?, Source::DetachedPds);
+                continue;
+            }
+
+            let text = match fs::read(&path) {
+                Ok(b) => match String::from_utf8_lossy(&b) {
+                    Cow::Borrowed(s) => s.to_owned(),
+                    Cow::Owned(s) => s,
+                },
+                Err(why) => return Err(Error::General(t!(why))),
+            };
+            if text.starts_with("PDS_VERSION_ID") || text.starts_with("ODL_VERSION_ID") {
+                // Parsing stops at the END statement, before the image data
+                metadata.add_pvl(&Pvl::from_string(&text)?, Source::AttachedPds);
+            }
+            if let Ok(label) = VicarReader::new(&path).and_then(|vr| vr.label()) {
+                metadata.add_vicar(&label);
+            }
+        }
+
+        if metadata.sources.is_empty() {
+            Err(Error::General(format!(
+                "No labels found for {:?}",
+                file_path.as_ref()
+            )))
+        } else {
+            Ok(metadata)
+        }
+    }
+
+    /// Adds the keywords of a PDS3 label, attached or detached
+    pub fn add_pvl(&mut self, pvl: &Pvl, source: Source) {
+        self.sources.push(source);
+        pvl.entries()
+            .into_iter()
+            .for_each(|(path, kvp)| self.add_entry(&path, kvp, source));
+    }
+
+    /// Adds the system items, property sections and history sections of a VICAR label
+    pub fn add_vicar(&mut self, label: &VicarLabel) {
+        self.sources.push(Source::VicarSystem);
+        label
+            .system
+            .iter()
+            .for_each(|kvp| self.add_entry("", kvp, Source::VicarSystem));
+        if !label.properties.is_empty() {
+            self.sources.push(Source::VicarProperty);
+        }
+        label.properties.iter().for_each(|s| {
+            s.items
+                .iter()
+                .for_each(|kvp| self.add_entry(&s.name, kvp, Source::VicarProperty))
+        });
+        if !label.history.is_empty() {
+            self.sources.push(Source::VicarHistory);
+        }
+        label.history.iter().for_each(|s| {
+            s.items
+                .iter()
+                .for_each(|kvp| self.add_entry(&s.name, kvp, Source::VicarHistory))
+        });
+    }
+
+    fn add_entry(&mut self, path: &str, kvp: &KeyValuePair, source: Source) {
+        let rank = match self.options.precedence.iter().position(|s| *s == source) {
+            Some(r) => r,
+            None => return,
+        };
+        let key = match kvp.key.value() {
+            Some(k) => k,
+            None => return,
+        };
+        // Keywords are matched by the innermost block they're in
+        let block = match path.rsplit('/').next().unwrap_or_default() {
+            b if source == Source::VicarProperty
+                && self.options.top_level_sections.iter().any(|s| s == b) =>
+            {
+                ""
+            }
+            b => b,
+        };
+
+        let candidate = SourcedValue {
+            value: kvp.value.clone(),
+            source,
+            path: path.to_owned(),
+        };
+        let id = (block.to_owned(), key.to_owned());
+        match self.lookup.get(&id) {
+            Some(i) => {
+                let precedence = &self.options.precedence;
+                let candidates = &mut self.entries[*i].candidates;
+                let at = candidates
+                    .iter()
+                    .position(|c| precedence.iter().position(|s| *s == c.source) > Some(rank))
+                    .unwrap_or(candidates.len());
+                candidates.insert(at, candidate);
+            }
+            None => {
+                self.lookup.insert(id, self.entries.len());
+                self.entries.push(MergedEntry {
+                    block: block.to_owned(),
+                    key,
+                    candidates: vec![candidate],
+                });
+            }
+        }
+    }
+
+    /// Retrieves a keyword, given as `BLOCK/KEY` or just `KEY`. A bare key is looked for at the
+    /// top level first, then in any block.
+    pub fn get_entry(&self, path: &str) -> Option<&MergedEntry> {
+        match path.rsplit_once('/') {
+            Some((block, key)) => self
+                .lookup
+                .get(&(block.to_owned(), key.to_owned()))
+                .map(|i| &self.entries[*i]),
+            None => match self.lookup.get(&("".to_owned(), path.to_owned())) {
+                Some(i) => Some(&self.entries[*i]),
+                None => self.entries.iter().find(|e| e.key == path),
+            },
+        }
+    }
+
+    /// Retrieves the chosen value of a keyword. See `get_entry`
+    pub fn get(&self, path: &str) -> Option<&SourcedValue> {
+        self.get_entry(path).map(|e| e.value())
+    }
+
+    /// Retrieves and converts the chosen value of a keyword
+    pub fn get_as<T: FromValue>(&self, path: &str) -> Result<T, Error> {
+        match self.get(path) {
+            Some(v) => T::from_value(&v.value),
+            None => Err(Error::General(format!("No value for '{}'", path))),
+        }
+    }
+
+    /// Every keyword, in the order first found
+    pub fn entries(&self) -> &[MergedEntry] {
+        &self.entries
+    }
+
+    /// The keywords for which the sources disagree
+    pub fn conflicts(&self) -> Vec<&MergedEntry> {
+        self.entries
+            .iter()
+            .filter(|e| !e.conflicts().is_empty())
+            .collect()
+    }
+}
+
+/// The product file and its detached label, whichever of them exist
+fn product_files(file_path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![file_path.to_path_buf()];
+    ["IMG", "img", "LBL", "lbl"]
+        .iter()
+        .map(|e| file_path.with_extension(e))
+        .filter(|p| p != file_path && p.exists())
+        .for_each(|p| {
+            if !files.iter().any(|f| {
+                f.extension().map(|e| e.to_ascii_uppercase())
+                    == p.extension().map(|e| e.to_ascii_uppercase())
+            }) {
+                files.push(p)
+            }
+        });
+    files.retain(|f| f.exists());
+    files
+}
@@ -0,0 +1,421 @@
+//! Mission and instrument profiles which read the commonly needed facts about an observation from
+//! a PDS3 or VICAR label. Missions keep these under different keywords, groups and units; a
+//! profile knows where its mission puts them and returns them in one common form.
+use crate::label::Label;
+use crate::pvl::{Error, FromValue, Value, ValueUnits};
+use crate::time::{LocalSolarTime, PdsTime, SpacecraftClock};
+use regex::Regex;
+
+lazy_static! {
+    // Voyager VICAR labels carry their identification as the free text of LAB02 and LAB03, e.g.
+    // `VGR-1 FDS 35808.00 PICNO 1444S1+028 SCET 80.346 18:57:23 C` and
+    // `NA CAMERA EXP 15360.0 MSEC FILT 7(UV ) LO GAIN SCAN RATE 3:1 C`
+    static ref VOYAGER_LAB02: Regex =
+        Regex::new("^VGR-([12]) +FDS +([0-9.]+) +PICNO +([^ ]+)").unwrap();
+    static ref VOYAGER_LAB03: Regex =
+        Regex::new("^(NA|WA) CAMERA +EXP +([0-9.]+) +MSEC +FILT +([0-9])\\(([A-Z]*) *\\) +([A-Z]+) GAIN").unwrap();
+}
+
+/// The missions with a profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mission {
+    MarsScienceLaboratory,
+    MarsExplorationRover,
+    Cassini,
+    Voyager,
+    Unknown,
+}
+
+/// Reads the observation metadata of one mission or instrument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// MSL and MER, whose labels share the same layout
+    MarsRover(Mission),
+    CassiniIss,
+    CassiniVims,
+    VoyagerIss,
+    /// Standard PDS3 keywords only, for missions without a profile
+    Generic,
+}
+
+/// Facts particular to the MSL and MER cameras
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RoverExtras {
+    pub local_mean_solar_time: Option<LocalSolarTime>,
+    /// LOCAL_TRUE_SOLAR_TIME on the true solar sol where given, otherwise PLANET_DAY_NUMBER
+    pub local_true_solar_time: Option<LocalSolarTime>,
+    pub sequence_id: Option<String>,
+    /// e.g. `REGULAR` or `THUMBNAIL`
+    pub image_type: Option<String>,
+    /// The eye of a stereo camera, `LEFT` or `RIGHT`
+    pub frame_id: Option<String>,
+    pub instrument_mode: Option<String>,
+    pub filter_number: Option<String>,
+    /// ROVER_MOTION_COUNTER, starting with the site and drive
+    pub rover_motion_counter: Vec<i64>,
+}
+
+/// Facts particular to the Cassini ISS cameras
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CassiniIssExtras {
+    /// The positions of the two filter wheels
+    pub filters: Vec<String>,
+    pub gain_mode: Option<String>,
+    pub instrument_mode: Option<String>,
+    pub shutter_mode: Option<String>,
+    pub observation_id: Option<String>,
+    pub sequence_id: Option<String>,
+}
+
+/// Facts particular to Cassini VIMS, which exposes its infrared and visible channels separately
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CassiniVimsExtras {
+    /// Infrared exposure in milliseconds
+    pub ir_exposure: Option<f64>,
+    /// Visible exposure in milliseconds
+    pub vis_exposure: Option<f64>,
+    pub gain_modes: Vec<String>,
+    pub sampling_modes: Vec<String>,
+    pub instrument_mode: Option<String>,
+    pub observation_id: Option<String>,
+}
+
+/// Facts particular to the Voyager ISS cameras
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VoyagerIssExtras {
+    /// The picture number, e.g. `1444S1+028`
+    pub image_id: Option<String>,
+    /// Flight Data Subsystem count at readout
+    pub fds_count: Option<String>,
+    pub filter_number: Option<String>,
+    pub gain_mode: Option<String>,
+    pub shutter_mode: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissionExtras {
+    MarsRover(RoverExtras),
+    CassiniIss(CassiniIssExtras),
+    CassiniVims(CassiniVimsExtras),
+    VoyagerIss(VoyagerIssExtras),
+    None,
+}
+
+/// The facts every team needs about an observation, regardless of mission
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservationMetadata {
+    pub mission: Mission,
+    pub profile: Profile,
+    /// INSTRUMENT_HOST_ID where given, e.g. `MSL`, `MER1` or `VG1`, otherwise the host name
+    pub spacecraft: Option<String>,
+    pub instrument_id: String,
+    pub instrument_name: Option<String>,
+    pub product_id: Option<String>,
+    pub target: Option<String>,
+    /// PLANET_DAY_NUMBER, for surface missions
+    pub sol: Option<u32>,
+    pub filter: Option<String>,
+    /// Exposure duration in milliseconds
+    pub exposure: Option<f64>,
+    pub start_time: Option<PdsTime>,
+    pub stop_time: Option<PdsTime>,
+    /// Clock counts in the partition of SPACECRAFT_CLOCK_CNT_PARTITION where given
+    pub spacecraft_clock_start: Option<SpacecraftClock>,
+    pub spacecraft_clock_stop: Option<SpacecraftClock>,
+    /// e.g. `MONO` or `STEREO`
+    pub frame_type: Option<String>,
+    pub extras: MissionExtras,
+}
+
+/// The first of the paths with a value convertible to the requested type
+fn first<L: Label, T: FromValue>(label: &L, paths: &[&str]) -> Option<T> {
+    paths.iter().find_map(|p| label.query_one(p).ok())
+}
+
+/// A keyword of the product identification, which is at the top level of PDS3 labels and in
+/// the IDENTIFICATION property of VICAR labels
+fn identification<L: Label, T: FromValue>(label: &L, key: &str) -> Option<T> {
+    first(label, &[key, &format!("IDENTIFICATION/{}", key)])
+}
+
+/// A spacecraft clock count, with the fine field of the mission's clock
+fn clock<L: Label>(label: &L, key: &str, mission: Mission) -> Option<SpacecraftClock> {
+    identification::<L, String>(label, key)
+        .and_then(|c| SpacecraftClock::from_string_for_mission(&c, mission).ok())
+}
+
+/// Every value of a keyword which may be given as a single value or an array
+fn list<L: Label>(label: &L, paths: &[&str]) -> Vec<String> {
+    first::<L, Vec<String>>(label, paths)
+        .or_else(|| first::<L, String>(label, paths).map(|v| vec![v]))
+        .unwrap_or_default()
+}
+
+/// Converts a duration to milliseconds. Units come from the value in PDS3 labels, from a
+/// `KEY__UNIT` item in VICAR labels, or are the mission's documented units otherwise.
+fn milliseconds(value: &Value, unit_item: Option<String>, default: &ValueUnits) -> Option<f64> {
+    let ms = ValueUnits::Milliseconds;
+    match (value.units(), unit_item) {
+        (Some(_), _) => value.parse_f64_as(&ms).ok(),
+        (None, Some(u)) => crate::units::convert(
+            f64::from_value(value).ok()?,
+            &ValueUnits::from_string(&u),
+            &ms,
+        )
+        .ok(),
+        (None, None) => crate::units::convert(f64::from_value(value).ok()?, default, &ms).ok(),
+    }
+}
+
+/// The exposure duration at the first of the paths which has one, in milliseconds
+fn exposure<L: Label>(label: &L, paths: &[&str], default: &ValueUnits) -> Option<f64> {
+    paths.iter().find_map(|p| {
+        let value: Value = label.query_one(p).ok()?;
+        milliseconds(
+            &value,
+            label.query_one(&format!("{}__UNIT", p)).ok(),
+            default,
+        )
+    })
+}
+
+impl Profile {
+    /// Recognizes the mission and instrument of a label from INSTRUMENT_HOST_ID, the host,
+    /// mission and spacecraft names, and for Voyager VICAR labels, the LAB02 text
+    pub fn detect<L: Label>(label: &L) -> Profile {
+        let host_id: Option<String> = identification(label, "INSTRUMENT_HOST_ID");
+        let names: Vec<String> = ["INSTRUMENT_HOST_NAME", "MISSION_NAME", "SPACECRAFT_NAME"]
+            .iter()
+            .filter_map(|k| identification::<L, String>(label, k))
+            .collect();
+        let named = |n: &str| names.iter().any(|s| s.to_uppercase().contains(n));
+        let mission = match host_id.as_deref().map(|s| s.to_uppercase()).as_deref() {
+            Some("MSL") => Mission::MarsScienceLaboratory,
+            Some("MER1") | Some("MER2") => Mission::MarsExplorationRover,
+            Some("CO") => Mission::Cassini,
+            Some("VG1") | Some("VG2") => Mission::Voyager,
+            _ if named("MARS SCIENCE LABORATORY") => Mission::MarsScienceLaboratory,
+            _ if named("MARS EXPLORATION ROVER") => Mission::MarsExplorationRover,
+            _ if named("CASSINI") => Mission::Cassini,
+            _ if named("VOYAGER") => Mission::Voyager,
+            _ => match label.query_one::<String>("**/LAB02") {
+                Ok(s) if VOYAGER_LAB02.is_match(&s) => Mission::Voyager,
+                _ => Mission::Unknown,
+            },
+        };
+
+        let instrument: String = identification(label, "INSTRUMENT_ID").unwrap_or_default();
+        match mission {
+            Mission::MarsScienceLaboratory | Mission::MarsExplorationRover => {
+                Profile::MarsRover(mission)
+            }
+            Mission::Cassini if instrument.starts_with("ISS") => Profile::CassiniIss,
+            Mission::Cassini if instrument == "VIMS" => Profile::CassiniVims,
+            Mission::Voyager => Profile::VoyagerIss,
+            _ => Profile::Generic,
+        }
+    }
+
+    pub fn mission(&self) -> Mission {
+        match self {
+            Profile::MarsRover(m) => *m,
+            Profile::CassiniIss | Profile::CassiniVims => Mission::Cassini,
+            Profile::VoyagerIss => Mission::Voyager,
+            Profile::Generic => Mission::Unknown,
+        }
+    }
+
+    /// Reads the observation metadata of a label under this profile. Fails if no instrument
+    /// can be identified.
+    pub fn read<L: Label>(&self, label: &L) -> Result<ObservationMetadata, L::Error> {
+        let mut metadata = ObservationMetadata {
+            mission: self.mission(),
+            profile: *self,
+            spacecraft: identification(label, "INSTRUMENT_HOST_ID")
+                .or_else(|| identification(label, "INSTRUMENT_HOST_NAME")),
+            instrument_id: identification(label, "INSTRUMENT_ID").unwrap_or_default(),
+            instrument_name: identification(label, "INSTRUMENT_NAME"),
+            product_id: identification(label, "PRODUCT_ID"),
+            target: identification(label, "TARGET_NAME"),
+            sol: None,
+            filter: None,
+            exposure: None,
+            start_time: identification(label, "START_TIME"),
+            stop_time: identification(label, "STOP_TIME"),
+            spacecraft_clock_start: clock(label, "SPACECRAFT_CLOCK_START_COUNT", self.mission()),
+            spacecraft_clock_stop: clock(label, "SPACECRAFT_CLOCK_STOP_COUNT", self.mission()),
+            frame_type: None,
+            extras: MissionExtras::None,
+        };
+        if let Some(partition) = identification(label, "SPACECRAFT_CLOCK_CNT_PARTITION") {
+            metadata.spacecraft_clock_start = metadata
+                .spacecraft_clock_start
+                .map(|c| c.with_partition(partition));
+            metadata.spacecraft_clock_stop = metadata
+                .spacecraft_clock_stop
+                .map(|c| c.with_partition(partition));
+        }
+
+        match self {
+            Profile::MarsRover(_) => {
+                metadata.sol = identification(label, "PLANET_DAY_NUMBER");
+                metadata.frame_type = identification(label, "FRAME_TYPE");
+                metadata.filter = first(label, &["INSTRUMENT_STATE_PARMS/FILTER_NAME"]);
+                metadata.exposure = exposure(
+                    label,
+                    &["INSTRUMENT_STATE_PARMS/EXPOSURE_DURATION"],
+                    &ValueUnits::Milliseconds,
+                );
+                let true_sol: Option<u32> = first(
+                    label,
+                    &[
+                        "*:LOCAL_TRUE_SOLAR_TIME_SOL",
+                        "IDENTIFICATION/LOCAL_TRUE_SOLAR_TIME_SOL",
+                    ],
+                )
+                .or(metadata.sol);
+                metadata.extras = MissionExtras::MarsRover(RoverExtras {
+                    local_mean_solar_time: first(
+                        label,
+                        &[
+                            "*:LOCAL_MEAN_SOLAR_TIME",
+                            "IDENTIFICATION/LOCAL_MEAN_SOLAR_TIME",
+                        ],
+                    ),
+                    local_true_solar_time: identification::<L, LocalSolarTime>(
+                        label,
+                        "LOCAL_TRUE_SOLAR_TIME",
+                    )
+                    .map(|t| match (t.sol(), true_sol) {
+                        (None, Some(sol)) => t.with_sol(sol),
+                        _ => t,
+                    }),
+                    sequence_id: identification(label, "SEQUENCE_ID"),
+                    image_type: identification(label, "IMAGE_TYPE"),
+                    frame_id: identification(label, "FRAME_ID"),
+                    instrument_mode: first(label, &["INSTRUMENT_STATE_PARMS/INSTRUMENT_MODE_ID"]),
+                    filter_number: first(label, &["INSTRUMENT_STATE_PARMS/FILTER_NUMBER"]),
+                    rover_motion_counter: identification(label, "ROVER_MOTION_COUNTER")
+                        .unwrap_or_default(),
+                });
+            }
+            Profile::CassiniIss => {
+                // Exposures are given in milliseconds without units
+                let paths = ["EXPOSURE_DURATION", "INSTRUMENT/EXPOSURE_DURATION"];
+                metadata.exposure = exposure(label, &paths, &ValueUnits::Milliseconds);
+                let filters = list(label, &["FILTER_NAME", "INSTRUMENT/FILTER_NAME"]);
+                if !filters.is_empty() {
+                    metadata.filter = Some(filters.join("/"));
+                }
+                metadata.extras = MissionExtras::CassiniIss(CassiniIssExtras {
+                    filters,
+                    gain_mode: first(label, &["GAIN_MODE_ID", "INSTRUMENT/GAIN_MODE_ID"]),
+                    instrument_mode: first(
+                        label,
+                        &["INSTRUMENT_MODE_ID", "INSTRUMENT/INSTRUMENT_MODE_ID"],
+                    ),
+                    shutter_mode: first(label, &["SHUTTER_MODE_ID", "INSTRUMENT/SHUTTER_MODE_ID"]),
+                    observation_id: identification(label, "OBSERVATION_ID"),
+                    sequence_id: identification(label, "SEQUENCE_ID"),
+                });
+            }
+            Profile::CassiniVims => {
+                // The exposure pair is (infrared, visible) in milliseconds. Neither is the
+                // exposure of the product as a whole, so it's left to the extras.
+                let exposures: Vec<Value> =
+                    first(label, &["EXPOSURE_DURATION"]).unwrap_or_default();
+                let ms = |i: usize| {
+                    exposures
+                        .get(i)
+                        .and_then(|v| milliseconds(v, None, &ValueUnits::Milliseconds))
+                };
+                metadata.extras = MissionExtras::CassiniVims(CassiniVimsExtras {
+                    ir_exposure: ms(0),
+                    vis_exposure: ms(1),
+                    gain_modes: list(label, &["GAIN_MODE_ID"]),
+                    sampling_modes: list(label, &["SAMPLING_MODE_ID"]),
+                    instrument_mode: first(label, &["INSTRUMENT_MODE_ID"]),
+                    observation_id: identification(label, "OBSERVATION_ID"),
+                });
+            }
+            Profile::VoyagerIss => {
+                let mut extras = VoyagerIssExtras {
+                    image_id: identification(label, "IMAGE_ID"),
+                    fds_count: None,
+                    filter_number: identification(label, "FILTER_NUMBER"),
+                    gain_mode: identification(label, "GAIN_MODE_ID"),
+                    shutter_mode: identification(label, "SHUTTER_MODE_ID"),
+                };
+                metadata.filter = identification(label, "FILTER_NAME");
+                metadata.exposure = exposure(label, &["EXPOSURE_DURATION"], &ValueUnits::Seconds);
+
+                // VICAR labels have only the LAB text of their history to go on
+                let lab = |key: &str| {
+                    label
+                        .query_one::<String>(&format!("**/{}", key))
+                        .unwrap_or_default()
+                };
+                if let Some(caps) = VOYAGER_LAB02.captures(&lab("LAB02")) {
+                    metadata.spacecraft = metadata
+                        .spacecraft
+                        .or_else(|| Some(format!("VG{}", &caps[1])));
+                    extras.fds_count = Some(caps[2].to_owned());
+                    extras.image_id = extras.image_id.or_else(|| Some(caps[3].to_owned()));
+                }
+                if let Some(caps) = VOYAGER_LAB03.captures(&lab("LAB03")) {
+                    if metadata.instrument_id.is_empty() {
+                        metadata.instrument_id = match &caps[1] {
+                            "NA" => "ISSN".to_owned(),
+                            _ => "ISSW".to_owned(),
+                        };
+                    }
+                    metadata.exposure = metadata.exposure.or_else(|| caps[2].parse().ok());
+                    extras.filter_number =
+                        extras.filter_number.or_else(|| Some(caps[3].to_owned()));
+                    metadata.filter = metadata.filter.or_else(|| Some(caps[4].to_owned()));
+                    let gain = match &caps[5] {
+                        "LO" => "LOW",
+                        "HI" => "HIGH",
+                        g => g,
+                    };
+                    extras.gain_mode = extras.gain_mode.or_else(|| Some(gain.to_owned()));
+                }
+                metadata.extras = MissionExtras::VoyagerIss(extras);
+            }
+            Profile::Generic => {
+                metadata.sol = identification(label, "PLANET_DAY_NUMBER");
+                metadata.frame_type = identification(label, "FRAME_TYPE");
+                metadata.filter = first(label, &["FILTER_NAME", "**/FILTER_NAME"]);
+                metadata.exposure = exposure(
+                    label,
+                    &["EXPOSURE_DURATION", "**/EXPOSURE_DURATION"],
+                    &ValueUnits::Milliseconds,
+                );
+            }
+        }
+
+        if metadata.instrument_id.is_empty() {
+            Err(Error::General("No instrument could be identified".to_owned()).into())
+        } else {
+            Ok(metadata)
+        }
+    }
+}
+
+impl ObservationMetadata {
+    /// Detects the profile of a label and reads its observation metadata
+    /// # Example
+    /// ```
+    /// use vicar::observation::{Mission, ObservationMetadata};
+    /// use vicar::pvl::Pvl;
+    ///
+    /// let p = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+    /// let metadata = ObservationMetadata::from_label(&Pvl::load(std::path::Path::new(p)).unwrap()).unwrap();
+    /// assert_eq!(metadata.mission, Mission::MarsScienceLaboratory);
+    /// assert_eq!(metadata.sol, Some(3423));
+    /// assert_eq!(metadata.exposure, Some(4526.08));
+    /// ```
+    pub fn from_label<L: Label>(label: &L) -> Result<ObservationMetadata, L::Error> {
+        Profile::detect(label).read(label)
+    }
+}
@@ -1,6 +1,26 @@
 use anyhow::Result;
 use regex::Regex;
-use std::{borrow::Cow, fs, path::Path};
+use std::{borrow::Cow, fmt, fs, path::Path};
+
+use crate::time::PdsTime;
+use crate::units;
+pub use crate::units::ValueUnits;
+
+pub mod builder;
+pub mod cst;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod dialect;
+pub mod index;
+pub mod query;
+#[cfg(feature = "serde")]
+pub(crate) mod ser;
+pub mod writer;
+pub use dialect::Dialect;
+use index::Lookup;
+pub use index::{Entries, KeyCase};
+pub use query::{FromValue, QueryPath};
+pub use writer::PvlWriter;
 
 /// Parse error types
 #[derive(Debug)]
@@ -12,7 +32,139 @@ pub enum Error {
     InvalidType,
     ValueTypeParseError,
     InvalidEncoding(String),
+    UnitConversion(String),
     General(String),
+    /// An error at a known location in the text, along with the line it occurred on
+    Located {
+        error: Box<Error>,
+        span: Span,
+        snippet: String,
+    },
+}
+
+/// Location of a parsing error within the text. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    /// Number of characters covered, from the column onwards
+    pub length: usize,
+}
+
+impl Span {
+    /// Determines the span of the byte range `start..end` within the text
+    pub fn from_range(text: &str, start: usize, end: usize) -> Self {
+        let start = start.min(text.len());
+        let line_start = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        Span {
+            line: text[..start].matches('\n').count() + 1,
+            column: start - line_start + 1,
+            length: end.max(start + 1) - start,
+        }
+    }
+}
+
+impl Error {
+    /// Attaches the location of the byte range `start..end` in `text`, along with the line it
+    /// starts on, to the error. Errors which are already located are left as they are.
+    pub fn at(self, text: &str, start: usize, end: usize) -> Error {
+        match self {
+            Error::Located { .. } => self,
+            _ => {
+                let start = start.min(text.len());
+                let line_start = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+                let line_end = text[start..]
+                    .find('\n')
+                    .map(|i| start + i)
+                    .unwrap_or(text.len());
+                Error::Located {
+                    error: Box::new(self),
+                    span: Span::from_range(text, start, end.min(line_end)),
+                    snippet: text[line_start..line_end].trim_end().to_owned(),
+                }
+            }
+        }
+    }
+
+    /// Location of the error in the text, if known
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Error::Located { span, .. } => Some(span),
+            _ => None,
+        }
+    }
+
+    /// The line of text the error occurred on, if known
+    pub fn snippet(&self) -> Option<&str> {
+        match self {
+            Error::Located { snippet, .. } => Some(snippet.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Eof => write!(f, "Unexpected end of file"),
+            Error::Syntax(s) => write!(f, "Syntax error: {}", s),
+            Error::CommentIsntComment => write!(f, "Expected the start of a comment"),
+            Error::Programming(s) => write!(f, "Programming error: {}", s),
+            Error::InvalidType => write!(f, "Value is not of the requested type"),
+            Error::ValueTypeParseError => write!(f, "Value could not be parsed"),
+            Error::InvalidEncoding(s) => write!(f, "Invalid encoding: {}", s),
+            Error::UnitConversion(s) => write!(f, "Unit conversion error: {}", s),
+            Error::General(s) => write!(f, "{}", s),
+            Error::Located {
+                error,
+                span,
+                snippet,
+            } => write!(
+                f,
+                "{} at line {}, column {}\n  {}\n  {}{}",
+                error,
+                span.line,
+                span.column,
+                snippet,
+                " ".repeat(span.column - 1),
+                "^".repeat(
+                    span.length
+                        .min(snippet.len().saturating_sub(span.column - 1))
+                        .max(1)
+                )
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// How strictly the text is held to the specification while parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Any violation of the specification is an error
+    Strict,
+    /// Violations are recovered from where possible and reported as warnings
+    #[default]
+    Lenient,
+}
+
+/// A violation of the specification which was recovered from while parsing leniently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub message: String,
+    pub span: Span,
+    pub snippet: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.span.line, self.span.column
+        )
+    }
 }
 
 /// PVL Symbol types
@@ -38,17 +190,47 @@ impl Symbol {
             _ => None,
         }
     }
-}
 
-/// PVL measurement units
-#[derive(Debug, Clone, Eq, PartialEq)]
-pub enum ValueUnits {
-    Celcius,
-    Farenheit,
-    Degrees,
-    Radians,
-    Milliseconds,
-    Seconds,
+    /// Extracts the namespace of a namespaced key or pointer, e.g. `MSL` from
+    /// `MSL:LOCAL_MEAN_SOLAR_TIME`. Returns None if the key isn't namespaced.
+    pub fn namespace(&self) -> Option<String> {
+        match self {
+            Symbol::Pointer(value) | Symbol::Key(value) => value
+                .split_once(':')
+                .map(|(ns, _)| ns.trim_start_matches('^').to_owned()),
+            _ => None,
+        }
+    }
+
+    /// Extracts the key or pointer name without its namespace, e.g. `LOCAL_MEAN_SOLAR_TIME`
+    /// from `MSL:LOCAL_MEAN_SOLAR_TIME`. Pointers retain their leading `^`.
+    pub fn name(&self) -> Option<String> {
+        match self {
+            Symbol::Pointer(value) => match value.split_once(':') {
+                Some((_, n)) => Some(format!("^{}", n)),
+                None => Some(value.to_owned()),
+            },
+            Symbol::Key(value) => match value.split_once(':') {
+                Some((_, n)) => Some(n.to_owned()),
+                None => Some(value.to_owned()),
+            },
+            _ => None,
+        }
+    }
+
+    /// Determines if this is a key or pointer written exactly as `key`, including any namespace
+    pub fn is_key(&self, key: &str) -> bool {
+        match self {
+            Symbol::Pointer(value) | Symbol::Key(value) => value == key,
+            _ => false,
+        }
+    }
+
+    /// Determines if this key or pointer has the specified namespace and name. A namespace of
+    /// None only matches keys which aren't namespaced.
+    pub fn matches(&self, namespace: Option<&str>, name: &str) -> bool {
+        self.namespace().as_deref() == namespace && self.name().as_deref() == Some(name)
+    }
 }
 
 /// PVL right-hand value data types
@@ -62,23 +244,31 @@ pub enum ValueType {
     Bool,
     Flag, // A string but not wrapped in quotes
     BitMask,
+    DateTime,
 }
 
-/// Contains PVL right-hand values and flags
-#[derive(Debug, Clone)]
+/// Contains the right-hand values and flags of PVL and VICAR labels. Strings may be double or
+/// single quoted, as VICAR labels use the latter.
+#[derive(Debug, Clone, Eq)]
 pub struct Value {
     value_raw: String,
     value_type: ValueType,
+    units: Option<ValueUnits>,
+    /// Whether single quotes delimit symbols, as in ODL and PDS3, rather than strings
+    quoted_symbols: bool,
 }
 
 lazy_static! {
-    static ref BOOL_DETERMINATE: Regex = Regex::new("^\"(TRUE|FALSE)\"$").unwrap();
-    static ref STRING_DETERMINATE: Regex = Regex::new("^\".*\"$").unwrap();
+    static ref BOOL_DETERMINATE: Regex = Regex::new("^(\"(TRUE|FALSE)\"|'(TRUE|FALSE)')$").unwrap();
+    static ref STRING_DETERMINATE: Regex = Regex::new("^(\".*\"|'.*')$").unwrap();
     static ref ARRAY_DETERMINATE: Regex = Regex::new("^\\(.*\\)$").unwrap();
+    static ref DATETIME_DETERMINATE: Regex =
+        Regex::new("^[0-9]{4}-([0-9]{2}-[0-9]{2}|[0-9]{3})(T[0-9:.]*Z?)?$").unwrap();
     static ref FLOAT_DETERMINATE: Regex = Regex::new("^-*[0-9]+\\.[0-9][ ]*").unwrap();
     static ref INTEGER_DETERMINATE: Regex = Regex::new("^[+-]*[0-9]+[^#a-zA-Z]*[ ]*").unwrap();
     static ref FLAG_DETERMINATE: Regex = Regex::new("^[a-zA-Z_]+[a-zA-Z0-9]+$").unwrap();
     static ref BITMASK_DETERMINATE: Regex = Regex::new("^[1-8]*#+[0-1]+#+$").unwrap();
+    static ref UNITS_DETERMINATE: Regex = Regex::new("^(.*?)[ ]*<([^<>]*)>$").unwrap();
 }
 const LINE_CONTINUATION_PREFIX: &str = "                                     ";
 
@@ -118,11 +308,63 @@ impl Value {
     impl_parse_pvl_fn!(parse_bool, bool, ValueType::Bool);
     impl_parse_pvl_fn!(parse_flag, String, ValueType::Flag);
 
-    /// Constructs a new Value object and determines type of provided raw data
+    /// Constructs a new Value object and determines type of provided raw data. Units of
+    /// measure (e.g. `2.91281 <rad>`) are split off of the raw data.
     pub fn new(value_raw: &str) -> Self {
+        let (value_raw, units) = Value::split_units(value_raw.trim());
         Value {
             value_raw: value_raw.to_owned(),
             value_type: Value::determine_type(value_raw),
+            units,
+            quoted_symbols: false,
+        }
+    }
+
+    /// Whether the value is a symbol quoted with single quotes, e.g. `'N/A'` in a PDS3 label,
+    /// rather than a text string
+    pub fn is_symbol(&self) -> bool {
+        self.quoted_symbols && self.value_raw.len() >= 2 && self.value_raw.starts_with('\'')
+    }
+
+    /// Separates a trailing `<units>` from the raw value. Quoted strings are left alone.
+    fn split_units(value_raw: &str) -> (&str, Option<ValueUnits>) {
+        if STRING_DETERMINATE.is_match(value_raw) {
+            return (value_raw, None);
+        }
+        match UNITS_DETERMINATE.captures(value_raw) {
+            Some(caps) => (
+                caps.get(1).unwrap().as_str(),
+                Some(ValueUnits::from_string(caps.get(2).unwrap().as_str())),
+            ),
+            None => (value_raw, None),
+        }
+    }
+
+    /// The data type determined for the value
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// Units of measure attached to the value, if any
+    pub fn units(&self) -> Option<&ValueUnits> {
+        self.units.as_ref()
+    }
+
+    /// Parses a numeric value and converts it from the units given in the label to the
+    /// requested units. Fails if the value has no units or they are not compatible.
+    pub fn parse_f64_as(&self, to: &ValueUnits) -> Result<f64, Error> {
+        if self.value_type != ValueType::Float && self.value_type != ValueType::Integer {
+            Err(Error::InvalidType)
+        } else if let Some(from) = &self.units {
+            match self.value_raw.parse::<f64>() {
+                Ok(v) => units::convert(v, from, to),
+                Err(_) => Err(Error::ValueTypeParseError),
+            }
+        } else {
+            Err(Error::UnitConversion(format!(
+                "Value '{}' has no units",
+                self.value_raw
+            )))
         }
     }
 
@@ -134,6 +376,8 @@ impl Value {
             ValueType::String
         } else if ARRAY_DETERMINATE.is_match(value_raw) {
             ValueType::Array
+        } else if DATETIME_DETERMINATE.is_match(value_raw) {
+            ValueType::DateTime
         } else if FLOAT_DETERMINATE.is_match(value_raw) {
             ValueType::Float
         } else if BITMASK_DETERMINATE.is_match(value_raw) {
@@ -153,25 +397,107 @@ impl Value {
         if self.value_type != ValueType::Undetermined && self.value_type != ValueType::String {
             Err(Error::InvalidType)
         } else {
-            Ok(self.value_raw.replace('\"', ""))
+            Ok(self.unquoted())
         }
     }
 
-    /// Parses the raw data value to an array of Values. Throws an error if we are not an array type
+    /// The raw value with string quoting removed. Within single quotes, as used by VICAR, `''`
+    /// stands for a quote, and within double quotes `""` does.
+    pub(crate) fn unquoted(&self) -> String {
+        let raw = self.value_raw.as_str();
+        if raw.len() >= 2 && raw.starts_with('\'') && raw.ends_with('\'') {
+            raw[1..(raw.len() - 1)].replace("''", "'")
+        } else if raw.len() >= 2 && raw.starts_with('"') && raw.ends_with('"') {
+            raw[1..(raw.len() - 1)].replace("\"\"", "\"")
+        } else {
+            raw.replace('"', "")
+        }
+    }
+
+    /// Parses a date/time value. Quoted strings are accepted as well, since some labels quote
+    /// their times.
+    pub fn parse_time(&self) -> Result<PdsTime, Error> {
+        match self.value_type {
+            ValueType::DateTime | ValueType::String | ValueType::Undetermined => {
+                PdsTime::from_string(&self.value_raw).map_err(|e| Error::General(e.to_string()))
+            }
+            _ => Err(Error::InvalidType),
+        }
+    }
+
+    /// Parses the raw data value to an array of Values. Throws an error if we are not an array type.
+    /// Units applied to the whole sequence, e.g. `(1.0, 2.0) <m>`, are given to each element
+    /// that doesn't specify its own.
     pub fn parse_array(&self) -> Result<Vec<Value>, Error> {
         if self.value_type != ValueType::Array {
             Err(Error::InvalidType)
         } else {
-            Ok(self.value_raw[1..(self.value_raw.len() - 1)]
-                .split(',')
-                .map(Value::new)
-                .collect())
+            Ok(
+                split_sequence(&self.value_raw[1..(self.value_raw.len() - 1)])
+                    .into_iter()
+                    .map(|s| {
+                        let mut v = Value::new(s);
+                        if v.units.is_none() {
+                            v.units = self.units.clone();
+                        }
+                        v.quoted_symbols = self.quoted_symbols;
+                        v
+                    })
+                    .collect(),
+            )
         }
     }
 }
 
+/// Splits the interior of a sequence on commas that aren't within quotes or a nested sequence
+fn split_sequence(s: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '(') | (None, '{') => depth += 1,
+            (None, ')') | (None, '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts
+}
+
+/// Values are equal if they are of the same type and units and have equivalent content.
+/// Differences in quoting, number formatting and whitespace within sequences are ignored.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.value_type == other.value_type
+            && self.units == other.units
+            && match self.value_type {
+                ValueType::Array => self.parse_array().ok() == other.parse_array().ok(),
+                ValueType::String | ValueType::Bool => self.unquoted() == other.unquoted(),
+                ValueType::Float | ValueType::Integer => {
+                    match (
+                        self.value_raw.parse::<f64>(),
+                        other.value_raw.parse::<f64>(),
+                    ) {
+                        (Ok(a), Ok(b)) => a == b,
+                        _ => self.value_raw == other.value_raw,
+                    }
+                }
+                _ => self.value_raw == other.value_raw,
+            }
+    }
+}
+
 /// Represents the basic KEY = VALUE pair in a PVL file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct KeyValuePair {
     pub key: Symbol,
     pub value: Value,
@@ -184,22 +510,37 @@ pub trait PropertyGrouping {
     fn type_of(&self) -> Symbol;
     fn get_property(&self, name: &str) -> Option<KeyValuePair>;
     fn has_property(&self, name: &str) -> bool;
+
+    /// Retrieves a property by namespace and name, e.g. ("MSL", "INTERPOLATION_METHOD")
+    fn get_namespaced_property(&self, namespace: &str, name: &str) -> Option<KeyValuePair> {
+        self.properties()
+            .into_iter()
+            .find(|p| p.key.matches(Some(namespace), name))
+    }
+
+    /// Retrieves a property by namespace and name, falling back to the same name without a
+    /// namespace if the namespaced property isn't present.
+    fn get_namespaced_property_or_bare(&self, namespace: &str, name: &str) -> Option<KeyValuePair> {
+        self.get_namespaced_property(namespace, name).or_else(|| {
+            self.properties()
+                .into_iter()
+                .find(|p| p.key.matches(None, name))
+        })
+    }
+
+    /// Retrieves all properties within the requested namespace
+    fn get_namespace_properties(&self, namespace: &str) -> Vec<KeyValuePair> {
+        self.properties()
+            .into_iter()
+            .filter(|p| p.key.namespace().as_deref() == Some(namespace))
+            .collect()
+    }
 }
 
 macro_rules! get_property {
     () => {
         fn get_property(&self, name: &str) -> Option<KeyValuePair> {
-            Some(
-                self.properties
-                    .iter()
-                    .filter(|p| match &p.key {
-                        Symbol::Key(n) | Symbol::Pointer(n) => n == name,
-                        _ => false,
-                    })
-                    .next()
-                    .unwrap()
-                    .to_owned(),
-            )
+            self.get_all(name).into_iter().next().cloned()
         }
     };
 }
@@ -207,24 +548,20 @@ macro_rules! get_property {
 macro_rules! has_property {
     () => {
         fn has_property(&self, name: &str) -> bool {
-            self.properties
-                .iter()
-                .filter(|p| match &p.key {
-                    Symbol::Key(n) | Symbol::Pointer(n) => n == name,
-                    _ => false,
-                })
-                .collect::<Vec<&KeyValuePair>>()
-                .len()
-                > 0
+            !self.get_all(name).is_empty()
         }
     };
 }
 
 /// Represents the PVL GROUP...END_GROUP structure
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Group {
     pub name: String,
-    pub properties: Vec<KeyValuePair>,
+    #[cfg_attr(feature = "serde", serde(with = "ser::properties"))]
+    pub properties: Entries<KeyValuePair>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lookup: Lookup,
 }
 
 impl PropertyGrouping for Group {
@@ -233,7 +570,7 @@ impl PropertyGrouping for Group {
     }
 
     fn properties(&self) -> Vec<KeyValuePair> {
-        self.properties.clone()
+        self.properties.to_vec()
     }
 
     fn type_of(&self) -> Symbol {
@@ -244,11 +581,18 @@ impl PropertyGrouping for Group {
     has_property! {}
 }
 
-/// Represents the PVL OBJECT...END_OBJECT structure
-#[derive(Debug)]
+/// Represents the PVL OBJECT...END_OBJECT structure. Objects may contain nested groups and
+/// objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Object {
     pub name: String,
-    pub properties: Vec<KeyValuePair>,
+    #[cfg_attr(feature = "serde", serde(with = "ser::properties"))]
+    pub properties: Entries<KeyValuePair>,
+    pub groups: Entries<Group>,
+    pub objects: Entries<Object>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lookup: Lookup,
 }
 
 impl PropertyGrouping for Object {
@@ -257,7 +601,7 @@ impl PropertyGrouping for Object {
     }
 
     fn properties(&self) -> Vec<KeyValuePair> {
-        self.properties.clone()
+        self.properties.to_vec()
     }
 
     fn type_of(&self) -> Symbol {
@@ -273,14 +617,91 @@ impl PropertyGrouping for Object {
 pub struct PvlReader {
     content: String,
     pos: usize,
+    mode: ParseMode,
+    dialect: Dialect,
+    warnings: Vec<Warning>,
+    mixed_line_ending: Option<usize>,
 }
 
 impl PvlReader {
     /// Constructs a new PVLReader object. Filters CRLF to LF. Expects UTF-8 encoded String
     pub fn new(content: &str) -> Self {
+        PvlReader::new_with_mode(content, ParseMode::default())
+    }
+
+    /// Constructs a new PVLReader object which parses with the requested strictness. The
+    /// dialect is detected from the content.
+    pub fn new_with_mode(content: &str, mode: ParseMode) -> Self {
+        PvlReader::new_with_dialect(content, Dialect::detect(content), mode)
+    }
+
+    /// Constructs a new PVLReader object which parses the requested dialect with the requested
+    /// strictness
+    pub fn new_with_dialect(content: &str, dialect: Dialect, mode: ParseMode) -> Self {
         PvlReader {
             content: PvlReader::filter_linefeeds(content),
             pos: 0,
+            mode,
+            dialect,
+            warnings: vec![],
+            mixed_line_ending: PvlReader::find_mixed_line_ending(content),
+        }
+    }
+
+    /// Position, in the filtered text, of the first line ending which differs from the one
+    /// before it, if the text mixes CRLF and LF
+    fn find_mixed_line_ending(content: &str) -> Option<usize> {
+        let b = content.as_bytes();
+        let mut first_is_crlf: Option<bool> = None;
+        let mut carriage_returns = 0;
+        for (i, c) in b.iter().enumerate() {
+            match c {
+                b'\r' => carriage_returns += 1,
+                b'\n' => {
+                    let is_crlf = i > 0 && b[i - 1] == b'\r';
+                    match first_is_crlf {
+                        None => first_is_crlf = Some(is_crlf),
+                        Some(f) if f != is_crlf => return Some(i - carriage_returns),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Warnings collected so far while parsing leniently
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Records a violation of the specification at `start`. In strict mode this is an error,
+    /// in lenient mode it is kept as a warning and parsing carries on.
+    fn violation(&mut self, message: &str, start: usize) -> Result<(), Error> {
+        let line_end = self.content[start.min(self.content.len())..]
+            .find('\n')
+            .map(|i| start + i)
+            .unwrap_or(self.content.len());
+        let error = Error::Syntax(message.to_owned()).at(&self.content, start, line_end);
+        match (self.mode, error) {
+            (ParseMode::Lenient, Error::Located { span, snippet, .. }) => {
+                self.warnings.push(Warning {
+                    message: message.to_owned(),
+                    span,
+                    snippet,
+                });
+                Ok(())
+            }
+            (_, error) => Err(error),
+        }
+    }
+
+    /// Reports mixed CRLF and LF line endings, if present
+    pub fn check_line_endings(&mut self) -> Result<(), Error> {
+        match self.mixed_line_ending {
+            Some(pos) => self.violation("Mixed CRLF and LF line endings", pos),
+            None => Ok(()),
         }
     }
 
@@ -294,7 +715,7 @@ impl PvlReader {
         if indx >= self.content.len() {
             Err(Error::Eof)
         } else {
-            //Ok(self.content.chars().nth(indx).unwrap()) // Slow but correct(er)
+            //Ok(self.content.chars().nth(indx)?) // Slow but correct(er)
             Ok(self.content.as_bytes()[indx] as char) // WAY faster, but won't work for non 8-bit text files
         }
     }
@@ -305,7 +726,7 @@ impl PvlReader {
         if self.pos + indx >= self.content.len() {
             Err(Error::Eof)
         } else {
-            //Ok(self.content.chars().nth(indx).unwrap()) // Slow but correct(er)
+            //Ok(self.content.chars().nth(indx)?) // Slow but correct(er)
             Ok(self.content.as_bytes()[self.pos + indx] as char) // WAY faster, but won't work for non 8-bit text files
         }
     }
@@ -352,7 +773,7 @@ impl PvlReader {
         } else if self.pos == 0 {
             Ok(true)
         } else {
-            let c = self.char_at(self.pos - 1).unwrap();
+            let c = self.char_at(self.pos - 1)?;
             match c {
                 '\r' | '\n' => Ok(true),
                 _ => Ok(false),
@@ -364,8 +785,8 @@ impl PvlReader {
         if self.is_eof() || self.pos + 1 >= self.content.len() {
             Ok(false)
         } else {
-            let c = self.current_char().unwrap();
-            let n = self.peek_char().unwrap();
+            let c = self.current_char()?;
+            let n = self.peek_char()?;
             Ok(c == '/' && n == '*')
         }
     }
@@ -374,25 +795,59 @@ impl PvlReader {
         if self.pos + 1 >= self.content.len() {
             Ok(false)
         } else {
-            let c = self.current_char().unwrap();
-            let n = self.peek_char().unwrap();
+            let c = self.current_char()?;
+            let n = self.peek_char()?;
             Ok(c == '*' && n == '/')
         }
     }
 
     pub fn skip_multiline_comment(&mut self) -> Result<String, Error> {
-        if !self.is_at_multiline_comment_start().unwrap() {
+        if !self.is_at_multiline_comment_start()? {
             Err(Error::CommentIsntComment)
         } else {
-            let mut comment_text = "".to_string();
-            while !self.is_at_multiline_comment_end().unwrap() {
-                comment_text.push(self.next_char().unwrap());
+            let start = self.pos + 2;
+            self.jump(2)?;
+            while !self.is_at_multiline_comment_end()? {
+                if self.is_eof() || self.pos + 1 >= self.content.len() {
+                    return Err(Error::Syntax("Unterminated comment".to_owned()));
+                }
+                self.pos += 1;
             }
-            self.jump(2).unwrap();
-            Ok(comment_text[1..(comment_text.len() - 2)].to_string())
+            let comment_text = self.content[start..self.pos].to_string();
+            self.jump(2)?;
+            Ok(comment_text)
         }
     }
 
+    /// Returns true if the line at the current position holds a comment, possibly indented
+    pub fn is_at_comment_line(&self) -> Result<bool, Error> {
+        if !self.is_at_line_start()? {
+            Ok(false)
+        } else {
+            let indent = self.content.as_bytes()[self.pos..]
+                .iter()
+                .take_while(|c| **c == b' ' || **c == b'\t')
+                .count();
+            let line = &self.content.as_bytes()[(self.pos + indent)..];
+            Ok(line.starts_with(b"/*")
+                || (self.dialect.allows_line_comments() && line.starts_with(b"#")))
+        }
+    }
+
+    /// Skips over a comment line, including any comment continuing over following lines
+    pub fn skip_comment_line(&mut self) -> Result<(), Error> {
+        while !self.is_at_multiline_comment_start()? {
+            if self.current_char()? == '#' {
+                self.skip_to_next_line();
+                return Ok(());
+            }
+            self.advance();
+        }
+        self.skip_multiline_comment()?;
+        self.skip_to_next_line();
+        Ok(())
+    }
+
     pub fn is_at_pointer(&self) -> Result<bool, Error> {
         match self.current_char() {
             Ok(c) => Ok(c == '^'),
@@ -400,78 +855,58 @@ impl PvlReader {
         }
     }
 
+    /// Returns the keyword of the statement starting at the current position, without
+    /// consuming it
+    fn peek_keyword(&self) -> &str {
+        let rest = &self.content[self.pos.min(self.content.len())..];
+        let end = rest.find(['=', '\n', '\0']).unwrap_or(rest.len());
+        rest[..end].trim()
+    }
+
     pub fn is_at_group(&self) -> Result<bool, Error> {
-        if !self.has_n_remaining(5) {
+        if self.is_eof() {
             Ok(false)
-        } else if !self.is_at_line_start().unwrap() {
+        } else if !self.is_at_line_start()? {
             Err(Error::Programming(t!(
                 "Attempt to check if at group when not at start of line"
             )))
         } else {
-            Ok(vec![
-                self.char_at_pos_plus_n(0).unwrap(),
-                self.char_at_pos_plus_n(1).unwrap(),
-                self.char_at_pos_plus_n(2).unwrap(),
-                self.char_at_pos_plus_n(3).unwrap(),
-                self.char_at_pos_plus_n(4).unwrap(),
-            ]
-            .into_iter()
-            .collect::<String>()
-                == "GROUP")
+            Ok(self.dialect.reserved_symbol(self.peek_keyword()) == Some(Symbol::Group))
         }
     }
 
     pub fn is_at_object(&self) -> Result<bool, Error> {
-        if !self.has_n_remaining(6) {
-            Ok(false)
-        } else {
-            Ok(vec![
-                self.char_at_pos_plus_n(0).unwrap(),
-                self.char_at_pos_plus_n(1).unwrap(),
-                self.char_at_pos_plus_n(2).unwrap(),
-                self.char_at_pos_plus_n(3).unwrap(),
-                self.char_at_pos_plus_n(4).unwrap(),
-                self.char_at_pos_plus_n(5).unwrap(),
-            ]
-            .into_iter()
-            .collect::<String>()
-                == "OBJECT")
-        }
+        Ok(!self.is_eof()
+            && self.dialect.reserved_symbol(self.peek_keyword()) == Some(Symbol::Object))
     }
 
     pub fn is_at_end(&self) -> bool {
-        if self.has_n_remaining(3) {
-            let mut s = String::new();
-
-            s.push(self.char_at_pos_plus_n(0).unwrap());
-            s.push(self.char_at_pos_plus_n(1).unwrap());
-            s.push(self.char_at_pos_plus_n(2).unwrap());
-
-            s == "END"
-        } else {
-            false
-        }
+        let at_word_start = self.pos == 0
+            || self
+                .char_at(self.pos - 1)
+                .is_ok_and(|c| c.is_ascii_whitespace());
+        at_word_start && self.dialect.reserved_symbol(self.peek_keyword()) == Some(Symbol::End)
     }
 
     pub fn read_symbol(&mut self) -> Result<Symbol, Error> {
-        if self.is_at_value_line_continuation().unwrap() {
+        if self.is_at_value_line_continuation()? {
             Err(Error::Syntax(
                 "Value line continuation without a preceeding key value pair".to_owned(),
             ))
-        } else if !self.is_at_line_start().unwrap() {
+        } else if !self.is_at_line_start()? {
             Err(Error::Programming(
                 "Attempt to read a key value pair when not at beginning of a line".to_owned(),
             ))
         } else {
             let mut symbol_text = String::new();
             while !self.is_eof() {
-                let c = self.current_char().unwrap();
-                if c != '\n' && c != '\r' && c != '=' {
+                let c = self.current_char()?;
+                if c != '\n' && c != '\r' && c != '=' && c != '\0' {
                     symbol_text.push(c);
                 } else {
                     break;
                 }
-                self.next_char().unwrap();
+                self.pos += 1;
             }
 
             symbol_text = symbol_text.trim().to_owned();
@@ -480,16 +915,8 @@ impl PvlReader {
                 Ok(Symbol::BlankLine)
             } else if symbol_text.starts_with('^') {
                 Ok(Symbol::Pointer(symbol_text))
-            } else if symbol_text == "GROUP" {
-                Ok(Symbol::Group)
-            } else if symbol_text == "OBJECT" {
-                Ok(Symbol::Object)
-            } else if symbol_text == "END_GROUP" {
-                Ok(Symbol::GroupEnd)
-            } else if symbol_text == "END_OBJECT" {
-                Ok(Symbol::ObjectEnd)
-            } else if symbol_text == "END" {
-                Ok(Symbol::End)
+            } else if let Some(symbol) = self.dialect.reserved_symbol(&symbol_text) {
+                Ok(symbol)
             } else {
                 Ok(Symbol::Key(symbol_text))
             }
@@ -498,19 +925,17 @@ impl PvlReader {
 
     pub fn read_remaining_line(&mut self) -> Result<String, Error> {
         let mut line_text = String::new();
+        if !self.is_eof() && self.current_char()? == '=' {
+            self.pos += 1;
+        }
         while !self.is_eof() {
-            if self.current_char().unwrap() == '=' {
-                self.jump(2).unwrap();
-            }
-            let c = self.current_char().unwrap();
+            let c = self.current_char()?;
             if c != '\n' && c != '\r' {
                 line_text.push(c);
             } else {
                 break;
             }
-            if !self.is_eof() {
-                self.next_char()?;
-            }
+            self.pos += 1;
         }
 
         line_text = line_text.trim().to_owned();
@@ -527,10 +952,9 @@ impl PvlReader {
         } else {
             let mut found_non_ws = false;
             for i in 0..100 {
-                if self.pos + i >= self.content.len() || self.char_at_pos_plus_n(i).unwrap() == '\n'
-                {
+                if self.pos + i >= self.content.len() || self.char_at_pos_plus_n(i)? == '\n' {
                     break;
-                } else if self.char_at_pos_plus_n(i).unwrap() != ' ' {
+                } else if self.char_at_pos_plus_n(i)? != ' ' {
                     found_non_ws = true;
                 }
             }
@@ -546,25 +970,24 @@ impl PvlReader {
     }
 
     pub fn is_at_value_line_continuation(&self) -> Result<bool, Error> {
-        if !self.is_at_line_start().unwrap() {
+        if !self.is_at_line_start()? {
             Ok(false)
-        } else if self.pos + LINE_CONTINUATION_PREFIX.len() >= self.content.len() {
+        } else if self.is_eof() {
             Err(Error::Eof)
+        } else if self.pos + LINE_CONTINUATION_PREFIX.len() >= self.content.len() {
+            // Too little text remains for this to be a continuation
+            Ok(false)
         } else {
             Ok(
-                &self.content[self.pos..(self.pos + LINE_CONTINUATION_PREFIX.len())]
-                    == LINE_CONTINUATION_PREFIX,
+                &self.content.as_bytes()[self.pos..(self.pos + LINE_CONTINUATION_PREFIX.len())]
+                    == LINE_CONTINUATION_PREFIX.as_bytes(),
             )
         }
     }
 
     pub fn jump_to_next_line(&mut self) -> Result<(), Error> {
-        while self.pos <= self.content.len() {
-            if self.char_at(self.pos).unwrap() == '\n' {
-                self.next_char()?;
-            } else {
-                break;
-            }
+        while !self.is_eof() && self.current_char()? == '\n' {
+            self.pos += 1;
         }
         Ok(())
     }
@@ -576,36 +999,127 @@ impl PvlReader {
         Ok(())
     }
 
+    /// Attaches the current position within the text to an error
+    pub fn locate(&self, error: Error) -> Error {
+        let line_end = self.content[self.pos.min(self.content.len())..]
+            .find('\n')
+            .map(|i| self.pos + i)
+            .unwrap_or(self.content.len());
+        error.at(&self.content, self.pos, line_end)
+    }
+
+    /// Moves forward one character, unless already at the end of the text
+    fn advance(&mut self) {
+        if !self.is_eof() {
+            self.pos += 1;
+        }
+    }
+
+    /// Moves to the start of the next line
+    fn skip_to_next_line(&mut self) {
+        while !self.is_eof() && self.content.as_bytes()[self.pos] != b'\n' {
+            self.pos += 1;
+        }
+        self.advance();
+    }
+
     pub fn read_key_value_pair_raw(&mut self) -> Result<KeyValuePair, Error> {
-        if self.is_at_value_line_continuation().unwrap() {
+        if self.is_at_value_line_continuation()? {
             Err(Error::Syntax(
                 "Value line continuation without a preceeding key value pair".to_owned(),
             ))
-        } else if !self.is_at_line_start().unwrap() {
+        } else if !self.is_at_line_start()? {
             Err(Error::Programming(
                 "Attempt to read a key value pair when not at beginning of a line".to_owned(),
             ))
         } else {
-            let mut value_string = String::new();
-            let key_res = self.read_symbol().unwrap();
-            value_string += self.read_remaining_line().unwrap().as_ref();
+            let start = self.pos;
+            let key_res = self.read_symbol()?;
+            let is_assignment = matches!(key_res, Symbol::Key(_) | Symbol::Pointer(_));
+            if is_assignment && !self.is_at_equals()? {
+                self.violation("Statement is missing '='", start)?;
+            }
+            let mut value_string = self.read_remaining_line()?;
+            self.advance();
+
+            // The value may start on the line following the equals sign
+            if is_assignment && value_string.is_empty() {
+                self.jump_to_next_line()?;
+                if !self.is_eof() {
+                    value_string = self.read_remaining_line()?;
+                    self.advance();
+                }
+            }
+
+            // Quoted strings and sequences continue until they're closed, however the
+            // following lines are indented
+            loop {
+                let (in_quotes, depth) =
+                    value_nesting(&value_string, self.dialect.single_quoted_strings());
+                if !in_quotes && depth <= 0 {
+                    break;
+                } else if self.is_eof() {
+                    self.violation("Value is not terminated", start)?;
+                    break;
+                } else if in_quotes {
+                    value_string.push(' ');
+                }
+                value_string += self.read_remaining_line()?.as_ref();
+                self.advance();
+            }
 
-            self.next_char()?;
             while let Ok(b) = self.is_at_value_line_continuation() {
                 if b {
-                    value_string += self.read_remaining_line().unwrap().to_string().as_ref();
-                    self.next_char()?;
+                    value_string += self.read_remaining_line()?.to_string().as_ref();
+                    self.advance();
                 } else {
                     break;
                 }
             }
+            // Normalize single quoted text strings to double quotes, where `''` stands for a
+            // quote and a double quote is written `""`
+            if self.dialect.single_quoted_strings()
+                && value_string.len() >= 2
+                && value_string.starts_with('\'')
+                && value_string.ends_with('\'')
+            {
+                value_string = format!(
+                    "\"{}\"",
+                    value_string[1..(value_string.len() - 1)]
+                        .replace("''", "'")
+                        .replace('"', "\"\"")
+                );
+            }
+
+            let mut value = Value::new(&value_string);
+            value.quoted_symbols = !self.dialect.single_quoted_strings();
+            if value.value_type == ValueType::Undetermined
+                && value.value_raw.contains(' ')
+                && !value.value_raw.starts_with('{')
+            {
+                self.violation("Unquoted string value contains spaces", start)?;
+            }
             Ok(KeyValuePair {
                 key: key_res,
-                value: Value::new(&value_string),
+                value,
             })
         }
     }
 
+    /// Checks that the name given on an END_GROUP or END_OBJECT, if any, matches the block
+    fn check_block_end(
+        &mut self,
+        end: &KeyValuePair,
+        name: &str,
+        start: usize,
+    ) -> Result<(), Error> {
+        let end_name = end.value.value_raw.trim_matches('"');
+        if !end_name.is_empty() && end_name != name {
+            self.violation(&format!("{} doesn't close {}", end_name, name), start)?;
+        }
+        Ok(())
+    }
+
     pub fn read_group(&mut self) -> Result<Group, Error> {
         if self.is_eof() {
             Err(Error::Eof)
@@ -615,27 +1129,56 @@ impl PvlReader {
             )))
         } else {
             let group_start = self.read_key_value_pair_raw()?;
+            self.read_group_body(group_start.value.parse_flag()?)
+        }
+    }
 
-            let mut group = Group {
-                name: group_start.value.parse_flag()?,
-                properties: vec![],
-            };
-
-            while !self.is_eof() {
-                if !self.is_blank_line()? {
-                    let kvp = self.read_key_value_pair_raw()?;
+    /// Reads the statements of a group through its END_GROUP
+    fn read_group_body(&mut self, name: String) -> Result<Group, Error> {
+        let mut group = Group {
+            name,
+            properties: Entries::new(),
+            lookup: Lookup::default(),
+        };
 
-                    match &kvp.key {
-                        Symbol::GroupEnd => break,
-                        _ => group.properties.push(kvp),
+        while !self.is_eof() {
+            if self.is_at_comment_line()? {
+                self.skip_comment_line()?;
+            } else if !self.is_blank_line()? {
+                let start = self.pos;
+                let kvp = self.read_key_value_pair_raw()?;
+
+                match &kvp.key {
+                    Symbol::GroupEnd => {
+                        self.check_block_end(&kvp, &group.name, start)?;
+                        return Ok(group);
                     }
-                } else {
-                    self.next_char()?;
+                    Symbol::End | Symbol::ObjectEnd => {
+                        // Leave the unexpected statement to close whatever encloses the group
+                        self.pos = start;
+                        self.violation(
+                            &format!("GROUP {} is missing its END_GROUP", group.name),
+                            start,
+                        )?;
+                        return Ok(group);
+                    }
+                    Symbol::Group | Symbol::Object => {
+                        self.violation("GROUP cannot contain a GROUP or OBJECT", start)?;
+                        let nested = self.read_group_body(kvp.value.value_raw.clone())?;
+                        group.properties.extend(nested.properties);
+                    }
+                    _ => group.properties.push(kvp),
                 }
+            } else {
+                self.skip_to_next_line();
             }
-
-            Ok(group)
         }
+
+        self.violation(
+            &format!("GROUP {} is missing its END_GROUP", group.name),
+            self.pos,
+        )?;
+        Ok(group)
     }
 
     pub fn read_object(&mut self) -> Result<Object, Error> {
@@ -647,35 +1190,96 @@ impl PvlReader {
             )))
         } else {
             let object_start = self.read_key_value_pair_raw()?;
+            self.read_object_body(object_start.value.parse_flag()?)
+        }
+    }
 
-            let mut object: Object = Object {
-                name: object_start.value.parse_flag()?,
-                properties: vec![],
-            };
-
-            while !self.is_eof() {
-                if !self.is_blank_line()? {
-                    let kvp = self.read_key_value_pair_raw()?;
+    /// Reads the statements of an object, including any nested groups and objects, through
+    /// its END_OBJECT
+    fn read_object_body(&mut self, name: String) -> Result<Object, Error> {
+        let mut object: Object = Object {
+            name,
+            properties: Entries::new(),
+            groups: Entries::new(),
+            objects: Entries::new(),
+            lookup: Lookup::default(),
+        };
 
-                    match &kvp.key {
-                        Symbol::ObjectEnd => break,
-                        _ => object.properties.push(kvp),
+        while !self.is_eof() {
+            if self.is_at_comment_line()? {
+                self.skip_comment_line()?;
+            } else if !self.is_blank_line()? {
+                let start = self.pos;
+                let kvp = self.read_key_value_pair_raw()?;
+
+                match &kvp.key {
+                    Symbol::ObjectEnd => {
+                        self.check_block_end(&kvp, &object.name, start)?;
+                        return Ok(object);
                     }
-                } else {
-                    self.next_char()?;
+                    Symbol::End => {
+                        // Leave END to finish the label
+                        self.pos = start;
+                        self.violation(
+                            &format!("OBJECT {} is missing its END_OBJECT", object.name),
+                            start,
+                        )?;
+                        return Ok(object);
+                    }
+                    Symbol::GroupEnd => {
+                        self.violation(
+                            &format!("END_GROUP without a GROUP in OBJECT {}", object.name),
+                            start,
+                        )?;
+                    }
+                    Symbol::Group => object
+                        .groups
+                        .push(self.read_group_body(kvp.value.parse_flag()?)?),
+                    Symbol::Object => object
+                        .objects
+                        .push(self.read_object_body(kvp.value.parse_flag()?)?),
+                    _ => object.properties.push(kvp),
                 }
+            } else {
+                self.skip_to_next_line();
             }
-
-            Ok(object)
         }
+
+        self.violation(
+            &format!("OBJECT {} is missing its END_OBJECT", object.name),
+            self.pos,
+        )?;
+        Ok(object)
     }
 }
 
+/// Whether a value is within a quoted string, and how many sequences or sets are open, at the
+/// end of the text. Single quotes are only considered if `single_quotes` is set.
+fn value_nesting(s: &str, single_quotes: bool) -> (bool, i32) {
+    let mut quote: Option<char> = None;
+    let mut depth = 0;
+    s.chars().for_each(|c| match (quote, c) {
+        (Some(q), _) if c == q => quote = None,
+        (Some(_), _) => {}
+        (None, '"') => quote = Some(c),
+        (None, '\'') if single_quotes => quote = Some(c),
+        (None, '(') | (None, '{') => depth += 1,
+        (None, ')') | (None, '}') => depth -= 1,
+        _ => {}
+    });
+    (quote.is_some(), depth)
+}
+
 /// The primary user-facing PVL structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pvl {
-    pub properties: Vec<KeyValuePair>,
-    pub groups: Vec<Group>,
-    pub objects: Vec<Object>,
+    #[cfg_attr(feature = "serde", serde(with = "ser::properties"))]
+    pub properties: Entries<KeyValuePair>,
+    pub groups: Entries<Group>,
+    pub objects: Entries<Object>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lookup: Lookup,
 }
 
 impl Pvl {
@@ -700,10 +1304,18 @@ impl Pvl {
     ///
     /// ```
     pub fn load(file_path: &Path) -> Result<Self, Error> {
+        Ok(Pvl::load_with_mode(file_path, ParseMode::Lenient)?.0)
+    }
+
+    /// Loads and parses a PVL file with the requested strictness. See `Pvl::parse`
+    pub fn load_with_mode(
+        file_path: &Path,
+        mode: ParseMode,
+    ) -> Result<(Self, Vec<Warning>), Error> {
         match fs::read(file_path) {
             Ok(b) => match String::from_utf8_lossy(&b) {
-                Cow::Borrowed(s) => Pvl::from_string(s),
-                Cow::Owned(s) => Pvl::from_string(&s),
+                Cow::Borrowed(s) => Pvl::parse(s, mode),
+                Cow::Owned(s) => Pvl::parse(&s, mode),
             },
             Err(why) => Err(Error::General(t!(why))),
         }
@@ -730,54 +1342,127 @@ impl Pvl {
     /// }
     /// ```
     pub fn from_string(content: &str) -> Result<Self, Error> {
+        Ok(Pvl::parse(content, ParseMode::Lenient)?.0)
+    }
+
+    /// Parses the contents of a supplied PVL-formatted String with the requested strictness.
+    /// Returns the warnings for any violations recovered from when parsing leniently.
+    /// # Example
+    /// ```
+    /// use vicar::pvl::{ParseMode, Pvl};
+    ///
+    /// let s = "GROUP = A\r\n  KEY = UNQUOTED TEXT\r\nEND_GROUP = A\r\nEND\r\n";
+    /// assert!(Pvl::parse(s, ParseMode::Strict).is_err());
+    ///
+    /// let (pvl, warnings) = Pvl::parse(s, ParseMode::Lenient).unwrap();
+    /// assert!(pvl.get_group("A").is_some());
+    /// assert_eq!(warnings.len(), 1);
+    /// ```
+    pub fn parse(content: &str, mode: ParseMode) -> Result<(Self, Vec<Warning>), Error> {
+        Pvl::parse_as(content, Dialect::detect(content), mode)
+    }
+
+    /// Parses the contents of a supplied PVL-formatted String as the requested dialect, rather
+    /// than detecting it. See `Pvl::parse`
+    pub fn parse_as(
+        content: &str,
+        dialect: Dialect,
+        mode: ParseMode,
+    ) -> Result<(Self, Vec<Warning>), Error> {
         let mut pvl = Pvl {
-            properties: vec![],
-            groups: vec![],
-            objects: vec![],
+            properties: Entries::new(),
+            groups: Entries::new(),
+            objects: Entries::new(),
+            lookup: Lookup::default(),
         };
 
-        let mut reader = PvlReader::new(content);
-
-        while !reader.is_eof() && !reader.is_at_end() {
-            if reader.is_at_multiline_comment_start().unwrap() {
-                let _ = reader.skip_multiline_comment().unwrap();
-            } else if reader.is_at_line_start().unwrap() && !reader.is_blank_line().unwrap() {
-                if reader.is_at_group().unwrap() {
-                    pvl.groups.push(reader.read_group().unwrap());
-                } else if reader.is_at_object().unwrap() {
-                    pvl.objects.push(reader.read_object().unwrap());
-                } else if let Ok(kvp) = reader.read_key_value_pair_raw() {
-                    if kvp.key == Symbol::End {
-                        break;
-                    } else {
-                        pvl.properties.push(kvp.clone())
+        let mut reader = PvlReader::new_with_dialect(content, dialect, mode);
+        match reader
+            .check_line_endings()
+            .and_then(|_| pvl.read_statements(&mut reader))
+        {
+            Ok(_) => Ok((pvl, reader.warnings)),
+            Err(why) => Err(reader.locate(why)),
+        }
+    }
+
+    /// Reads top level statements, groups and objects through to the END statement
+    fn read_statements(&mut self, reader: &mut PvlReader) -> Result<(), Error> {
+        while !reader.is_eof() {
+            if reader.is_at_end() {
+                return Ok(());
+            } else if reader.is_at_multiline_comment_start()? {
+                reader.skip_multiline_comment()?;
+            } else if reader.is_at_comment_line()? {
+                reader.skip_comment_line()?;
+                continue;
+            } else if reader.is_at_line_start()? && !reader.is_blank_line()? {
+                if reader.is_at_group()? {
+                    self.groups.push(reader.read_group()?);
+                } else if reader.is_at_object()? {
+                    self.objects.push(reader.read_object()?);
+                } else {
+                    let start = reader.pos;
+                    let kvp = reader.read_key_value_pair_raw()?;
+                    match kvp.key {
+                        Symbol::End => return Ok(()),
+                        Symbol::GroupEnd | Symbol::ObjectEnd => reader.violation(
+                            "END_GROUP or END_OBJECT without a GROUP or OBJECT",
+                            start,
+                        )?,
+                        _ => self.properties.push(kvp),
                     }
                 }
+                // Each of the reads above leaves us at the start of the following line
+                continue;
+            } else {
+                reader.advance();
             }
-            if !reader.is_eof() && !reader.is_at_end() {
-                reader.jump_to_next_line()?;
-            }
+            reader.jump_to_next_line()?;
         }
-        Ok(pvl)
+        reader.violation("Missing END statement", reader.pos)
     }
 
-    pub fn has_property(&self, name: &str) -> bool {
-        self.get_property(name).is_some()
+    /// Retrieves a top-level property by namespace and name, e.g. ("MSL", "LOCAL_MEAN_SOLAR_TIME")
+    pub fn get_namespaced_property(&self, namespace: &str, name: &str) -> Option<&KeyValuePair> {
+        self.properties
+            .iter()
+            .find(|p| p.key.matches(Some(namespace), name))
     }
 
-    pub fn get_property(&self, name: &str) -> Option<&KeyValuePair> {
-        self.properties.iter().find(|p| match &p.key {
-            Symbol::Key(n) | Symbol::Pointer(n) => n == name,
-            _ => false,
-        })
+    /// Retrieves a top-level property by namespace and name, falling back to the same name
+    /// without a namespace if the namespaced property isn't present.
+    pub fn get_namespaced_property_or_bare(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Option<&KeyValuePair> {
+        self.get_namespaced_property(namespace, name)
+            .or_else(|| self.properties.iter().find(|p| p.key.matches(None, name)))
     }
 
-    pub fn get_group(&self, name: &str) -> Option<&Group> {
-        self.groups.iter().find(|g| g.name() == name)
+    /// Retrieves all top-level properties within the requested namespace
+    pub fn get_namespace_properties(&self, namespace: &str) -> Vec<&KeyValuePair> {
+        self.properties
+            .iter()
+            .filter(|p| p.key.namespace().as_deref() == Some(namespace))
+            .collect()
     }
 
-    pub fn get_object(&self, name: &str) -> Option<&Object> {
-        self.objects.iter().find(|o| o.name() == name)
+    /// Lists the distinct namespaces used by keys anywhere in the label, in order of first use
+    pub fn namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = vec![];
+        self.properties
+            .iter()
+            .chain(self.groups.iter().flat_map(|g| g.properties.iter()))
+            .chain(self.objects.iter().flat_map(|o| o.properties.iter()))
+            .filter_map(|p| p.key.namespace())
+            .for_each(|ns| {
+                if !namespaces.contains(&ns) {
+                    namespaces.push(ns);
+                }
+            });
+        namespaces
     }
 }
 
@@ -0,0 +1,459 @@
+//! Construction and editing of labels in code, e.g. for derived products. Labels built here
+//! are written with `PvlWriter` like any parsed label. See `pvl!` for building a whole label
+//! at once.
+use super::{
+    index::Lookup, writer::format_value, Entries, Error, Group, KeyValuePair, Object, Pvl, Symbol,
+    Value,
+};
+use crate::time::PdsTime;
+use crate::units::ValueUnits;
+
+/// Formats a float such that it is parsed as a float again, i.e. always with a decimal point
+pub(crate) fn float_text(v: f64) -> String {
+    with_decimal_point(format!("{:?}", v), v.is_finite())
+}
+
+fn with_decimal_point(s: String, is_finite: bool) -> String {
+    if s.contains('.') || !is_finite {
+        s
+    } else {
+        match s.find('e') {
+            Some(i) => format!("{}.0{}", &s[..i], &s[i..]),
+            None => format!("{}.0", s),
+        }
+    }
+}
+
+/// The symbol for a property key. Keys starting with `^` are pointers.
+pub(crate) fn key_symbol(key: &str) -> Symbol {
+    if key.starts_with('^') {
+        Symbol::Pointer(key.to_owned())
+    } else {
+        Symbol::Key(key.to_owned())
+    }
+}
+
+impl Value {
+    /// Constructs an unquoted symbolic value, e.g. `PDS3` or `UNSIGNED_INTEGER`
+    pub fn symbol(s: &str) -> Self {
+        Value::new(s)
+    }
+
+    /// Attaches units of measure to the value, e.g. `Value::from(137.6).with_units("ms")`
+    pub fn with_units<U: Into<ValueUnits>>(mut self, units: U) -> Self {
+        self.units = Some(units.into());
+        self
+    }
+}
+
+/// Implements conversion to a Value for numeric types
+macro_rules! impl_value_from_number {
+    ($($type:ty),+) => {
+        $(
+            impl From<$type> for Value {
+                fn from(v: $type) -> Self {
+                    Value::new(&v.to_string())
+                }
+            }
+        )+
+    };
+}
+
+impl_value_from_number!(u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+impl From<f32> for Value {
+    fn from(v: f32) -> Self {
+        Value::new(&with_decimal_point(format!("{:?}", v), v.is_finite()))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::new(&float_text(v))
+    }
+}
+
+/// Booleans are written as the quoted strings `"TRUE"` and `"FALSE"`
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::new(if v { "\"TRUE\"" } else { "\"FALSE\"" })
+    }
+}
+
+/// Text is written as a quoted string. See `Value::symbol` for unquoted values.
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::new(&format!("\"{}\"", s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::from(s.as_str())
+    }
+}
+
+impl From<&PdsTime> for Value {
+    fn from(t: &PdsTime) -> Self {
+        Value::new(&t.to_calendar_string(3))
+    }
+}
+
+impl From<PdsTime> for Value {
+    fn from(t: PdsTime) -> Self {
+        Value::from(&t)
+    }
+}
+
+/// Elements are written as a sequence, each keeping any units of its own
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(elements: Vec<T>) -> Self {
+        Value::new(&format!(
+            "({})",
+            elements
+                .into_iter()
+                .map(|e| format_value(&e.into()))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ))
+    }
+}
+
+impl<T: Into<Value>, const N: usize> From<[T; N]> for Value {
+    fn from(elements: [T; N]) -> Self {
+        Value::from(Vec::from(elements))
+    }
+}
+
+/// Implements conversion of tuples to sequences of mixed types, e.g. `("PRODUCT.IMG", 2)`
+macro_rules! impl_value_from_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Into<Value>),+> From<($($name,)+)> for Value {
+            #[allow(non_snake_case)]
+            fn from(($($name,)+): ($($name,)+)) -> Self {
+                Value::from(vec![$($name.into()),+] as Vec<Value>)
+            }
+        }
+    };
+}
+
+impl_value_from_tuple!(A, B);
+impl_value_from_tuple!(A, B, C);
+impl_value_from_tuple!(A, B, C, D);
+
+/// Fails if `index` is past the end of a list of `len` items
+fn check_position(index: usize, len: usize) -> Result<(), Error> {
+    if index > len {
+        Err(Error::General(format!(
+            "Position {} is past the end of {} items",
+            index, len
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Moves the item at `from` such that it ends up at `to`
+fn move_item<T>(items: &mut Vec<T>, from: usize, to: usize) -> Result<(), Error> {
+    check_position(to, items.len().saturating_sub(1))?;
+    let item = items.remove(from);
+    items.insert(to, item);
+    Ok(())
+}
+
+/// Implements the editing of properties. The first property with a key is the one
+/// replaced, removed or moved, as for `get_property`.
+macro_rules! impl_edit_properties {
+    () => {
+        /// Appends a property to the end, even if the key is already present
+        pub fn add_property<V: Into<Value>>(&mut self, key: &str, value: V) {
+            self.properties.push(KeyValuePair {
+                key: key_symbol(key),
+                value: value.into(),
+            });
+        }
+
+        /// Appends a property, returning self so that calls can be chained
+        pub fn with_property<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+            self.add_property(key, value);
+            self
+        }
+
+        /// Inserts a property at a position among the properties
+        pub fn insert_property<V: Into<Value>>(
+            &mut self,
+            index: usize,
+            key: &str,
+            value: V,
+        ) -> Result<(), Error> {
+            check_position(index, self.properties.len())?;
+            self.properties.insert(
+                index,
+                KeyValuePair {
+                    key: key_symbol(key),
+                    value: value.into(),
+                },
+            );
+            Ok(())
+        }
+
+        /// Sets the value of a property, appending it if the key isn't present. Returns the
+        /// previous value, if any.
+        pub fn set_property<V: Into<Value>>(&mut self, key: &str, value: V) -> Option<Value> {
+            match self.get_property_mut(key) {
+                Some(p) => Some(std::mem::replace(&mut p.value, value.into())),
+                None => {
+                    self.add_property(key, value);
+                    None
+                }
+            }
+        }
+
+        /// Replaces the value of an existing property, returning the previous value
+        pub fn replace_property<V: Into<Value>>(
+            &mut self,
+            key: &str,
+            value: V,
+        ) -> Result<Value, Error> {
+            match self.get_property_mut(key) {
+                Some(p) => Ok(std::mem::replace(&mut p.value, value.into())),
+                None => Err(Error::General(format!("No property '{}'", key))),
+            }
+        }
+
+        /// Removes and returns a property
+        pub fn remove_property(&mut self, key: &str) -> Option<KeyValuePair> {
+            self.property_position(key)
+                .map(|i| self.properties.remove(i))
+        }
+
+        /// Moves a property to a new position among the properties
+        pub fn move_property(&mut self, key: &str, index: usize) -> Result<(), Error> {
+            match self.property_position(key) {
+                Some(i) => move_item(&mut self.properties, i, index),
+                None => Err(Error::General(format!("No property '{}'", key))),
+            }
+        }
+
+        pub fn get_property_mut(&mut self, key: &str) -> Option<&mut KeyValuePair> {
+            self.properties.iter_mut().find(|p| p.key.is_key(key))
+        }
+
+        fn property_position(&self, key: &str) -> Option<usize> {
+            self.properties.iter().position(|p| p.key.is_key(key))
+        }
+    };
+}
+
+/// Implements the editing of nested groups or objects, which are found by name
+macro_rules! impl_edit_blocks {
+    ($field:ident, $type:ty, $add:ident, $with:ident, $insert:ident, $replace:ident, $remove:ident, $move:ident, $get_mut:ident) => {
+        pub fn $add(&mut self, block: $type) {
+            self.$field.push(block);
+        }
+
+        pub fn $with(mut self, block: $type) -> Self {
+            self.$add(block);
+            self
+        }
+
+        pub fn $insert(&mut self, index: usize, block: $type) -> Result<(), Error> {
+            check_position(index, self.$field.len())?;
+            self.$field.insert(index, block);
+            Ok(())
+        }
+
+        /// Replaces the block of the same name, returning the previous one
+        pub fn $replace(&mut self, block: $type) -> Result<$type, Error> {
+            match self.$get_mut(&block.name) {
+                Some(b) => Ok(std::mem::replace(b, block)),
+                None => Err(Error::General(format!("No block named '{}'", block.name))),
+            }
+        }
+
+        pub fn $remove(&mut self, name: &str) -> Option<$type> {
+            self.$field
+                .iter()
+                .position(|b| b.name == name)
+                .map(|i| self.$field.remove(i))
+        }
+
+        pub fn $move(&mut self, name: &str, index: usize) -> Result<(), Error> {
+            match self.$field.iter().position(|b| b.name == name) {
+                Some(i) => move_item(&mut self.$field, i, index),
+                None => Err(Error::General(format!("No block named '{}'", name))),
+            }
+        }
+
+        pub fn $get_mut(&mut self, name: &str) -> Option<&mut $type> {
+            self.$field.iter_mut().find(|b| b.name == name)
+        }
+    };
+}
+
+impl Pvl {
+    /// Constructs an empty label
+    pub fn new() -> Self {
+        Pvl {
+            properties: Entries::new(),
+            groups: Entries::new(),
+            objects: Entries::new(),
+            lookup: Lookup::default(),
+        }
+    }
+
+    impl_edit_properties! {}
+    impl_edit_blocks!(
+        groups,
+        Group,
+        add_group,
+        with_group,
+        insert_group,
+        replace_group,
+        remove_group,
+        move_group,
+        get_group_mut
+    );
+    impl_edit_blocks!(
+        objects,
+        Object,
+        add_object,
+        with_object,
+        insert_object,
+        replace_object,
+        remove_object,
+        move_object,
+        get_object_mut
+    );
+}
+
+impl Default for Pvl {
+    fn default() -> Self {
+        Pvl::new()
+    }
+}
+
+impl Group {
+    /// Constructs an empty group
+    pub fn new(name: &str) -> Self {
+        Group {
+            name: name.to_owned(),
+            properties: Entries::new(),
+            lookup: Lookup::default(),
+        }
+    }
+
+    impl_edit_properties! {}
+}
+
+impl Object {
+    /// Constructs an empty object
+    pub fn new(name: &str) -> Self {
+        Object {
+            name: name.to_owned(),
+            properties: Entries::new(),
+            groups: Entries::new(),
+            objects: Entries::new(),
+            lookup: Lookup::default(),
+        }
+    }
+
+    impl_edit_properties! {}
+    impl_edit_blocks!(
+        groups,
+        Group,
+        add_group,
+        with_group,
+        insert_group,
+        replace_group,
+        remove_group,
+        move_group,
+        get_group_mut
+    );
+    impl_edit_blocks!(
+        objects,
+        Object,
+        add_object,
+        with_object,
+        insert_object,
+        replace_object,
+        remove_object,
+        move_object,
+        get_object_mut
+    );
+}
+
+/// Builds a label from statements written much as they appear in PVL text. Values are
+/// anything convertible to a `Value`, and units follow a `=>`. Pointers start with `^` and
+/// namespaced keys are written `MSL:KEY`. Keys which aren't valid identifiers may be given
+/// as string literals. Statements are separated by commas.
+/// # Example
+/// ```
+/// use vicar::pvl;
+/// use vicar::pvl::Value;
+///
+/// let label = pvl! {
+///     PDS_VERSION_ID = Value::symbol("PDS3"),
+///     ^IMAGE = "PRODUCT.IMG",
+///     MSL:LOCAL_MEAN_SOLAR_TIME = "Sol-03423M14:36:39.612",
+///     GROUP INSTRUMENT_STATE_PARMS {
+///         EXPOSURE_DURATION = 137.6 => "ms",
+///         FILTER_NAME = Value::symbol("N/A"),
+///     }
+///     OBJECT IMAGE {
+///         LINES = 1200,
+///         BANDS = 3,
+///     }
+/// };
+/// assert!(label.to_pvl_string().contains("EXPOSURE_DURATION               = 137.6 <ms>"));
+/// ```
+#[macro_export]
+macro_rules! pvl {
+    (@entries $mode:ident $target:ident;) => {};
+    (@entries $mode:ident $target:ident; , $($rest:tt)*) => {
+        $crate::pvl!(@entries $mode $target; $($rest)*);
+    };
+    (@entries items $target:ident; GROUP $name:ident { $($body:tt)* } $($rest:tt)*) => {
+        let mut group = $crate::pvl::Group::new(stringify!($name));
+        $crate::pvl!(@entries props group; $($body)*);
+        $target.add_group(group);
+        $crate::pvl!(@entries items $target; $($rest)*);
+    };
+    (@entries items $target:ident; OBJECT $name:ident { $($body:tt)* } $($rest:tt)*) => {
+        let mut object = $crate::pvl::Object::new(stringify!($name));
+        $crate::pvl!(@entries items object; $($body)*);
+        $target.add_object(object);
+        $crate::pvl!(@entries items $target; $($rest)*);
+    };
+    (@entries $mode:ident $target:ident; ^ $key:ident = $value:expr $(=> $units:expr)? $(, $($rest:tt)*)?) => {
+        $crate::pvl!(@property $target; concat!("^", stringify!($key)), $value $(, $units)?);
+        $crate::pvl!(@entries $mode $target; $($($rest)*)?);
+    };
+    (@entries $mode:ident $target:ident; $ns:ident : $key:ident = $value:expr $(=> $units:expr)? $(, $($rest:tt)*)?) => {
+        $crate::pvl!(@property $target; concat!(stringify!($ns), ":", stringify!($key)), $value $(, $units)?);
+        $crate::pvl!(@entries $mode $target; $($($rest)*)?);
+    };
+    (@entries $mode:ident $target:ident; $key:ident = $value:expr $(=> $units:expr)? $(, $($rest:tt)*)?) => {
+        $crate::pvl!(@property $target; stringify!($key), $value $(, $units)?);
+        $crate::pvl!(@entries $mode $target; $($($rest)*)?);
+    };
+    (@entries $mode:ident $target:ident; $key:literal = $value:expr $(=> $units:expr)? $(, $($rest:tt)*)?) => {
+        $crate::pvl!(@property $target; $key, $value $(, $units)?);
+        $crate::pvl!(@entries $mode $target; $($($rest)*)?);
+    };
+    (@entries $mode:ident $target:ident; $($rest:tt)*) => {
+        compile_error!(concat!("Unexpected PVL statement: ", stringify!($($rest)*)))
+    };
+    (@property $target:ident; $key:expr, $value:expr) => {
+        $target.add_property($key, $crate::pvl::Value::from($value));
+    };
+    (@property $target:ident; $key:expr, $value:expr, $units:expr) => {
+        $target.add_property($key, $crate::pvl::Value::from($value).with_units($units));
+    };
+    ($($body:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut pvl = $crate::pvl::Pvl::new();
+        $crate::pvl!(@entries items pvl; $($body)*);
+        pvl
+    }};
+}
@@ -0,0 +1,350 @@
+use super::{Error, Pvl, Value};
+use std::{fmt, fs, path::Path};
+
+/// A single node of a PVL concrete syntax tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// Anything which isn't a statement: whitespace, line endings, comments and the padding
+    /// following END. Kept verbatim.
+    Trivia(String),
+    Statement(Statement),
+}
+
+/// A `KEY = VALUE` statement, or a bare END, END_GROUP or END_OBJECT, as written in the source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub key: String,
+    /// Everything between the key and the value, including the alignment padding and `=`
+    pub separator: String,
+    /// The value text, including any quotes, units and continuation lines
+    pub value_raw: String,
+}
+
+impl Statement {
+    /// Parses the raw value text
+    pub fn value(&self) -> Value {
+        Value::new(&self.value_raw)
+    }
+
+    fn is_key(&self, key: &str) -> bool {
+        self.key.eq_ignore_ascii_case(key)
+    }
+
+    /// Name of the GROUP or OBJECT opened or closed by this statement
+    fn block_name(&self) -> String {
+        self.value_raw
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_owned()
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.key, self.separator, self.value_raw)
+    }
+}
+
+/// A lossless representation of PVL text. Comments, alignment, line endings and record padding
+/// are retained so that writing an unmodified document reproduces the source byte-for-byte, and
+/// editing a value changes only that value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvlDocument {
+    pub nodes: Vec<Node>,
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+/// Byte offset of the end of the trivia (whitespace, comments and ISIS `#` comment lines)
+/// starting at `pos`
+fn scan_trivia(text: &str, mut pos: usize) -> Result<usize, Error> {
+    let b = text.as_bytes();
+    while pos < b.len() {
+        if is_whitespace(b[pos]) {
+            pos += 1;
+        } else if text[pos..].starts_with("/*") {
+            match text[pos + 2..].find("*/") {
+                Some(end) => pos += end + 4,
+                None => {
+                    return Err(Error::Syntax("Unterminated comment".to_owned()).at(
+                        text,
+                        pos,
+                        text.len(),
+                    ))
+                }
+            }
+        } else if b[pos] == b'#' {
+            // ISIS line comment
+            pos += text[pos..].find('\n').unwrap_or(text.len() - pos);
+        } else {
+            break;
+        }
+    }
+    Ok(pos)
+}
+
+/// Byte offset of the end of the value starting at `pos`. A value ends at the first line
+/// ending or comment which isn't within quotes or an open sequence or set.
+fn scan_value(text: &str, mut pos: usize) -> Result<usize, Error> {
+    let start = pos;
+    let b = text.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut depth = 0;
+    while pos < b.len() {
+        match (quote, b[pos]) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'"') | (None, b'\'') => quote = Some(b[pos]),
+            (None, b'(') | (None, b'{') => depth += 1,
+            (None, b')') | (None, b'}') => depth -= 1,
+            (None, b'\r') | (None, b'\n') if depth <= 0 => break,
+            (None, b'/') if depth <= 0 && text[pos..].starts_with("/*") => break,
+            _ => {}
+        }
+        pos += 1;
+    }
+    if quote.is_some() || depth > 0 {
+        Err(Error::Syntax("Unterminated value".to_owned()).at(text, start, pos))
+    } else {
+        // Trailing whitespace before a comment or line ending belongs to the trivia
+        Ok(start + text[start..pos].trim_end().len())
+    }
+}
+
+impl PvlDocument {
+    /// Loads a PVL file from the requested file path, retaining all of its text
+    pub fn load(file_path: &Path) -> Result<Self, Error> {
+        match fs::read(file_path) {
+            Ok(b) => match String::from_utf8(b) {
+                Ok(s) => PvlDocument::from_string(&s),
+                Err(why) => Err(Error::InvalidEncoding(t!(why))),
+            },
+            Err(why) => Err(Error::General(t!(why))),
+        }
+    }
+
+    /// Parses PVL text into a concrete syntax tree
+    /// # Example
+    /// ```
+    /// use vicar::pvl::cst::PvlDocument;
+    ///
+    /// let text = "/* Comment */\r\nKEY     = 1\r\nEND\r\n    ";
+    /// let doc = PvlDocument::from_string(text).unwrap();
+    /// assert_eq!(doc.to_string(), text);
+    /// ```
+    pub fn from_string(content: &str) -> Result<Self, Error> {
+        let b = content.as_bytes();
+        let mut nodes = vec![];
+        let mut pos = 0;
+
+        while pos < b.len() {
+            let trivia_end = scan_trivia(content, pos)?;
+            if trivia_end > pos {
+                nodes.push(Node::Trivia(content[pos..trivia_end].to_owned()));
+                pos = trivia_end;
+            }
+            if pos >= b.len() {
+                break;
+            }
+
+            let key_end = pos
+                + content[pos..]
+                    .find(|c: char| c == '=' || c.is_whitespace() || c == '\0')
+                    .unwrap_or(content.len() - pos);
+            let key = &content[pos..key_end];
+
+            // The separator runs through the equals sign and the spaces following it
+            let mut sep_end = key_end;
+            while sep_end < b.len() && (b[sep_end] == b' ' || b[sep_end] == b'\t') {
+                sep_end += 1;
+            }
+
+            if sep_end >= b.len() || b[sep_end] != b'=' {
+                // Block ends may omit the name of the block they close, as ISIS labels do
+                if ["END_GROUP", "END_OBJECT"]
+                    .iter()
+                    .any(|k| key.eq_ignore_ascii_case(k))
+                {
+                    nodes.push(Node::Statement(Statement {
+                        key: key.to_owned(),
+                        separator: "".to_owned(),
+                        value_raw: "".to_owned(),
+                    }));
+                    pos = key_end;
+                    continue;
+                }
+                if key.eq_ignore_ascii_case("END") {
+                    nodes.push(Node::Statement(Statement {
+                        key: key.to_owned(),
+                        separator: "".to_owned(),
+                        value_raw: "".to_owned(),
+                    }));
+                    // Anything after END, typically record padding, is retained untouched
+                    if key_end < b.len() {
+                        nodes.push(Node::Trivia(content[key_end..].to_owned()));
+                    }
+                    break;
+                }
+                return Err(Error::Syntax(format!("Expected '=' after '{}'", key))
+                    .at(content, pos, key_end));
+            }
+
+            sep_end += 1;
+            while sep_end < b.len() && is_whitespace(b[sep_end]) {
+                sep_end += 1;
+            }
+
+            let value_end = scan_value(content, sep_end)?;
+            nodes.push(Node::Statement(Statement {
+                key: key.to_owned(),
+                separator: content[key_end..sep_end].to_owned(),
+                value_raw: content[sep_end..value_end].to_owned(),
+            }));
+            pos = value_end;
+        }
+
+        Ok(PvlDocument { nodes })
+    }
+
+    /// Writes the document text to the requested file path
+    pub fn save(&self, file_path: &Path) -> Result<(), Error> {
+        match fs::write(file_path, self.to_string()) {
+            Ok(_) => Ok(()),
+            Err(why) => Err(Error::General(t!(why))),
+        }
+    }
+
+    /// Parses the document into the regular PVL structure
+    pub fn to_pvl(&self) -> Result<Pvl, Error> {
+        Pvl::from_string(&self.to_string())
+    }
+
+    /// Iterates over the statements of the document, in order
+    pub fn statements(&self) -> impl Iterator<Item = &Statement> {
+        self.nodes.iter().filter_map(|n| match n {
+            Node::Statement(s) => Some(s),
+            Node::Trivia(_) => None,
+        })
+    }
+
+    /// The text of each comment in the document, including the delimiters
+    pub fn comments(&self) -> Vec<&str> {
+        let mut comments = vec![];
+        self.nodes.iter().for_each(|n| {
+            if let Node::Trivia(t) = n {
+                let mut rest = t.as_str();
+                while let Some(start) = rest.find("/*") {
+                    let end = rest[start..].find("*/").map(|e| start + e + 2).unwrap();
+                    comments.push(&rest[start..end]);
+                    rest = &rest[end..];
+                }
+            }
+        });
+        comments
+    }
+
+    /// Index within `nodes` of the statement with the given key in the GROUP or OBJECT at
+    /// `scope`. An empty scope refers to the top level of the document.
+    fn find_statement(&self, scope: &[&str], key: &str) -> Option<usize> {
+        let mut stack: Vec<String> = vec![];
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let Node::Statement(s) = node {
+                if ["GROUP", "OBJECT", "BEGIN_GROUP", "BEGIN_OBJECT"]
+                    .iter()
+                    .any(|k| s.is_key(k))
+                {
+                    stack.push(s.block_name());
+                } else if s.is_key("END_GROUP") || s.is_key("END_OBJECT") {
+                    stack.pop();
+                } else if s.is_key(key)
+                    && stack.len() == scope.len()
+                    && stack
+                        .iter()
+                        .zip(scope.iter())
+                        .all(|(a, b)| a.eq_ignore_ascii_case(b))
+                {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    }
+
+    /// Retrieves the statement with the given key at the top level of the document
+    pub fn get_statement(&self, key: &str) -> Option<&Statement> {
+        self.get_statement_in(&[], key)
+    }
+
+    /// Retrieves the statement with the given key within a GROUP or OBJECT. Nested blocks are
+    /// addressed by listing their names from the outermost, e.g. `&["IMAGE", "HISTOGRAM"]`
+    pub fn get_statement_in(&self, scope: &[&str], key: &str) -> Option<&Statement> {
+        match self.find_statement(scope, key).map(|i| &self.nodes[i]) {
+            Some(Node::Statement(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Replaces the raw value text of a top-level statement. See `set_value_in`
+    pub fn set_value(&mut self, key: &str, value_raw: &str) -> Result<(), Error> {
+        self.set_value_in(&[], key, value_raw)
+    }
+
+    /// Replaces the raw value text of a statement within a GROUP or OBJECT, leaving the rest of
+    /// the document untouched. If the document ends in padding after END, the padding is
+    /// lengthened or shortened, where possible, to keep the overall length unchanged so that
+    /// fixed-length label records still line up.
+    pub fn set_value_in(
+        &mut self,
+        scope: &[&str],
+        key: &str,
+        value_raw: &str,
+    ) -> Result<(), Error> {
+        let i = match self.find_statement(scope, key) {
+            Some(i) => i,
+            None => {
+                return Err(Error::General(format!(
+                    "Key '{}' not found in '{}'",
+                    key,
+                    scope.join("/")
+                )))
+            }
+        };
+
+        let old_len = match &mut self.nodes[i] {
+            Node::Statement(s) => {
+                let old_len = s.value_raw.len();
+                s.value_raw = value_raw.to_owned();
+                old_len
+            }
+            Node::Trivia(_) => unreachable!(),
+        };
+        self.adjust_padding(old_len as isize - value_raw.len() as isize);
+        Ok(())
+    }
+
+    /// Grows (or shrinks) trailing space padding after END by `delta` characters
+    fn adjust_padding(&mut self, delta: isize) {
+        if let Some(Node::Trivia(padding)) = self.nodes.last_mut() {
+            let pad_char = match padding.chars().last() {
+                Some(c) if c == ' ' || c == '\0' => c,
+                _ => return,
+            };
+            let run = padding.len() - padding.trim_end_matches(pad_char).len();
+            if delta > 0 {
+                padding.push_str(&pad_char.to_string().repeat(delta as usize));
+            } else if (-delta) as usize <= run {
+                padding.truncate(padding.len() - (-delta) as usize);
+            }
+        }
+    }
+}
+
+impl fmt::Display for PvlDocument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.nodes.iter().try_for_each(|n| match n {
+            Node::Trivia(t) => write!(f, "{}", t),
+            Node::Statement(s) => write!(f, "{}", s),
+        })
+    }
+}
@@ -0,0 +1,396 @@
+//! Deserialization of PVL labels into user structs with serde. Requires the `serde` feature.
+//!
+//! The keys of a GROUP or OBJECT, including its nested GROUP and OBJECT names, map to struct
+//! fields. Pointers keep their `^`, so are reached with `#[serde(rename = "^IMAGE")]`.
+//! Sequences map to `Vec` and tuples, and units of measure are dropped.
+use super::{Error, Group, KeyValuePair, Object, Pvl, Symbol, Value, ValueType};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::General(msg.to_string())
+    }
+}
+
+/// The PDS symbolic constants which deserialize to `None`
+const NULL_CONSTANTS: [&str; 3] = ["N/A", "UNK", "NULL"];
+
+impl Value {
+    /// The value with any string quoting removed
+    fn text(&self) -> String {
+        match self.value_type {
+            ValueType::String | ValueType::Bool => self.unquoted(),
+            _ => self.value_raw.to_owned(),
+        }
+    }
+}
+
+/// Deserializes a single right-hand value
+struct ValueDeserializer(Value);
+
+/// Parses the value text as a number, for the requested visitor method
+macro_rules! deserialize_number {
+    ($fn_name:ident, $visit:ident, $type:ty) => {
+        fn $fn_name<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+            let text = self.0.text();
+            match text.trim().parse::<$type>() {
+                Ok(v) => visitor.$visit(v),
+                Err(_) => Err(de::Error::invalid_value(
+                    Unexpected::Str(&text),
+                    &stringify!($type),
+                )),
+            }
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        match self.0.value_type {
+            ValueType::Array => self.deserialize_seq(visitor),
+            ValueType::Bool => self.deserialize_bool(visitor),
+            ValueType::Integer if self.0.value_raw.parse::<i64>().is_ok() => {
+                self.deserialize_i64(visitor)
+            }
+            ValueType::Integer | ValueType::Float if self.0.value_raw.parse::<f64>().is_ok() => {
+                self.deserialize_f64(visitor)
+            }
+            _ => visitor.visit_string(self.0.text()),
+        }
+    }
+
+    fn deserialize_bool<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        let text = self.0.text();
+        if text.eq_ignore_ascii_case("TRUE") {
+            visitor.visit_bool(true)
+        } else if text.eq_ignore_ascii_case("FALSE") {
+            visitor.visit_bool(false)
+        } else {
+            Err(de::Error::invalid_value(Unexpected::Str(&text), &"bool"))
+        }
+    }
+
+    deserialize_number!(deserialize_i8, visit_i8, i8);
+    deserialize_number!(deserialize_i16, visit_i16, i16);
+    deserialize_number!(deserialize_i32, visit_i32, i32);
+    deserialize_number!(deserialize_i64, visit_i64, i64);
+    deserialize_number!(deserialize_u8, visit_u8, u8);
+    deserialize_number!(deserialize_u16, visit_u16, u16);
+    deserialize_number!(deserialize_u32, visit_u32, u32);
+    deserialize_number!(deserialize_u64, visit_u64, u64);
+    deserialize_number!(deserialize_f32, visit_f32, f32);
+    deserialize_number!(deserialize_f64, visit_f64, f64);
+
+    fn deserialize_char<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_str<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        visitor.visit_string(self.0.text())
+    }
+
+    fn deserialize_identifier<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_bytes<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        visitor.visit_byte_buf(self.0.text().into_bytes())
+    }
+
+    fn deserialize_byte_buf<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    /// The PDS constants N/A, UNK and NULL are taken as None
+    fn deserialize_option<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        let text = self.0.text();
+        if NULL_CONSTANTS.iter().any(|c| text.eq_ignore_ascii_case(c)) {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<W: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: W,
+    ) -> Result<W::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<W: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: W,
+    ) -> Result<W::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// A value which isn't a sequence is taken as a sequence of one
+    fn deserialize_seq<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        let elements = match self.0.value_type {
+            ValueType::Array => self.0.parse_array().unwrap_or_default(),
+            _ => vec![self.0],
+        };
+        visitor.visit_seq(ValueSeq(elements.into_iter()))
+    }
+
+    fn deserialize_tuple<W: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: W,
+    ) -> Result<W::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<W: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: W,
+    ) -> Result<W::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<W: Visitor<'de>>(self, _visitor: W) -> Result<W::Value, Error> {
+        Err(de::Error::invalid_type(
+            Unexpected::Str(&self.0.value_raw),
+            &"a GROUP or OBJECT",
+        ))
+    }
+
+    fn deserialize_struct<W: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: W,
+    ) -> Result<W::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Only unit variants can be represented, by their name
+    fn deserialize_enum<W: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: W,
+    ) -> Result<W::Value, Error> {
+        visitor.visit_enum(self.0.text().into_deserializer())
+    }
+
+    fn deserialize_ignored_any<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// The elements of a sequence value
+struct ValueSeq<I>(I);
+
+impl<'de, I: Iterator<Item = Value>> SeqAccess<'de> for ValueSeq<I> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.0.next() {
+            Some(v) => seed.deserialize(ValueDeserializer(v)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The contents of a label, GROUP or OBJECT
+#[derive(Clone, Copy)]
+struct Block<'a> {
+    properties: &'a [KeyValuePair],
+    groups: &'a [Group],
+    objects: &'a [Object],
+}
+
+enum Entry<'a> {
+    Value(&'a Value),
+    Block(Block<'a>),
+}
+
+impl<'a> Block<'a> {
+    /// The keys and nested blocks, in order. Only the first of any repeated key is kept.
+    fn entries(&self) -> Vec<(&'a str, Entry<'a>)> {
+        let mut entries: Vec<(&'a str, Entry<'a>)> = vec![];
+        let properties = self.properties.iter().filter_map(|p| match &p.key {
+            Symbol::Key(k) | Symbol::Pointer(k) => Some((k.as_str(), Entry::Value(&p.value))),
+            _ => None,
+        });
+        let groups = self.groups.iter().map(|g| {
+            (
+                g.name.as_str(),
+                Entry::Block(Block {
+                    properties: &g.properties,
+                    groups: &[],
+                    objects: &[],
+                }),
+            )
+        });
+        let objects = self.objects.iter().map(|o| {
+            (
+                o.name.as_str(),
+                Entry::Block(Block {
+                    properties: &o.properties,
+                    groups: &o.groups,
+                    objects: &o.objects,
+                }),
+            )
+        });
+        properties.chain(groups).chain(objects).for_each(|(k, e)| {
+            if !entries.iter().any(|(seen, _)| *seen == k) {
+                entries.push((k, e));
+            }
+        });
+        entries
+    }
+}
+
+struct BlockDeserializer<'a>(Block<'a>);
+
+impl<'de, 'a> de::Deserializer<'de> for BlockDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        visitor.visit_map(BlockMap {
+            entries: self.0.entries().into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_option<W: Visitor<'de>>(self, visitor: W) -> Result<W::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<W: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: W,
+    ) -> Result<W::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct BlockMap<'a, I> {
+    entries: I,
+    value: Option<Entry<'a>>,
+}
+
+impl<'de, 'a, I: Iterator<Item = (&'a str, Entry<'a>)>> MapAccess<'de> for BlockMap<'a, I> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, entry)) => {
+                self.value = Some(entry);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, Error> {
+        match self.value.take() {
+            Some(Entry::Value(v)) => seed.deserialize(ValueDeserializer(v.clone())),
+            Some(Entry::Block(b)) => seed.deserialize(BlockDeserializer(b)),
+            None => Err(Error::Programming(t!("Value requested before its key"))),
+        }
+    }
+}
+
+/// Deserializes a parsed label into a user struct
+/// # Example
+/// ```
+/// use serde::Deserialize;
+/// use vicar::pvl::{de, Pvl};
+///
+/// #[derive(Deserialize)]
+/// struct Image {
+///     #[serde(rename = "LINES")]
+///     lines: usize,
+///     #[serde(rename = "FILTER")]
+///     filter: (String, u8),
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Label {
+///     #[serde(rename = "^IMAGE")]
+///     image_pointer: Vec<String>,
+///     #[serde(rename = "IMAGE")]
+///     image: Image,
+/// }
+///
+/// let pvl = Pvl::from_string(
+///     "^IMAGE = (\"A.IMG\", 2)\nOBJECT = IMAGE\n  LINES = 1024\n  FILTER = (\"L0\", 0)\nEND_OBJECT = IMAGE\nEND\n",
+/// )
+/// .unwrap();
+/// let label: Label = de::from_pvl(&pvl).unwrap();
+/// assert_eq!(label.image_pointer[0], "A.IMG");
+/// assert_eq!(label.image.lines, 1024);
+/// assert_eq!(label.image.filter.0, "L0");
+/// ```
+pub fn from_pvl<T: DeserializeOwned>(pvl: &Pvl) -> Result<T, Error> {
+    T::deserialize(BlockDeserializer(Block {
+        properties: &pvl.properties,
+        groups: &pvl.groups,
+        objects: &pvl.objects,
+    }))
+}
+
+/// Parses PVL text and deserializes it into a user struct. See `from_pvl`
+pub fn from_str<T: DeserializeOwned>(content: &str) -> Result<T, Error> {
+    from_pvl(&Pvl::from_string(content)?)
+}
+
+/// Deserializes the properties of a GROUP into a user struct
+pub fn from_group<T: DeserializeOwned>(group: &Group) -> Result<T, Error> {
+    from_properties(&group.properties)
+}
+
+/// Deserializes a list of properties, such as a VICAR label section, into a user struct
+pub(crate) fn from_properties<T: DeserializeOwned>(
+    properties: &[KeyValuePair],
+) -> Result<T, Error> {
+    T::deserialize(BlockDeserializer(Block {
+        properties,
+        groups: &[],
+        objects: &[],
+    }))
+}
+
+/// Deserializes the properties, groups and objects of an OBJECT into a user struct
+pub fn from_object<T: DeserializeOwned>(object: &Object) -> Result<T, Error> {
+    T::deserialize(BlockDeserializer(Block {
+        properties: &object.properties,
+        groups: &object.groups,
+        objects: &object.objects,
+    }))
+}
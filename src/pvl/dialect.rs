@@ -0,0 +1,95 @@
+use super::Symbol;
+
+/// The variants of PVL found in planetary data labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Object Description Language, as used by products which predate PDS3
+    Odl3,
+    /// PDS3 labels, a restricted form of ODL
+    #[default]
+    Pds3,
+    /// ISIS3 cube and table labels, e.g. `Object = IsisCube` ... `End_Object`
+    Isis,
+    /// CCSDS Parameter Value Language (CCSDS 641.0-B-2)
+    Pvl,
+}
+
+impl Dialect {
+    /// Determines the dialect of a label from its content. `BEGIN_GROUP` or `BEGIN_OBJECT`
+    /// indicate CCSDS PVL, otherwise mixed-case reserved words or `#` comments indicate ISIS,
+    /// and a `PDS_VERSION_ID` indicates PDS3. Anything else is taken to be ODL.
+    pub fn detect(content: &str) -> Dialect {
+        let mut is_pds3 = false;
+        let mut is_pvl = false;
+        let mut is_isis = false;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                is_isis = true;
+                continue;
+            }
+            let keyword = line.split('=').next().unwrap_or("").trim();
+            let upper = keyword.to_uppercase();
+            match upper.as_str() {
+                "OBJECT" | "GROUP" | "END_OBJECT" | "END_GROUP" | "END" if keyword != upper => {
+                    is_isis = true
+                }
+                "BEGIN_OBJECT" | "BEGIN_GROUP" => is_pvl = true,
+                "PDS_VERSION_ID" => is_pds3 = true,
+                _ => {}
+            }
+            if upper == "END" {
+                break;
+            }
+        }
+
+        if is_pvl {
+            Dialect::Pvl
+        } else if is_isis {
+            Dialect::Isis
+        } else if is_pds3 {
+            Dialect::Pds3
+        } else {
+            Dialect::Odl3
+        }
+    }
+
+    /// Whether reserved words such as `END_GROUP` are recognized regardless of case
+    pub fn case_insensitive_keywords(&self) -> bool {
+        matches!(self, Dialect::Isis | Dialect::Pvl)
+    }
+
+    /// Whether `BEGIN_GROUP` and `BEGIN_OBJECT` may be used to open blocks
+    pub fn allows_begin_blocks(&self) -> bool {
+        matches!(self, Dialect::Odl3 | Dialect::Pvl)
+    }
+
+    /// Whether `#` starts a comment running to the end of the line
+    pub fn allows_line_comments(&self) -> bool {
+        matches!(self, Dialect::Isis)
+    }
+
+    /// Whether single quotes delimit text strings. In ODL and PDS3 they delimit symbols.
+    pub fn single_quoted_strings(&self) -> bool {
+        matches!(self, Dialect::Isis | Dialect::Pvl)
+    }
+
+    /// Determines which reserved word, if any, the statement keyword is
+    pub fn reserved_symbol(&self, keyword: &str) -> Option<Symbol> {
+        let keyword = if self.case_insensitive_keywords() {
+            keyword.to_uppercase()
+        } else {
+            keyword.to_owned()
+        };
+        match keyword.as_str() {
+            "GROUP" => Some(Symbol::Group),
+            "OBJECT" => Some(Symbol::Object),
+            "BEGIN_GROUP" if self.allows_begin_blocks() => Some(Symbol::Group),
+            "BEGIN_OBJECT" if self.allows_begin_blocks() => Some(Symbol::Object),
+            "END_GROUP" => Some(Symbol::GroupEnd),
+            "END_OBJECT" => Some(Symbol::ObjectEnd),
+            "END" => Some(Symbol::End),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use super::{Group, KeyValuePair, Object, Pvl, Symbol};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::RwLock;
+
+/// How keys and block names are compared when looking them up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyCase {
+    #[default]
+    Sensitive,
+    /// `exposure_duration` finds `EXPOSURE_DURATION`, as is needed for ISIS labels
+    Insensitive,
+}
+
+impl KeyCase {
+    fn matches(&self, a: &str, b: &str) -> bool {
+        match self {
+            KeyCase::Sensitive => a == b,
+            KeyCase::Insensitive => a.eq_ignore_ascii_case(b),
+        }
+    }
+}
+
+/// Positions of the entries of one list, by name folded to upper case
+type Positions = HashMap<String, Vec<usize>>;
+
+/// The properties, groups or objects of a label, in label order. Used as a `Vec`, which it
+/// dereferences to, while keeping an index of the entries by name. The index is built on first
+/// lookup and dropped whenever the entries are borrowed mutably, so that entries pushed,
+/// removed or renamed in place are always found as they now are.
+pub struct Entries<T> {
+    items: Vec<T>,
+    positions: RwLock<Option<Positions>>,
+}
+
+impl<T> Entries<T> {
+    pub fn new() -> Self {
+        Entries::from(vec![])
+    }
+
+    /// The entries whose names match regardless of case, in order
+    fn find(&self, name_of: impl Fn(&T) -> Option<&str>, name: &str) -> Vec<&T> {
+        let key = name.to_ascii_uppercase();
+        if let Ok(cache) = self.positions.read() {
+            if let Some(positions) = cache.as_ref() {
+                return self.at(positions.get(&key));
+            }
+        }
+
+        let mut positions: Positions = HashMap::with_capacity(self.items.len());
+        self.items.iter().enumerate().for_each(|(i, e)| {
+            if let Some(n) = name_of(e) {
+                positions.entry(n.to_ascii_uppercase()).or_default().push(i);
+            }
+        });
+        let found = self.at(positions.get(&key));
+        if let Ok(mut cache) = self.positions.write() {
+            *cache = Some(positions);
+        }
+        found
+    }
+
+    fn at(&self, positions: Option<&Vec<usize>>) -> Vec<&T> {
+        positions
+            .map(|p| p.iter().map(|i| &self.items[*i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// The entries whose names match as the key case requires, in order
+    fn get_all(&self, name_of: impl Fn(&T) -> Option<&str>, name: &str, case: KeyCase) -> Vec<&T> {
+        self.find(&name_of, name)
+            .into_iter()
+            .filter(|e| matches!(name_of(e), Some(n) if case.matches(n, name)))
+            .collect()
+    }
+}
+
+impl<T> Deref for Entries<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.items
+    }
+}
+
+impl<T> DerefMut for Entries<T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        // The entries may be changed through the reference
+        if let Ok(cache) = self.positions.get_mut() {
+            *cache = None;
+        }
+        &mut self.items
+    }
+}
+
+impl<T> Default for Entries<T> {
+    fn default() -> Self {
+        Entries::new()
+    }
+}
+
+impl<T> From<Vec<T>> for Entries<T> {
+    fn from(items: Vec<T>) -> Self {
+        Entries {
+            items,
+            positions: RwLock::new(None),
+        }
+    }
+}
+
+impl<T> From<Entries<T>> for Vec<T> {
+    fn from(entries: Entries<T>) -> Self {
+        entries.items
+    }
+}
+
+impl<T> FromIterator<T> for Entries<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Entries::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl<T> IntoIterator for Entries<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Entries<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Entries<T> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref_mut().iter_mut()
+    }
+}
+
+/// Clones start with no index
+impl<T: Clone> Clone for Entries<T> {
+    fn clone(&self) -> Self {
+        Entries::from(self.items.clone())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Entries<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.items == other.items
+    }
+}
+
+impl<T: Eq> Eq for Entries<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for Entries<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.items.fmt(f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Entries<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.items.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Entries<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Entries::from)
+    }
+}
+
+/// How a `Pvl`, `Group` or `Object` looks up keys. It holds nothing of the label itself, so
+/// it's never compared or serialized.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Lookup {
+    case: KeyCase,
+}
+
+impl PartialEq for Lookup {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Lookup {}
+
+fn key_name(p: &KeyValuePair) -> Option<&str> {
+    match &p.key {
+        Symbol::Key(n) | Symbol::Pointer(n) => Some(n),
+        _ => None,
+    }
+}
+
+/// Implements the indexed lookups shared by `Pvl`, `Group` and `Object`
+macro_rules! impl_indexed_lookup {
+    () => {
+        /// Retrieves every occurrence of a repeated key, in label order
+        pub fn get_all(&self, name: &str) -> Vec<&KeyValuePair> {
+            self.properties.get_all(key_name, name, self.lookup.case)
+        }
+
+        /// How keys and block names are compared in lookups
+        pub fn key_case(&self) -> KeyCase {
+            self.lookup.case
+        }
+
+        /// Sets how keys and block names are compared in lookups, here and in the blocks within
+        pub fn set_key_case(&mut self, case: KeyCase) {
+            self.lookup.case = case;
+            self.set_nested_key_case(case);
+        }
+
+        /// Sets how keys and block names are compared in lookups, returning self so that calls
+        /// can be chained
+        pub fn with_key_case(mut self, case: KeyCase) -> Self {
+            self.set_key_case(case);
+            self
+        }
+    };
+}
+
+impl Pvl {
+    impl_indexed_lookup! {}
+
+    fn set_nested_key_case(&mut self, case: KeyCase) {
+        self.groups.iter_mut().for_each(|g| g.set_key_case(case));
+        self.objects.iter_mut().for_each(|o| o.set_key_case(case));
+    }
+
+    /// Retrieves the first property with the given key. Lookups are indexed, so each takes
+    /// constant time once the first has been made.
+    /// # Example
+    /// ```
+    /// use vicar::pvl::{KeyCase, Pvl};
+    ///
+    /// let pvl = Pvl::from_string("Samples = 1024\nNOTE = \"A\"\nNOTE = \"B\"\nEND\n")
+    ///     .unwrap()
+    ///     .with_key_case(KeyCase::Insensitive);
+    /// assert!(pvl.has_property("SAMPLES"));
+    /// assert_eq!(pvl.get_all("note").len(), 2);
+    /// ```
+    pub fn get_property(&self, name: &str) -> Option<&KeyValuePair> {
+        self.get_all(name).into_iter().next()
+    }
+
+    pub fn has_property(&self, name: &str) -> bool {
+        self.get_property(name).is_some()
+    }
+
+    /// Retrieves the first group with the given name
+    pub fn get_group(&self, name: &str) -> Option<&Group> {
+        self.groups
+            .get_all(|g: &Group| Some(&g.name), name, self.lookup.case)
+            .into_iter()
+            .next()
+    }
+
+    /// Retrieves the first object with the given name
+    pub fn get_object(&self, name: &str) -> Option<&Object> {
+        self.objects
+            .get_all(|o: &Object| Some(&o.name), name, self.lookup.case)
+            .into_iter()
+            .next()
+    }
+}
+
+impl Object {
+    impl_indexed_lookup! {}
+
+    fn set_nested_key_case(&mut self, case: KeyCase) {
+        self.groups.iter_mut().for_each(|g| g.set_key_case(case));
+        self.objects.iter_mut().for_each(|o| o.set_key_case(case));
+    }
+
+    /// Retrieves the first group with the given name
+    pub fn get_group(&self, name: &str) -> Option<&Group> {
+        self.groups
+            .get_all(|g: &Group| Some(&g.name), name, self.lookup.case)
+            .into_iter()
+            .next()
+    }
+
+    /// Retrieves the first object with the given name
+    pub fn get_object(&self, name: &str) -> Option<&Object> {
+        self.objects
+            .get_all(|o: &Object| Some(&o.name), name, self.lookup.case)
+            .into_iter()
+            .next()
+    }
+}
+
+impl Group {
+    impl_indexed_lookup! {}
+
+    fn set_nested_key_case(&mut self, _: KeyCase) {}
+}
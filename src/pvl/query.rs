@@ -0,0 +1,236 @@
+use super::{Error, Group, KeyValuePair, Object, Pvl, Value, ValueType};
+use crate::time::{LocalSolarTime, PdsTime, SpacecraftClock};
+
+/// A parsed label query such as `IMAGE/BANDS` or `GEOMETRIC_CAMERA_MODEL_PARMS/MODEL_COMPONENT_1[2]`.
+///
+/// Each segment but the last names a GROUP or OBJECT, and the last names a key. `*` within a
+/// segment matches any run of characters, e.g. `MODEL_COMPONENT_*`, and a `**` segment matches
+/// any number of nested blocks. Trailing indices select elements of sequence values, starting
+/// from zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPath {
+    pub segments: Vec<String>,
+    pub indices: Vec<usize>,
+}
+
+impl QueryPath {
+    pub fn from_string(path: &str) -> Result<Self, Error> {
+        let path = path.trim();
+        let (path, index_text) = match path.find('[') {
+            Some(i) => (&path[..i], &path[i..]),
+            None => (path, ""),
+        };
+
+        let mut indices = vec![];
+        let mut rest = index_text;
+        while !rest.is_empty() {
+            let close = match rest.find(']') {
+                Some(c) if rest.starts_with('[') => c,
+                _ => return Err(Error::Syntax(format!("Invalid index in query '{}'", path))),
+            };
+            match rest[1..close].trim().parse::<usize>() {
+                Ok(i) => indices.push(i),
+                Err(_) => return Err(Error::Syntax(format!("Invalid index in query '{}'", path))),
+            }
+            rest = &rest[(close + 1)..];
+        }
+
+        let segments: Vec<String> = path.split('/').map(|s| s.trim().to_owned()).collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            Err(Error::Syntax(format!("Empty segment in query '{}'", path)))
+        } else if segments.last().map(|s| s == "**").unwrap_or(false) {
+            Err(Error::Syntax(format!(
+                "Query '{}' doesn't end in a key",
+                path
+            )))
+        } else {
+            Ok(QueryPath { segments, indices })
+        }
+    }
+
+    /// Selects the indexed element of a matched value, if the path has indices
+    pub fn select(&self, value: &Value) -> Option<Value> {
+        let mut value = value.clone();
+        for i in self.indices.iter() {
+            value = value.parse_array().ok()?.get(*i)?.to_owned();
+        }
+        Some(value)
+    }
+}
+
+/// Determines if a name matches a query segment, where `*` matches any run of characters
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            name.starts_with(prefix)
+                && (0..=(name.len() - prefix.len()))
+                    .filter(|i| name.is_char_boundary(prefix.len() + i))
+                    .any(|i| glob_match(rest, &name[(prefix.len() + i)..]))
+        }
+    }
+}
+
+/// Conversion from a label value to a typed value, for use with `Pvl::query`
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, Error>;
+}
+
+/// Implements FromValue for numeric types. Integers are accepted where floats are requested.
+macro_rules! impl_from_value_number {
+    ($type:ty, $($value_type:expr),+) => {
+        impl FromValue for $type {
+            fn from_value(value: &Value) -> Result<Self, Error> {
+                if ![$($value_type),+, ValueType::Undetermined].contains(&value.value_type) {
+                    Err(Error::InvalidType)
+                } else {
+                    match value.value_raw.parse::<$type>() {
+                        Ok(v) => Ok(v),
+                        Err(_) => Err(Error::ValueTypeParseError),
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_from_value_number!(f32, ValueType::Float, ValueType::Integer);
+impl_from_value_number!(f64, ValueType::Float, ValueType::Integer);
+impl_from_value_number!(u8, ValueType::Integer);
+impl_from_value_number!(u16, ValueType::Integer);
+impl_from_value_number!(u32, ValueType::Integer);
+impl_from_value_number!(u64, ValueType::Integer);
+impl_from_value_number!(usize, ValueType::Integer);
+impl_from_value_number!(i8, ValueType::Integer);
+impl_from_value_number!(i16, ValueType::Integer);
+impl_from_value_number!(i32, ValueType::Integer);
+impl_from_value_number!(i64, ValueType::Integer);
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        Ok(value.clone())
+    }
+}
+
+/// Quoted strings have their quotes removed. Unquoted symbols, dates and bit masks are taken as
+/// written.
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value.value_type {
+            ValueType::Flag | ValueType::DateTime | ValueType::BitMask => {
+                Ok(value.value_raw.to_owned())
+            }
+            _ => value.parse_string(),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value.value_raw.trim_matches('"').to_uppercase().as_str() {
+            "TRUE" => Ok(true),
+            "FALSE" => Ok(false),
+            _ => Err(Error::InvalidType),
+        }
+    }
+}
+
+impl FromValue for PdsTime {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        value.parse_time()
+    }
+}
+
+impl FromValue for SpacecraftClock {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        SpacecraftClock::from_string(&value.value_raw).map_err(|e| Error::General(e.to_string()))
+    }
+}
+
+impl FromValue for LocalSolarTime {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        LocalSolarTime::from_string(&value.value_raw).map_err(|e| Error::General(e.to_string()))
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        value.parse_array()?.iter().map(T::from_value).collect()
+    }
+}
+
+/// Finds the values matching the remaining segments of a query, in label order. Properties come
+/// before nested groups, which come before nested objects.
+fn find_in<'a>(
+    properties: &'a [KeyValuePair],
+    groups: &'a [Group],
+    objects: &'a [Object],
+    segments: &[String],
+    found: &mut Vec<&'a Value>,
+) {
+    match segments {
+        [] => {}
+        [key] => properties
+            .iter()
+            .filter(|p| p.key.value().is_some_and(|k| glob_match(key, &k)))
+            .for_each(|p| found.push(&p.value)),
+        [block, rest @ ..] => {
+            let (next, recurse) = if block == "**" {
+                // Either no further levels, or one more level followed by any number
+                find_in(properties, groups, objects, rest, found);
+                (segments, true)
+            } else {
+                (rest, false)
+            };
+            groups
+                .iter()
+                .filter(|g| recurse || glob_match(block, &g.name))
+                .for_each(|g| find_in(&g.properties, &[], &[], next, found));
+            objects
+                .iter()
+                .filter(|o| recurse || glob_match(block, &o.name))
+                .for_each(|o| find_in(&o.properties, &o.groups, &o.objects, next, found));
+        }
+    }
+}
+
+impl Pvl {
+    /// Retrieves and converts every value matching a query path, in label order. See
+    /// `QueryPath` for the path syntax.
+    /// # Example
+    /// ```
+    /// use vicar::pvl::Pvl;
+    ///
+    /// let pvl = Pvl::from_string(
+    ///     "GROUP = MODEL\n  C = (1.0, 2.0, 3.0)\nEND_GROUP = MODEL\nOBJECT = IMAGE\n  BANDS = 3\nEND_OBJECT = IMAGE\nEND\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(pvl.query::<f64>("MODEL/C[2]").unwrap(), vec![3.0]);
+    /// assert_eq!(pvl.query::<Vec<f64>>("*/C").unwrap(), vec![vec![1.0, 2.0, 3.0]]);
+    /// assert_eq!(pvl.query_one::<usize>("**/BANDS").unwrap(), 3);
+    /// ```
+    pub fn query<T: FromValue>(&self, path: &str) -> Result<Vec<T>, Error> {
+        self.find_values(path)?.iter().map(T::from_value).collect()
+    }
+
+    /// Retrieves and converts the first value matching a query path. Fails if there is no match.
+    pub fn query_one<T: FromValue>(&self, path: &str) -> Result<T, Error> {
+        match self.find_values(path)?.first() {
+            Some(v) => T::from_value(v),
+            None => Err(Error::General(format!("No value matches '{}'", path))),
+        }
+    }
+
+    pub(crate) fn find_values(&self, path: &str) -> Result<Vec<Value>, Error> {
+        let query = QueryPath::from_string(path)?;
+        let mut found = vec![];
+        find_in(
+            &self.properties,
+            &self.groups,
+            &self.objects,
+            &query.segments,
+            &mut found,
+        );
+        Ok(found.into_iter().filter_map(|v| query.select(v)).collect())
+    }
+}
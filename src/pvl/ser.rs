@@ -0,0 +1,311 @@
+//! Serialization of the label model with serde, e.g. to store labels as JSON. Requires the
+//! `serde` feature.
+//!
+//! Properties are written as a map of key to value. Quoted strings, numbers, booleans and
+//! sequences map to their natural types. Values with units, and values written without quotes
+//! such as symbols and dates, are written as `{"value": .., "units": .., "type": ..}` so that
+//! they read back as they were. A key given more than once is written once, as
+//! `{"value": [..], "type": "repeated"}` holding each of its values in turn.
+use super::builder::{float_text, key_symbol};
+use super::{split_sequence, KeyValuePair, Value, ValueType};
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+/// The serialized form of a right-hand value. Shared by PVL and VICAR values.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ValueRepr {
+    Text(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Sequence(Vec<ValueRepr>),
+    /// A value with units, or one which isn't one of the types above. The latter keep the
+    /// text as written in the label, along with the name of their type.
+    Annotated {
+        value: Box<ValueRepr>,
+        units: Option<String>,
+        type_name: Option<String>,
+    },
+}
+
+impl ValueRepr {
+    /// Wraps the value with its units and type, if it has either
+    pub(crate) fn annotate(
+        value: ValueRepr,
+        units: Option<String>,
+        type_name: Option<&str>,
+    ) -> Self {
+        if units.is_none() && type_name.is_none() {
+            value
+        } else {
+            ValueRepr::Annotated {
+                value: Box::new(value),
+                units,
+                type_name: type_name.map(|t| t.to_owned()),
+            }
+        }
+    }
+}
+
+/// Name of a value type which doesn't have a natural serialized form
+pub(crate) fn type_name(value_type: ValueType) -> &'static str {
+    match value_type {
+        ValueType::Undetermined => "undetermined",
+        ValueType::Array => "array",
+        ValueType::String => "string",
+        ValueType::Float => "float",
+        ValueType::Integer => "integer",
+        ValueType::Bool => "bool",
+        ValueType::Flag => "flag",
+        ValueType::BitMask => "bit_mask",
+        ValueType::DateTime => "date_time",
+    }
+}
+
+impl Serialize for ValueRepr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ValueRepr::Text(s) => serializer.serialize_str(s),
+            ValueRepr::Integer(i) => serializer.serialize_i64(*i),
+            ValueRepr::Float(f) => serializer.serialize_f64(*f),
+            ValueRepr::Bool(b) => serializer.serialize_bool(*b),
+            ValueRepr::Sequence(elements) => serializer.collect_seq(elements),
+            ValueRepr::Annotated {
+                value,
+                units,
+                type_name,
+            } => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("value", value)?;
+                if let Some(u) = units {
+                    map.serialize_entry("units", u)?;
+                }
+                if let Some(t) = type_name {
+                    map.serialize_entry("type", t)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct ValueReprVisitor;
+
+impl<'de> Visitor<'de> for ValueReprVisitor {
+    type Value = ValueRepr;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a label value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<ValueRepr, E> {
+        Ok(ValueRepr::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<ValueRepr, E> {
+        Ok(ValueRepr::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<ValueRepr, E> {
+        Ok(match i64::try_from(v) {
+            Ok(i) => ValueRepr::Integer(i),
+            Err(_) => ValueRepr::Float(v as f64),
+        })
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<ValueRepr, E> {
+        Ok(ValueRepr::Float(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<ValueRepr, E> {
+        Ok(ValueRepr::Text(v.to_owned()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ValueRepr, A::Error> {
+        let mut elements = vec![];
+        while let Some(e) = seq.next_element()? {
+            elements.push(e);
+        }
+        Ok(ValueRepr::Sequence(elements))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ValueRepr, A::Error> {
+        let mut value = None;
+        let mut units = None;
+        let mut type_name = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "value" => value = Some(map.next_value()?),
+                "units" => units = Some(map.next_value()?),
+                "type" => type_name = Some(map.next_value()?),
+                _ => return Err(de::Error::unknown_field(&key, &["value", "units", "type"])),
+            }
+        }
+        match value {
+            Some(v) => Ok(ValueRepr::Annotated {
+                value: Box::new(v),
+                units,
+                type_name,
+            }),
+            None => Err(de::Error::missing_field("value")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ValueRepr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueReprVisitor)
+    }
+}
+
+impl Value {
+    fn to_repr(&self) -> ValueRepr {
+        let natural = match self.value_type {
+            ValueType::String => Some(ValueRepr::Text(self.unquoted())),
+            ValueType::Integer => self.value_raw.parse::<i64>().ok().map(ValueRepr::Integer),
+            ValueType::Float => self.value_raw.parse::<f64>().ok().map(ValueRepr::Float),
+            ValueType::Bool => Some(ValueRepr::Bool(self.value_raw.contains("TRUE"))),
+            // Units of the whole sequence are kept once, alongside it
+            ValueType::Array => Some(ValueRepr::Sequence(
+                split_sequence(&self.value_raw[1..(self.value_raw.len() - 1)])
+                    .into_iter()
+                    .map(|s| Value::new(s).to_repr())
+                    .collect(),
+            )),
+            _ => None,
+        };
+        let units = self.units.as_ref().map(|u| u.to_string());
+        match natural {
+            Some(v) => ValueRepr::annotate(v, units, None),
+            None => ValueRepr::annotate(
+                ValueRepr::Text(self.value_raw.to_owned()),
+                units,
+                Some(type_name(self.value_type)),
+            ),
+        }
+    }
+
+    fn from_repr(repr: &ValueRepr) -> Self {
+        Value::new(&repr_text(repr))
+    }
+}
+
+/// The label text for a serialized value
+fn repr_text(repr: &ValueRepr) -> String {
+    match repr {
+        ValueRepr::Text(s) => format!("\"{}\"", s.replace('"', "\"\"")),
+        ValueRepr::Integer(i) => i.to_string(),
+        ValueRepr::Float(f) => float_text(*f),
+        ValueRepr::Bool(true) => "\"TRUE\"".to_owned(),
+        ValueRepr::Bool(false) => "\"FALSE\"".to_owned(),
+        ValueRepr::Sequence(elements) => format!(
+            "({})",
+            elements
+                .iter()
+                .map(repr_text)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        ValueRepr::Annotated {
+            value,
+            units,
+            type_name,
+        } => {
+            let text = match (type_name, value.as_ref()) {
+                (Some(_), ValueRepr::Text(s)) => s.to_owned(),
+                _ => repr_text(value),
+            };
+            match units {
+                Some(u) => format!("{} <{}>", text, u),
+                None => text,
+            }
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_repr().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Value::from_repr(&ValueRepr::deserialize(deserializer)?))
+    }
+}
+
+/// Type name of the entry holding the values of a key given more than once
+const REPEATED: &str = "repeated";
+
+/// Serializes properties as a map of key to value, for `#[serde(with = "..")]`. The values of
+/// a repeated key are gathered where the key first appears.
+pub(crate) mod properties {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        properties: &[KeyValuePair],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<(String, Vec<&Value>)> = vec![];
+        for (key, value) in properties
+            .iter()
+            .filter_map(|p| p.key.value().map(|k| (k, &p.value)))
+        {
+            match entries.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, values)) => values.push(value),
+                None => entries.push((key, vec![value])),
+            }
+        }
+        serializer.collect_map(entries.iter().map(|(key, values)| {
+            let repr = match values.as_slice() {
+                [value] => value.to_repr(),
+                _ => ValueRepr::annotate(
+                    ValueRepr::Sequence(values.iter().map(|v| v.to_repr()).collect()),
+                    None,
+                    Some(REPEATED),
+                ),
+            };
+            (key, repr)
+        }))
+    }
+
+    struct PropertiesVisitor;
+
+    impl<'de> Visitor<'de> for PropertiesVisitor {
+        type Value = Vec<KeyValuePair>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a map of keys to values")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut properties = vec![];
+            while let Some((key, repr)) = map.next_entry::<String, ValueRepr>()? {
+                let values = match repr {
+                    ValueRepr::Annotated {
+                        value,
+                        units: None,
+                        type_name: Some(t),
+                    } if t == REPEATED => match *value {
+                        ValueRepr::Sequence(elements) => elements,
+                        _ => return Err(de::Error::custom("repeated values must be a sequence")),
+                    },
+                    _ => vec![repr],
+                };
+                properties.extend(values.iter().map(|v| KeyValuePair {
+                    key: key_symbol(&key),
+                    value: Value::from_repr(v),
+                }));
+            }
+            Ok(properties)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, C: From<Vec<KeyValuePair>>>(
+        deserializer: D,
+    ) -> Result<C, D::Error> {
+        deserializer.deserialize_map(PropertiesVisitor).map(C::from)
+    }
+}
@@ -0,0 +1,246 @@
+use super::{Error, Group, KeyValuePair, Object, Pvl, Value, ValueType, LINE_CONTINUATION_PREFIX};
+use std::{fmt, fs, path::Path};
+
+/// Serializes a `Pvl` structure to canonical PDS3/ODL text
+#[derive(Debug, Clone)]
+pub struct PvlWriter {
+    /// Column at which the `=` of each statement is aligned. Keys which are too long push it
+    /// further right.
+    pub equals_column: usize,
+    /// Lines longer than this are wrapped, where possible, onto continuation lines
+    pub line_width: usize,
+    /// Number of spaces each nesting level of GROUP or OBJECT is indented
+    pub indent: usize,
+    /// Line terminator. PDS3 requires CRLF.
+    pub line_ending: String,
+}
+
+impl Default for PvlWriter {
+    fn default() -> Self {
+        PvlWriter::new()
+    }
+}
+
+impl PvlWriter {
+    /// Constructs a writer producing labels in the layout used by MSL and MER products
+    pub fn new() -> Self {
+        PvlWriter {
+            equals_column: 34,
+            line_width: 80,
+            indent: 2,
+            line_ending: "\r\n".to_owned(),
+        }
+    }
+
+    /// Serializes the PVL structure, terminated by the END statement
+    pub fn write(&self, pvl: &Pvl) -> String {
+        let mut out = String::new();
+
+        pvl.properties
+            .iter()
+            .for_each(|kvp| self.write_kvp(&mut out, kvp, 0));
+
+        pvl.groups.iter().for_each(|g| {
+            self.write_line(&mut out, "");
+            self.write_group(&mut out, g, 0);
+        });
+
+        pvl.objects.iter().for_each(|o| {
+            self.write_line(&mut out, "");
+            self.write_object(&mut out, o, 0);
+        });
+
+        self.write_line(&mut out, "END");
+        out
+    }
+
+    fn write_line(&self, out: &mut String, line: &str) {
+        out.push_str(line);
+        out.push_str(&self.line_ending);
+    }
+
+    fn write_group(&self, out: &mut String, group: &Group, depth: usize) {
+        self.write_statement(out, "GROUP", &group.name, depth);
+        group
+            .properties
+            .iter()
+            .for_each(|kvp| self.write_kvp(out, kvp, depth + 1));
+        self.write_statement(out, "END_GROUP", &group.name, depth);
+    }
+
+    fn write_object(&self, out: &mut String, object: &Object, depth: usize) {
+        self.write_statement(out, "OBJECT", &object.name, depth);
+        object
+            .properties
+            .iter()
+            .for_each(|kvp| self.write_kvp(out, kvp, depth + 1));
+        object
+            .groups
+            .iter()
+            .for_each(|g| self.write_group(out, g, depth + 1));
+        object
+            .objects
+            .iter()
+            .for_each(|o| self.write_object(out, o, depth + 1));
+        self.write_statement(out, "END_OBJECT", &object.name, depth);
+    }
+
+    fn write_kvp(&self, out: &mut String, kvp: &KeyValuePair, depth: usize) {
+        if let Some(key) = kvp.key.value() {
+            self.write_statement(out, &key, &format_value(&kvp.value), depth);
+        }
+    }
+
+    /// Writes `KEY = VALUE` with the equals sign aligned, wrapping the value if needed
+    fn write_statement(&self, out: &mut String, key: &str, value: &str, depth: usize) {
+        let lhs = format!("{}{}", " ".repeat(depth * self.indent), key);
+        let padding = if lhs.len() < self.equals_column {
+            self.equals_column - lhs.len()
+        } else {
+            1
+        };
+        let prefix = format!("{}{}= ", lhs, " ".repeat(padding));
+
+        let tokens = if prefix.len() + value.len() <= self.line_width {
+            vec![value]
+        } else if value.starts_with('(') {
+            split_after_commas(value)
+        } else if value.starts_with('"') {
+            split_after_spaces(value)
+        } else {
+            vec![value]
+        };
+        let lines = wrap_tokens(
+            &tokens,
+            self.line_width.saturating_sub(prefix.len()),
+            self.line_width
+                .saturating_sub(LINE_CONTINUATION_PREFIX.len()),
+        );
+        lines.iter().enumerate().for_each(|(i, l)| {
+            if i == 0 {
+                self.write_line(out, &format!("{}{}", prefix, l));
+            } else {
+                self.write_line(out, &format!("{}{}", LINE_CONTINUATION_PREFIX, l));
+            }
+        });
+    }
+}
+
+/// Gathers tokens into lines, so that each line fits within the widths given. Tokens longer
+/// than the width are left whole.
+fn wrap_tokens(tokens: &[&str], first_width: usize, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut limit = first_width;
+    for token in tokens {
+        if !line.is_empty() && line.len() + token.trim_end().len() > limit {
+            lines.push(line.trim_end().to_owned());
+            line = String::new();
+            limit = width;
+        }
+        line.push_str(token);
+    }
+    lines.push(line);
+    lines
+}
+
+/// Splits a sequence into tokens which each end with a comma outside of any quotes
+fn split_after_commas(value: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (_, Some(q)) if c == q => quote = None,
+            (',', None) => {
+                tokens.push(&value[start..=i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    tokens.push(&value[start..]);
+    tokens
+}
+
+/// Splits a quoted string into words which each end with a single space. Readers join the
+/// lines of a string with one space, so runs of spaces are never broken.
+fn split_after_spaces(value: &str) -> Vec<&str> {
+    let b = value.as_bytes();
+    let mut tokens = vec![];
+    let mut start = 0;
+    for i in 1..b.len().saturating_sub(1) {
+        if b[i] == b' ' && b[i - 1] != b' ' && b[i + 1] != b' ' {
+            tokens.push(&value[start..=i]);
+            start = i + 1;
+        }
+    }
+    tokens.push(&value[start..]);
+    tokens
+}
+
+/// Formats a value as it is written on the right-hand side of a PVL statement, including units
+pub fn format_value(value: &Value) -> String {
+    let formatted = match value.value_type {
+        ValueType::Array => match value.parse_array() {
+            Ok(elements) => format!(
+                "({})",
+                elements
+                    .iter()
+                    .map(|e| {
+                        // Units inherited from the whole sequence are written once, after it
+                        if e.units == value.units {
+                            format_value(&Value {
+                                units: None,
+                                ..e.clone()
+                            })
+                        } else {
+                            format_value(e)
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            Err(_) => value.value_raw.to_owned(),
+        },
+        ValueType::String if value.is_symbol() => format!("'{}'", value.unquoted()),
+        ValueType::String => format!("\"{}\"", value.unquoted().replace('"', "\"\"")),
+        _ => value.value_raw.to_owned(),
+    };
+    match &value.units {
+        Some(units) => format!("{} <{}>", formatted, units),
+        None => formatted,
+    }
+}
+
+impl Pvl {
+    /// Serializes to canonical PDS3 label text using the default writer layout
+    /// # Example
+    /// ```
+    /// use vicar::pvl::Pvl;
+    /// use std::path::Path;
+    ///
+    /// let p = "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL";
+    /// let pvl = Pvl::load(Path::new(p)).unwrap();
+    /// let text = pvl.to_pvl_string();
+    /// assert!(text.starts_with("PDS_VERSION_ID"));
+    /// ```
+    pub fn to_pvl_string(&self) -> String {
+        PvlWriter::new().write(self)
+    }
+
+    /// Serializes and writes the PVL structure to the requested file path
+    pub fn save(&self, file_path: &Path) -> Result<(), Error> {
+        match fs::write(file_path, self.to_pvl_string()) {
+            Ok(_) => Ok(()),
+            Err(why) => Err(Error::General(t!(why))),
+        }
+    }
+}
+
+impl fmt::Display for Pvl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_pvl_string())
+    }
+}
@@ -0,0 +1,165 @@
+//! Radiometric scaling of stored sample values to physical values, from the keywords with which
+//! PDS3 and VICAR labels describe it: RADIANCE_SCALING_FACTOR and RADIANCE_OFFSET of calibrated
+//! rover products, SCALING_FACTOR and OFFSET of PDS3 image objects, and CORE_MULTIPLIER and
+//! CORE_BASE of qubes.
+use crate::label::Label;
+use crate::pvl::{Error, FromValue, KeyValuePair, Value, ValueType, ValueUnits};
+
+/// The keyword sets describing a scaling, in order of preference: the factor, the offset and
+/// the keyword giving units, where the factor doesn't carry them
+const KEYWORDS: [(&str, &str, &str); 3] = [
+    (
+        "RADIANCE_SCALING_FACTOR",
+        "RADIANCE_OFFSET",
+        "RADIANCE_UNIT",
+    ),
+    ("SCALING_FACTOR", "OFFSET", "UNIT"),
+    ("CORE_MULTIPLIER", "CORE_BASE", "CORE_UNIT"),
+];
+
+/// Finds a property of a block by name, regardless of its namespace
+fn find<'a>(properties: &[&'a KeyValuePair], key: &str) -> Option<&'a Value> {
+    properties
+        .iter()
+        .find(|kvp| kvp.key.name().as_deref() == Some(key))
+        .map(|kvp| &kvp.value)
+}
+
+/// Reads a value which may be given once or per band. Returns None if any of them isn't a
+/// number, as with the `"N/A"` of uncalibrated products.
+fn per_band(value: &Value) -> Option<Vec<f64>> {
+    if value.value_type() == ValueType::Array {
+        Vec::<f64>::from_value(value).ok()
+    } else {
+        f64::from_value(value).ok().map(|v| vec![v])
+    }
+}
+
+/// A linear scaling of stored values to physical values: `offset + factor * value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scaling {
+    /// Scaling factors, one per band or a single one for every band
+    pub factors: Vec<f64>,
+    /// Offsets, one per band or a single one for every band
+    pub offsets: Vec<f64>,
+    /// Units of the physical values, as given by the label
+    pub units: Option<ValueUnits>,
+    /// The keyword the factors were read from, e.g. `RADIANCE_SCALING_FACTOR`
+    pub source: String,
+    /// The label group or VICAR property the scaling was read from
+    pub block: String,
+}
+
+impl Scaling {
+    /// Reads the scaling described by the keywords `factor` and `offset` of one label block.
+    /// Returns None if the block doesn't give a numeric factor. A missing offset is taken to be
+    /// zero.
+    pub fn from_properties(
+        block: &str,
+        properties: &[&KeyValuePair],
+        factor: &str,
+        offset: &str,
+        unit: &str,
+    ) -> Result<Option<Scaling>, Error> {
+        let factor_value = match find(properties, factor) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let factors = match per_band(factor_value) {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        let offsets = match find(properties, offset) {
+            Some(v) => match per_band(v) {
+                Some(o) => o,
+                None => return Ok(None),
+            },
+            None => vec![0.0],
+        };
+        if factors.len() != offsets.len() && factors.len() > 1 && offsets.len() > 1 {
+            return Err(Error::General(format!(
+                "{} has {} scaling factors for {} offsets",
+                block,
+                factors.len(),
+                offsets.len()
+            )));
+        }
+
+        // VICAR labels give units in a separate `KEY__UNIT` item
+        let units = factor_value
+            .units()
+            .cloned()
+            .or_else(|| {
+                let v = find(properties, &format!("{}__UNIT", factor))?;
+                String::from_value(v)
+                    .ok()
+                    .or_else(|| Vec::<String>::from_value(v).ok()?.first().cloned())
+                    .map(|u| ValueUnits::from_string(&u))
+            })
+            .or_else(|| {
+                find(properties, unit)
+                    .and_then(|v| String::from_value(v).ok())
+                    .map(|u| ValueUnits::from_string(&u))
+            });
+
+        Ok(Some(Scaling {
+            factors,
+            offsets,
+            units,
+            source: factor.to_owned(),
+            block: block.to_owned(),
+        }))
+    }
+
+    /// Reads the scaling of a PDS3 or VICAR label. Radiance scaling is preferred to the
+    /// scaling of the image or qube object where a label gives both. Returns None where the
+    /// label gives no scaling.
+    /// # Example
+    /// ```
+    /// use vicar::pvl::Pvl;
+    /// use vicar::radiometry::Scaling;
+    ///
+    /// let p = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+    /// let scaling = Scaling::from_label(&Pvl::load(std::path::Path::new(p)).unwrap()).unwrap().unwrap();
+    /// assert_eq!(scaling.source, "RADIANCE_SCALING_FACTOR");
+    /// assert_eq!(scaling.factors, vec![1.0e-5]);
+    /// assert_eq!(scaling.offsets, vec![0.0]);
+    /// ```
+    pub fn from_label<L: Label>(label: &L) -> Result<Option<Scaling>, L::Error> {
+        let blocks = label.blocks();
+        for (factor, offset, unit) in KEYWORDS.iter() {
+            for (path, properties) in blocks.iter() {
+                if let Some(scaling) =
+                    Scaling::from_properties(path, properties, factor, offset, unit)?
+                {
+                    return Ok(Some(scaling));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// The factor and offset of a band
+    pub fn band(&self, band: usize) -> (f64, f64) {
+        let pick = |values: &Vec<f64>| match values.len() {
+            0 => None,
+            1 => Some(values[0]),
+            _ => values.get(band).copied(),
+        };
+        (
+            pick(&self.factors).unwrap_or(1.0),
+            pick(&self.offsets).unwrap_or(0.0),
+        )
+    }
+
+    /// Converts a stored value of a band to a physical value
+    pub fn apply(&self, value: f64, band: usize) -> f64 {
+        let (factor, offset) = self.band(band);
+        offset + factor * value
+    }
+
+    /// Determines if the scaling leaves values unchanged
+    pub fn is_identity(&self) -> bool {
+        self.factors.iter().all(|f| *f == 1.0) && self.offsets.iter().all(|o| *o == 0.0)
+    }
+}
@@ -0,0 +1,258 @@
+//! Streaming statistics and histograms of pixel values, counting only valid pixels, and their
+//! comparison with the statistics labels give, such as MEAN or CORE_MINIMUM_DN.
+use crate::label::Label;
+use crate::mask::PixelClass;
+use std::collections::BTreeMap;
+
+/// The label keywords compared by default, and the statistic each gives
+pub const LABEL_STATISTICS: [(&str, Statistic); 6] = [
+    ("MINIMUM", Statistic::Minimum),
+    ("MAXIMUM", Statistic::Maximum),
+    ("MEAN", Statistic::Mean),
+    ("STANDARD_DEVIATION", Statistic::StandardDeviation),
+    ("CORE_MINIMUM_DN", Statistic::Minimum),
+    ("CORE_MAXIMUM_DN", Statistic::Maximum),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Statistic {
+    Count,
+    Minimum,
+    Maximum,
+    Mean,
+    StandardDeviation,
+}
+
+/// A region of a band, in lines and samples from zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub line: usize,
+    pub sample: usize,
+    pub lines: usize,
+    pub samples: usize,
+}
+
+impl Window {
+    pub fn new(line: usize, sample: usize, lines: usize, samples: usize) -> Self {
+        Window {
+            line,
+            sample,
+            lines,
+            samples,
+        }
+    }
+}
+
+/// Options controlling the statistics gathered from a band
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatisticsOptions {
+    /// Width of the histogram bins, or None for no histogram
+    pub bin_width: Option<f64>,
+    /// The region to gather from, or None for the whole band
+    pub window: Option<Window>,
+}
+
+impl Default for StatisticsOptions {
+    fn default() -> Self {
+        StatisticsOptions {
+            bin_width: Some(1.0),
+            window: None,
+        }
+    }
+}
+
+/// A histogram of bins of equal width starting at zero, which grows to cover the values added
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub bin_width: f64,
+    counts: BTreeMap<i64, u64>,
+}
+
+impl Histogram {
+    pub fn new(bin_width: f64) -> Self {
+        Histogram {
+            bin_width,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        *self
+            .counts
+            .entry((value / self.bin_width).floor() as i64)
+            .or_insert(0) += 1;
+    }
+
+    /// The lower bound and count of each bin with values, in ascending order
+    pub fn bins(&self) -> Vec<(f64, u64)> {
+        self.counts
+            .iter()
+            .map(|(b, c)| (*b as f64 * self.bin_width, *c))
+            .collect()
+    }
+
+    /// The number of values in the bin containing a value
+    pub fn count(&self, value: f64) -> u64 {
+        self.counts
+            .get(&((value / self.bin_width).floor() as i64))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The lower bound of the bin with the most values
+    pub fn mode(&self) -> Option<f64> {
+        self.counts
+            .iter()
+            .max_by_key(|(b, c)| (**c, -**b))
+            .map(|(b, _)| *b as f64 * self.bin_width)
+    }
+}
+
+/// Statistics of the valid values of a band or window, along with the number of pixels of
+/// each class
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statistics {
+    pub count: usize,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub mean: Option<f64>,
+    /// Population standard deviation
+    pub standard_deviation: Option<f64>,
+    pub histogram: Option<Histogram>,
+    pub null: usize,
+    pub low_saturation: usize,
+    pub high_saturation: usize,
+    pub missing: usize,
+}
+
+/// A label statistic which disagrees with the one computed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Disagreement {
+    pub keyword: String,
+    pub label: f64,
+    pub computed: Option<f64>,
+}
+
+impl Statistics {
+    pub fn get(&self, statistic: Statistic) -> Option<f64> {
+        match statistic {
+            Statistic::Count => Some(self.count as f64),
+            Statistic::Minimum => self.minimum,
+            Statistic::Maximum => self.maximum,
+            Statistic::Mean => self.mean,
+            Statistic::StandardDeviation => self.standard_deviation,
+        }
+    }
+
+    /// Compares the statistics with those a label gives under the keywords, in the IMAGE
+    /// object or anywhere else in the label. Values agree within a tolerance relative to the
+    /// label value, or absolute for label values less than one.
+    /// # Example
+    /// ```
+    /// use vicar::pvl::Pvl;
+    /// use vicar::statistics::{StatisticsAccumulator, LABEL_STATISTICS};
+    /// use vicar::mask::PixelClass;
+    ///
+    /// let pvl = Pvl::from_string("OBJECT = IMAGE\n  MINIMUM = 1\n  MEAN = 2.5\nEND_OBJECT = IMAGE\nEND\n").unwrap();
+    /// let mut stats = StatisticsAccumulator::new(None);
+    /// [1.0, 2.0, 3.0].iter().for_each(|v| stats.add(*v, PixelClass::Valid));
+    /// let disagreements = stats.finish().compare_with_label(&pvl, &LABEL_STATISTICS, 0.01);
+    /// assert_eq!(disagreements.len(), 1);
+    /// assert_eq!(disagreements[0].keyword, "MEAN");
+    /// ```
+    pub fn compare_with_label<L: Label>(
+        &self,
+        label: &L,
+        keywords: &[(&str, Statistic)],
+        tolerance: f64,
+    ) -> Vec<Disagreement> {
+        keywords
+            .iter()
+            .filter_map(|(keyword, statistic)| {
+                let expected = [format!("IMAGE/{}", keyword), format!("**/{}", keyword)]
+                    .iter()
+                    .find_map(|p| label.query_one::<f64>(p).ok())?;
+                let computed = self.get(*statistic);
+                match computed {
+                    Some(c) if (c - expected).abs() <= tolerance * expected.abs().max(1.0) => None,
+                    _ => Some(Disagreement {
+                        keyword: keyword.to_string(),
+                        label: expected,
+                        computed,
+                    }),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Gathers statistics one value at a time, so bands needn't be held in memory
+#[derive(Debug, Clone)]
+pub struct StatisticsAccumulator {
+    count: usize,
+    mean: f64,
+    m2: f64, // Sum of squared differences from the mean
+    minimum: f64,
+    maximum: f64,
+    histogram: Option<Histogram>,
+    null: usize,
+    low_saturation: usize,
+    high_saturation: usize,
+    missing: usize,
+}
+
+impl StatisticsAccumulator {
+    /// A new accumulator, with a histogram of bins of the given width if requested
+    pub fn new(bin_width: Option<f64>) -> Self {
+        StatisticsAccumulator {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            minimum: f64::INFINITY,
+            maximum: f64::NEG_INFINITY,
+            histogram: bin_width.map(Histogram::new),
+            null: 0,
+            low_saturation: 0,
+            high_saturation: 0,
+            missing: 0,
+        }
+    }
+
+    /// Adds a value of the given class. Only valid values contribute to the statistics.
+    pub fn add(&mut self, value: f64, class: PixelClass) {
+        match class {
+            PixelClass::Null => self.null += 1,
+            PixelClass::LowSaturation => self.low_saturation += 1,
+            PixelClass::HighSaturation => self.high_saturation += 1,
+            PixelClass::Missing => self.missing += 1,
+            PixelClass::Valid => {
+                // Welford's method, which is stable over long runs of values
+                self.count += 1;
+                let delta = value - self.mean;
+                self.mean += delta / self.count as f64;
+                self.m2 += delta * (value - self.mean);
+                self.minimum = self.minimum.min(value);
+                self.maximum = self.maximum.max(value);
+                if let Some(histogram) = &mut self.histogram {
+                    histogram.add(value);
+                }
+            }
+        }
+    }
+
+    pub fn finish(self) -> Statistics {
+        let valid = self.count > 0;
+        Statistics {
+            count: self.count,
+            minimum: valid.then_some(self.minimum),
+            maximum: valid.then_some(self.maximum),
+            mean: valid.then_some(self.mean),
+            standard_deviation: valid.then(|| (self.m2 / self.count as f64).sqrt()),
+            histogram: self.histogram,
+            null: self.null,
+            low_saturation: self.low_saturation,
+            high_saturation: self.high_saturation,
+            missing: self.missing,
+        }
+    }
+}
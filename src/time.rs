@@ -0,0 +1,654 @@
+use crate::observation::Mission;
+use regex::Regex;
+use std::fmt;
+
+/// Errors parsing times and clock counts
+#[derive(Debug)]
+pub enum Error {
+    /// The text isn't in a recognized format
+    Format(String),
+    /// A field is out of range, e.g. a 13th month or a fine count past the modulus
+    Range(String),
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Format(s) | Error::Range(s) => write!(f, "Invalid time: {}", s),
+        }
+    }
+}
+
+const SECONDS_PER_DAY: i64 = 86400;
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+lazy_static! {
+    // YYYY-MM-DDThh:mm:ss.fffZ, all parts after the date optional
+    static ref CALENDAR_TIME: Regex = Regex::new(
+        r"^(\d{4})-(\d{2})-(\d{2})(?:T(\d{2}):(\d{2})(?::(\d{2})(?:\.(\d+))?)?)?Z?$"
+    )
+    .unwrap();
+    // YYYY-DDDThh:mm:ss.fffZ, all parts after the day of year optional
+    static ref DOY_TIME: Regex = Regex::new(
+        r"^(\d{4})-(\d{3})(?:T(\d{2}):(\d{2})(?::(\d{2})(?:\.(\d+))?)?)?Z?$"
+    )
+    .unwrap();
+    // VICAR DAT_TIM, e.g. 'Fri Sep 15 01:09:47 2017'
+    static ref VICAR_TIME: Regex = Regex::new(
+        r"^(?:[A-Za-z]{3}\s+)?([A-Za-z]{3})\s+(\d{1,2})\s+(\d{1,2}):(\d{2}):(\d{2})\s+(\d{4})$"
+    )
+    .unwrap();
+    // [partition/]count[:count...][.fraction], e.g. 1/1883935199.157 or Voyager's 35807:59:544
+    static ref SCLK: Regex = Regex::new(r"^(?:(\d+)/)?(\d+(?::\d+)*)(?:\.(\d+))?$").unwrap();
+    // [Sol-NNNNNM]hh:mm:ss[.fff], e.g. Sol-03423M17:05:43.460 or 17:45:24
+    static ref SOLAR_TIME: Regex =
+        Regex::new(r"^(?i:SOL-?(\d+)([MT]))?(\d{1,2}):(\d{2})(?::(\d{2})(?:\.(\d+))?)?$").unwrap();
+}
+
+/// A UTC date and time as found in PDS and VICAR labels, with nanosecond precision.
+/// Leap seconds are not accounted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PdsTime {
+    seconds: i64, // Since 1970-01-01T00:00:00
+    nanos: u32,
+}
+
+/// Days since 1970-01-01 of the given proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Proleptic Gregorian (year, month, day) of the given number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts the digits following a decimal point to nanoseconds, truncating beyond nine digits
+fn fraction_to_nanos(fraction: &str) -> u32 {
+    let digits: String = fraction
+        .chars()
+        .chain("000000000".chars())
+        .take(9)
+        .collect();
+    digits.parse::<u32>().unwrap_or(0)
+}
+
+/// Formats billionths as a decimal fraction of `digits` places (max 9), including the decimal
+/// point
+fn format_fraction(nanos: u32, digits: usize) -> String {
+    if digits == 0 {
+        String::new()
+    } else {
+        format!(".{:09}", nanos)[..digits.min(9) + 1].to_owned()
+    }
+}
+
+/// Parses an optional regex capture to a number, defaulting to zero if not present
+fn capture_or_zero(caps: &regex::Captures, i: usize) -> u32 {
+    caps.get(i)
+        .map(|m| m.as_str().parse::<u32>().unwrap_or(0))
+        .unwrap_or(0)
+}
+
+impl PdsTime {
+    /// Constructs a time from calendar date and time of day components
+    pub fn from_calendar(
+        year: i64,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        nanos: u32,
+    ) -> Result<Self, Error> {
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            Err(Error::Range(format!(
+                "Invalid calendar date: {}-{}-{}",
+                year, month, day
+            )))
+        } else {
+            PdsTime::from_days_and_time(
+                days_from_civil(year, month, day),
+                hour,
+                minute,
+                second,
+                nanos,
+            )
+        }
+    }
+
+    /// Constructs a time from a year, day of year (starting at 1), and time of day components
+    pub fn from_day_of_year(
+        year: i64,
+        day_of_year: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        nanos: u32,
+    ) -> Result<Self, Error> {
+        let days_in_year = if is_leap_year(year) { 366 } else { 365 };
+        if day_of_year < 1 || day_of_year > days_in_year {
+            Err(Error::Range(format!(
+                "Invalid day of year: {}-{:03}",
+                year, day_of_year
+            )))
+        } else {
+            PdsTime::from_days_and_time(
+                days_from_civil(year, 1, 1) + day_of_year as i64 - 1,
+                hour,
+                minute,
+                second,
+                nanos,
+            )
+        }
+    }
+
+    fn from_days_and_time(
+        days: i64,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        nanos: u32,
+    ) -> Result<Self, Error> {
+        // Allow a 60th second so leap seconds written into labels can be parsed
+        if hour > 23 || minute > 59 || second > 60 || nanos as i64 >= NANOS_PER_SECOND {
+            Err(Error::Range(format!(
+                "Invalid time of day: {:02}:{:02}:{:02}.{:09}",
+                hour, minute, second, nanos
+            )))
+        } else {
+            Ok(PdsTime {
+                seconds: days * SECONDS_PER_DAY
+                    + hour as i64 * 3600
+                    + minute as i64 * 60
+                    + second as i64,
+                nanos,
+            })
+        }
+    }
+
+    /// Constructs a time from seconds and nanoseconds since 1970-01-01T00:00:00
+    pub fn from_unix(seconds: i64, nanos: u32) -> Self {
+        PdsTime {
+            seconds: seconds + nanos as i64 / NANOS_PER_SECOND,
+            nanos: (nanos as i64 % NANOS_PER_SECOND) as u32,
+        }
+    }
+
+    /// Parses any of the time formats found in PDS and VICAR labels:
+    ///  * ISO calendar: `2022-03-24T09:51:32.577`
+    ///  * Day of year: `2017-255T18:10:02.599`
+    ///  * Either of the above with a trailing `Z`, or as a date alone
+    ///  * VICAR `DAT_TIM`: `Fri Sep 15 01:09:47 2017`
+    ///
+    /// Surrounding quotes are ignored.
+    pub fn from_string(s: &str) -> Result<Self, Error> {
+        let s = s.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+
+        if let Some(caps) = CALENDAR_TIME.captures(s) {
+            PdsTime::from_calendar(
+                caps[1].parse::<i64>().unwrap(),
+                capture_or_zero(&caps, 2),
+                capture_or_zero(&caps, 3),
+                capture_or_zero(&caps, 4),
+                capture_or_zero(&caps, 5),
+                capture_or_zero(&caps, 6),
+                caps.get(7)
+                    .map(|m| fraction_to_nanos(m.as_str()))
+                    .unwrap_or(0),
+            )
+        } else if let Some(caps) = DOY_TIME.captures(s) {
+            PdsTime::from_day_of_year(
+                caps[1].parse::<i64>().unwrap(),
+                capture_or_zero(&caps, 2),
+                capture_or_zero(&caps, 3),
+                capture_or_zero(&caps, 4),
+                capture_or_zero(&caps, 5),
+                caps.get(6)
+                    .map(|m| fraction_to_nanos(m.as_str()))
+                    .unwrap_or(0),
+            )
+        } else if let Some(caps) = VICAR_TIME.captures(s) {
+            let month_name = caps[1].to_uppercase();
+            match MONTH_NAMES.iter().position(|m| *m == month_name) {
+                Some(m) => PdsTime::from_calendar(
+                    caps[6].parse::<i64>().unwrap(),
+                    m as u32 + 1,
+                    capture_or_zero(&caps, 2),
+                    capture_or_zero(&caps, 3),
+                    capture_or_zero(&caps, 4),
+                    capture_or_zero(&caps, 5),
+                    0,
+                ),
+                None => Err(Error::Format(format!("Invalid month name: {}", s))),
+            }
+        } else {
+            Err(Error::Format(format!("Unrecognized time format: {}", s)))
+        }
+    }
+
+    fn days(&self) -> i64 {
+        self.seconds.div_euclid(SECONDS_PER_DAY)
+    }
+
+    fn seconds_of_day(&self) -> i64 {
+        self.seconds.rem_euclid(SECONDS_PER_DAY)
+    }
+
+    pub fn year(&self) -> i64 {
+        civil_from_days(self.days()).0
+    }
+
+    pub fn month(&self) -> u32 {
+        civil_from_days(self.days()).1
+    }
+
+    pub fn day(&self) -> u32 {
+        civil_from_days(self.days()).2
+    }
+
+    /// Day of the year, starting at 1 for January 1st
+    pub fn day_of_year(&self) -> u32 {
+        (self.days() - days_from_civil(self.year(), 1, 1) + 1) as u32
+    }
+
+    pub fn hour(&self) -> u32 {
+        (self.seconds_of_day() / 3600) as u32
+    }
+
+    pub fn minute(&self) -> u32 {
+        (self.seconds_of_day() % 3600 / 60) as u32
+    }
+
+    pub fn second(&self) -> u32 {
+        (self.seconds_of_day() % 60) as u32
+    }
+
+    pub fn nanosecond(&self) -> u32 {
+        self.nanos
+    }
+
+    /// Whole seconds since 1970-01-01T00:00:00
+    pub fn unix_seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Seconds elapsed from `other` to this time. Negative if `other` is later.
+    pub fn seconds_since(&self, other: &PdsTime) -> f64 {
+        (self.seconds - other.seconds) as f64
+            + (self.nanos as f64 - other.nanos as f64) / NANOS_PER_SECOND as f64
+    }
+
+    /// Formats as a calendar time, e.g. `2022-03-24T09:51:32.577`, with `digits` places of
+    /// fractional seconds
+    pub fn to_calendar_string(&self, digits: usize) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+            self.year(),
+            self.month(),
+            self.day(),
+            self.hour(),
+            self.minute(),
+            self.second(),
+            format_fraction(self.nanos, digits)
+        )
+    }
+
+    /// Formats as a day of year time, e.g. `2022-083T09:51:32.577`, with `digits` places of
+    /// fractional seconds
+    pub fn to_doy_string(&self, digits: usize) -> String {
+        format!(
+            "{:04}-{:03}T{:02}:{:02}:{:02}{}",
+            self.year(),
+            self.day_of_year(),
+            self.hour(),
+            self.minute(),
+            self.second(),
+            format_fraction(self.nanos, digits)
+        )
+    }
+}
+
+impl fmt::Display for PdsTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_calendar_string(3))
+    }
+}
+
+/// How the field following the point of a clock count divides the last field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FineField {
+    /// An integer count, of the given number per tick where known
+    Count(Option<u64>),
+    /// A decimal fraction of a tick
+    Decimal,
+}
+
+impl FineField {
+    /// The fine field of a mission's clock: 256 counts per tick for Cassini, 65536 for MSL and
+    /// a decimal fraction for MER. Other missions' fine fields are taken to be counts.
+    pub fn for_mission(mission: Mission) -> Self {
+        match mission {
+            Mission::Cassini => FineField::Count(Some(256)),
+            Mission::MarsScienceLaboratory => FineField::Count(Some(65536)),
+            Mission::MarsExplorationRover => FineField::Decimal,
+            _ => FineField::Count(None),
+        }
+    }
+}
+
+/// A spacecraft clock (SCLK) count, e.g. `1/1883935199.157`, as given by
+/// SPACECRAFT_CLOCK_START_COUNT. Counts are ordered by partition, then by each field of the
+/// count and the fine field, so products of one spacecraft can be sorted without SPICE kernels.
+/// Counts whose fine fields are of different kinds are never equal.
+#[derive(Debug, Clone)]
+pub struct SpacecraftClock {
+    partition: u32,
+    counts: Vec<u64>,
+    fine: u64, // Billionths of the last count for decimal fine fields
+    fine_field: FineField,
+    digits: usize, // Of the fine field as written, for display
+}
+
+impl SpacecraftClock {
+    /// Parses a clock count whose fine field is an integer count, as with Cassini and MSL. The
+    /// partition is taken to be 1 when not given, as is usual in PDS3 labels which state it
+    /// separately. Surrounding quotes are ignored.
+    pub fn from_string(s: &str) -> Result<Self, Error> {
+        SpacecraftClock::from_string_with(s, FineField::Count(None))
+    }
+
+    /// Parses a clock count of a mission's spacecraft
+    /// # Example
+    /// ```
+    /// use vicar::observation::Mission;
+    /// use vicar::time::SpacecraftClock;
+    ///
+    /// let a = SpacecraftClock::from_string_for_mission("1/1883935199.99", Mission::Cassini).unwrap();
+    /// let b = SpacecraftClock::from_string_for_mission("1/1883935199.157", Mission::Cassini).unwrap();
+    /// assert!(a < b);
+    /// assert_eq!(b.fraction(), Some(157.0 / 256.0));
+    /// ```
+    pub fn from_string_for_mission(s: &str, mission: Mission) -> Result<Self, Error> {
+        SpacecraftClock::from_string_with(s, FineField::for_mission(mission))
+    }
+
+    /// Parses a clock count with the given kind of fine field. Fails for counts beyond the
+    /// number per tick.
+    pub fn from_string_with(s: &str, fine_field: FineField) -> Result<Self, Error> {
+        let s = s.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+        let caps = match SCLK.captures(s) {
+            Some(caps) => caps,
+            None => {
+                return Err(Error::Format(format!(
+                    "Unrecognized spacecraft clock format: {}",
+                    s
+                )))
+            }
+        };
+        let counts = caps[2]
+            .split(':')
+            .map(|c| c.parse::<u64>())
+            .collect::<Result<Vec<u64>, _>>();
+        let fine = match (caps.get(3), fine_field) {
+            (None, _) => Ok(0),
+            (Some(f), FineField::Decimal) => Ok(fraction_to_nanos(f.as_str()) as u64),
+            (Some(f), FineField::Count(_)) => f.as_str().parse::<u64>(),
+        };
+        match (
+            caps.get(1).map_or(Ok(1), |p| p.as_str().parse::<u32>()),
+            counts,
+            fine,
+        ) {
+            (Ok(partition), Ok(counts), Ok(fine)) if !matches!(fine_field, FineField::Count(Some(m)) if fine >= m) => {
+                Ok(SpacecraftClock {
+                    partition,
+                    counts,
+                    fine,
+                    fine_field,
+                    digits: caps.get(3).map_or(0, |f| f.as_str().len()),
+                })
+            }
+            _ => Err(Error::Range(format!("Clock count out of range: {}", s))),
+        }
+    }
+
+    pub fn partition(&self) -> u32 {
+        self.partition
+    }
+
+    /// The same count in another partition, e.g. from SPACECRAFT_CLOCK_CNT_PARTITION
+    pub fn with_partition(&self, partition: u32) -> Self {
+        SpacecraftClock {
+            partition,
+            ..self.clone()
+        }
+    }
+
+    /// The fields of the count, most significant first
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// The coarse ticks, the most significant field of the count
+    pub fn ticks(&self) -> u64 {
+        self.counts[0]
+    }
+
+    /// The field following the point, in billionths of the last field where it's a decimal
+    /// fraction
+    pub fn fine(&self) -> u64 {
+        self.fine
+    }
+
+    pub fn fine_field(&self) -> FineField {
+        self.fine_field
+    }
+
+    /// The fine field as a fraction of the last field. Returns None for counts whose number
+    /// per tick isn't known.
+    pub fn fraction(&self) -> Option<f64> {
+        match self.fine_field {
+            FineField::Count(Some(modulus)) => Some(self.fine as f64 / modulus as f64),
+            FineField::Count(None) => None,
+            FineField::Decimal => Some(self.fine as f64 / NANOS_PER_SECOND as f64),
+        }
+    }
+
+    /// The count as a single number of coarse ticks, for clocks of one field
+    pub fn as_f64(&self) -> Option<f64> {
+        Some(self.counts[0] as f64 + self.fraction()?)
+    }
+
+    fn key(&self) -> (u32, &[u64], u64, FineField) {
+        (self.partition, &self.counts, self.fine, self.fine_field)
+    }
+}
+
+impl PartialEq for SpacecraftClock {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SpacecraftClock {}
+
+impl PartialOrd for SpacecraftClock {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SpacecraftClock {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl std::hash::Hash for SpacecraftClock {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+impl fmt::Display for SpacecraftClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts: Vec<String> = self.counts.iter().map(|c| c.to_string()).collect();
+        let fine = match (self.digits, self.fine_field) {
+            (0, _) => String::new(),
+            (digits, FineField::Decimal) => format_fraction(self.fine as u32, digits),
+            (digits, FineField::Count(_)) => format!(".{:0digits$}", self.fine),
+        };
+        write!(f, "{}/{}{}", self.partition, counts.join(":"), fine)
+    }
+}
+
+/// Whether a local solar time follows the mean or the true (apparent) sun
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SolarTimeKind {
+    Mean,
+    True,
+}
+
+/// A local solar time on a planet's surface, in 24 hours of the local sol, e.g. MSL's
+/// LOCAL_MEAN_SOLAR_TIME `Sol-03423M17:05:43.460` or LOCAL_TRUE_SOLAR_TIME `17:45:24`.
+/// Times are ordered by kind, as mean and true solar times differ, then by sol, then time of
+/// sol; times without a sol come first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LocalSolarTime {
+    kind: SolarTimeKind,
+    sol: Option<u32>,
+    nanos: u64, // Since local midnight
+}
+
+impl LocalSolarTime {
+    /// Parses a local solar time. Times with a sol prefix give their own kind (`M` for mean,
+    /// `T` for true); bare times of sol are taken to be true solar time, as in
+    /// LOCAL_TRUE_SOLAR_TIME. Surrounding quotes are ignored.
+    pub fn from_string(s: &str) -> Result<Self, Error> {
+        let s = s.trim().trim_matches(|c| c == '"' || c == '\'').trim();
+        let caps = match SOLAR_TIME.captures(s) {
+            Some(caps) => caps,
+            None => {
+                return Err(Error::Format(format!(
+                    "Unrecognized local solar time format: {}",
+                    s
+                )))
+            }
+        };
+        let (hour, minute, second) = (
+            capture_or_zero(&caps, 3),
+            capture_or_zero(&caps, 4),
+            capture_or_zero(&caps, 5),
+        );
+        if hour > 23 || minute > 59 || second > 60 {
+            return Err(Error::Range(format!("Invalid local solar time: {}", s)));
+        }
+        Ok(LocalSolarTime {
+            sol: caps.get(1).and_then(|m| m.as_str().parse::<u32>().ok()),
+            nanos: (hour as u64 * 3600 + minute as u64 * 60 + second as u64)
+                * NANOS_PER_SECOND as u64
+                + caps.get(6).map_or(0, |f| fraction_to_nanos(f.as_str())) as u64,
+            kind: match caps
+                .get(2)
+                .map(|m| m.as_str().to_ascii_uppercase())
+                .as_deref()
+            {
+                Some("M") => SolarTimeKind::Mean,
+                _ => SolarTimeKind::True,
+            },
+        })
+    }
+
+    /// The same time of sol on the given sol, e.g. from PLANET_DAY_NUMBER
+    pub fn with_sol(&self, sol: u32) -> Self {
+        LocalSolarTime {
+            sol: Some(sol),
+            ..*self
+        }
+    }
+
+    pub fn sol(&self) -> Option<u32> {
+        self.sol
+    }
+
+    pub fn kind(&self) -> SolarTimeKind {
+        self.kind
+    }
+
+    pub fn hour(&self) -> u32 {
+        (self.nanos / (3600 * NANOS_PER_SECOND as u64)) as u32
+    }
+
+    pub fn minute(&self) -> u32 {
+        (self.nanos / (60 * NANOS_PER_SECOND as u64) % 60) as u32
+    }
+
+    pub fn second(&self) -> u32 {
+        (self.nanos / NANOS_PER_SECOND as u64 % 60) as u32
+    }
+
+    pub fn nanosecond(&self) -> u32 {
+        (self.nanos % NANOS_PER_SECOND as u64) as u32
+    }
+
+    /// Fraction of the sol elapsed since local midnight
+    pub fn fraction_of_sol(&self) -> f64 {
+        self.nanos as f64 / (SECONDS_PER_DAY as f64 * NANOS_PER_SECOND as f64)
+    }
+}
+
+impl fmt::Display for LocalSolarTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time = format!(
+            "{:02}:{:02}:{:02}{}",
+            self.hour(),
+            self.minute(),
+            self.second(),
+            if self.nanosecond() > 0 {
+                format_fraction(self.nanosecond(), 3)
+            } else {
+                String::new()
+            }
+        );
+        match (self.sol, self.kind) {
+            (Some(sol), SolarTimeKind::Mean) => write!(f, "Sol-{:05}M{}", sol, time),
+            (Some(sol), SolarTimeKind::True) => write!(f, "Sol-{:05}T{}", sol, time),
+            (None, _) => write!(f, "{}", time),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::pvl::Error;
+use std::collections::HashMap;
+use std::fmt;
+
+/// PVL measurement units
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ValueUnits {
+    Celcius,
+    Farenheit,
+    Kelvin,
+    Degrees,
+    Radians,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+    Nanometers,
+    Micrometers,
+    Millimeters,
+    Meters,
+    Kilometers,
+    Hertz,
+    Megahertz,
+    Pixels,
+    Other(String), // Anything we don't specifically recognize, as written in the label
+}
+
+impl ValueUnits {
+    /// Determines the units from the text found between the angle brackets of a PVL value.
+    /// Unrecognized units are retained verbatim as `ValueUnits::Other`
+    pub fn from_string(s: &str) -> ValueUnits {
+        match s.trim().to_uppercase().as_str() {
+            "DEGC" | "CELCIUS" | "CELSIUS" => ValueUnits::Celcius,
+            "DEGF" | "FARENHEIT" | "FAHRENHEIT" => ValueUnits::Farenheit,
+            "K" | "DEGK" | "KELVIN" => ValueUnits::Kelvin,
+            "DEG" | "DEGREE" | "DEGREES" => ValueUnits::Degrees,
+            "RAD" | "RADIAN" | "RADIANS" => ValueUnits::Radians,
+            "US" | "USEC" | "MICROSECOND" | "MICROSECONDS" => ValueUnits::Microseconds,
+            "MS" | "MSEC" | "MILLISECOND" | "MILLISECONDS" => ValueUnits::Milliseconds,
+            "S" | "SEC" | "SECOND" | "SECONDS" => ValueUnits::Seconds,
+            "NM" | "NANOMETER" | "NANOMETERS" => ValueUnits::Nanometers,
+            "UM" | "MICRON" | "MICRONS" | "MICROMETER" | "MICROMETERS" => ValueUnits::Micrometers,
+            "MM" | "MILLIMETER" | "MILLIMETERS" => ValueUnits::Millimeters,
+            "M" | "METER" | "METERS" => ValueUnits::Meters,
+            "KM" | "KILOMETER" | "KILOMETERS" => ValueUnits::Kilometers,
+            "HZ" | "HERTZ" => ValueUnits::Hertz,
+            "MHZ" | "MEGAHERTZ" => ValueUnits::Megahertz,
+            "PIXEL" | "PIXELS" | "PIX" => ValueUnits::Pixels,
+            _ => ValueUnits::Other(s.trim().to_owned()),
+        }
+    }
+
+    /// The canonical PDS abbreviation for the units
+    pub fn as_str(&self) -> &str {
+        match self {
+            ValueUnits::Celcius => "degC",
+            ValueUnits::Farenheit => "degF",
+            ValueUnits::Kelvin => "K",
+            ValueUnits::Degrees => "deg",
+            ValueUnits::Radians => "rad",
+            ValueUnits::Microseconds => "us",
+            ValueUnits::Milliseconds => "ms",
+            ValueUnits::Seconds => "s",
+            ValueUnits::Nanometers => "nm",
+            ValueUnits::Micrometers => "um",
+            ValueUnits::Millimeters => "mm",
+            ValueUnits::Meters => "m",
+            ValueUnits::Kilometers => "km",
+            ValueUnits::Hertz => "Hz",
+            ValueUnits::Megahertz => "MHz",
+            ValueUnits::Pixels => "pixel",
+            ValueUnits::Other(s) => s.as_str(),
+        }
+    }
+}
+
+impl From<&str> for ValueUnits {
+    fn from(s: &str) -> Self {
+        ValueUnits::from_string(s)
+    }
+}
+
+impl fmt::Display for ValueUnits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A linear relationship between a unit and the base unit of its quantity, such that
+/// `base = value * scale + offset`
+#[derive(Debug, Clone, PartialEq)]
+struct Conversion {
+    quantity: String,
+    scale: f64,
+    offset: f64,
+}
+
+/// Lookup table of conversions between compatible units. Units are compatible if they
+/// measure the same quantity (angle, temperature, time, etc)
+#[derive(Debug, Clone)]
+pub struct UnitRegistry {
+    conversions: HashMap<ValueUnits, Conversion>,
+}
+
+lazy_static! {
+    static ref DEFAULT_REGISTRY: UnitRegistry = UnitRegistry::default();
+}
+
+impl Default for UnitRegistry {
+    /// Constructs a registry populated with the units commonly found in PDS and VICAR labels
+    fn default() -> Self {
+        let mut registry = UnitRegistry::new();
+        registry.register(ValueUnits::Radians, "angle", 1.0, 0.0);
+        registry.register(
+            ValueUnits::Degrees,
+            "angle",
+            std::f64::consts::PI / 180.0,
+            0.0,
+        );
+        registry.register(ValueUnits::Kelvin, "temperature", 1.0, 0.0);
+        registry.register(ValueUnits::Celcius, "temperature", 1.0, 273.15);
+        registry.register(
+            ValueUnits::Farenheit,
+            "temperature",
+            5.0 / 9.0,
+            273.15 - 32.0 * 5.0 / 9.0,
+        );
+        registry.register(ValueUnits::Seconds, "time", 1.0, 0.0);
+        registry.register(ValueUnits::Milliseconds, "time", 1.0e-3, 0.0);
+        registry.register(ValueUnits::Microseconds, "time", 1.0e-6, 0.0);
+        registry.register(ValueUnits::Meters, "length", 1.0, 0.0);
+        registry.register(ValueUnits::Kilometers, "length", 1.0e3, 0.0);
+        registry.register(ValueUnits::Millimeters, "length", 1.0e-3, 0.0);
+        registry.register(ValueUnits::Micrometers, "length", 1.0e-6, 0.0);
+        registry.register(ValueUnits::Nanometers, "length", 1.0e-9, 0.0);
+        registry.register(ValueUnits::Hertz, "frequency", 1.0, 0.0);
+        registry.register(ValueUnits::Megahertz, "frequency", 1.0e6, 0.0);
+        registry
+    }
+}
+
+impl UnitRegistry {
+    /// Constructs an empty registry
+    pub fn new() -> Self {
+        UnitRegistry {
+            conversions: HashMap::new(),
+        }
+    }
+
+    /// Adds or replaces a unit in the registry. `quantity` names what the unit measures, and
+    /// `scale` and `offset` convert a value in these units to the base units of that quantity.
+    pub fn register(&mut self, units: ValueUnits, quantity: &str, scale: f64, offset: f64) {
+        self.conversions.insert(
+            units,
+            Conversion {
+                quantity: quantity.to_owned(),
+                scale,
+                offset,
+            },
+        );
+    }
+
+    /// Returns true if a value can be converted between the two units
+    pub fn is_compatible(&self, from: &ValueUnits, to: &ValueUnits) -> bool {
+        match (self.conversions.get(from), self.conversions.get(to)) {
+            (Some(f), Some(t)) => f.quantity == t.quantity,
+            _ => from == to,
+        }
+    }
+
+    /// Converts a value from one unit to another compatible unit
+    pub fn convert(&self, value: f64, from: &ValueUnits, to: &ValueUnits) -> Result<f64, Error> {
+        if from == to {
+            return Ok(value);
+        }
+        match (self.conversions.get(from), self.conversions.get(to)) {
+            (Some(f), Some(t)) if f.quantity == t.quantity => {
+                Ok((value * f.scale + f.offset - t.offset) / t.scale)
+            }
+            (Some(f), Some(t)) => Err(Error::UnitConversion(format!(
+                "Cannot convert {} ({}) to {} ({})",
+                from, f.quantity, to, t.quantity
+            ))),
+            _ => Err(Error::UnitConversion(format!(
+                "No conversion registered between {} and {}",
+                from, to
+            ))),
+        }
+    }
+}
+
+/// Converts a value between units using the default registry
+pub fn convert(value: f64, from: &ValueUnits, to: &ValueUnits) -> Result<f64, Error> {
+    DEFAULT_REGISTRY.convert(value, from, to)
+}
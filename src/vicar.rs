@@ -1,5 +1,10 @@
-use crate::pvl::{PropertyGrouping, Pvl};
-use regex::Regex;
+use crate::companding::{CompandingRegistry, CompandingTable};
+use crate::mask::{MaskedBand, PixelClass, SpecialValues};
+use crate::pvl::{self, ParseMode, PropertyGrouping, Pvl, Symbol, Warning};
+use crate::radiometry::Scaling;
+use crate::statistics::{
+    Disagreement, Statistic, Statistics, StatisticsAccumulator, StatisticsOptions, Window,
+};
 use sciimg::binfilereader::*;
 use std::borrow::Cow;
 use std::ffi::OsStr;
@@ -7,6 +12,12 @@ use std::fs;
 use std::path::Path;
 use std::{error::Error, fmt};
 
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod label;
+pub use crate::pvl::{KeyValuePair, Value, ValueType};
+pub use label::{LabelSection, VicarLabel};
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum PixelFormat {
     Byte,    // One byte, u8
@@ -125,126 +136,21 @@ impl From<anyhow::Error> for VicarError {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub enum ValueType {
-    Undetermined,
-    Array,
-    String,
-    Float,
-    Integer,
-    Bool,
-    Flag, // A string but not wrapped in quotes
-    BitMask,
-}
-
-/// Contains PVL right-hand values and flags
-#[derive(Debug, Clone)]
-pub struct Value {
-    value_raw: String,
-    value_type: ValueType,
-}
-
-lazy_static! {
-    static ref BOOL_DETERMINATE: Regex = Regex::new("^['\"](TRUE|FALSE)['\"]$").unwrap();
-    static ref STRING_DETERMINATE: Regex = Regex::new("^['\"].*['\"]$").unwrap();
-    static ref ARRAY_DETERMINATE: Regex = Regex::new("^\\(.*\\)$").unwrap();
-    static ref FLOAT_DETERMINATE: Regex = Regex::new("^-*[0-9]+\\.[0-9][ ]*").unwrap();
-    static ref INTEGER_DETERMINATE: Regex = Regex::new("^[+-]*[0-9]+[^#a-zA-Z]*[ ]*").unwrap();
-    static ref FLAG_DETERMINATE: Regex = Regex::new("^[a-zA-Z_]+[a-zA-Z0-9]+$").unwrap();
-    static ref BITMASK_DETERMINATE: Regex = Regex::new("^[1-8]*#+[0-1]+#+$").unwrap();
-}
-
-#[macro_export]
-macro_rules! impl_parse_vic_fn {
-    ($fn_name:ident, $type:ty, $value_type:expr) => {
-        // the $values just get swapped in.
-        pub fn $fn_name(&self) -> Result<$type, VicarError> {
-            // I'm gonna allow parsing if the type is undetermined. A type being undetermined is my problem, but
-            // the user will have the option (and risk) of parsing it
-            if self.value_type != ValueType::Undetermined && self.value_type != $value_type {
-                Err(VicarError::InvalidType)
-            } else {
-                match self.value_raw.parse::<$type>() {
-                    Ok(v) => Ok(v),
-                    Err(_) => Err(VicarError::ValueTypeParseError),
-                }
-            }
-        }
-    };
-}
-
-impl Value {
-    impl_parse_vic_fn!(parse_f32, f32, ValueType::Float);
-    impl_parse_vic_fn!(parse_f64, f64, ValueType::Float);
-    impl_parse_vic_fn!(parse_u8, u8, ValueType::Integer);
-    impl_parse_vic_fn!(parse_u16, u16, ValueType::Integer);
-    impl_parse_vic_fn!(parse_u32, u32, ValueType::Integer);
-    impl_parse_vic_fn!(parse_u64, u64, ValueType::Integer);
-    impl_parse_vic_fn!(parse_usize, usize, ValueType::Integer);
-    impl_parse_vic_fn!(parse_i8, i8, ValueType::Integer);
-    impl_parse_vic_fn!(parse_i16, i16, ValueType::Integer);
-    impl_parse_vic_fn!(parse_i32, i32, ValueType::Integer);
-    impl_parse_vic_fn!(parse_i64, i64, ValueType::Integer);
-    impl_parse_vic_fn!(parse_bool, bool, ValueType::Bool);
-    impl_parse_vic_fn!(parse_flag, String, ValueType::Flag);
-
-    /// Constructs a new Value object and determines type of provided raw data
-    pub fn new(value_raw: &str) -> Self {
-        Value {
-            value_raw: value_raw.to_owned(),
-            value_type: Value::determine_type(value_raw),
-        }
-    }
-
-    /// Determines the data type of the raw value based on regex matches.
-    fn determine_type(value_raw: &str) -> ValueType {
-        if BOOL_DETERMINATE.is_match(value_raw) {
-            ValueType::Bool
-        } else if STRING_DETERMINATE.is_match(value_raw) {
-            ValueType::String
-        } else if ARRAY_DETERMINATE.is_match(value_raw) {
-            ValueType::Array
-        } else if FLOAT_DETERMINATE.is_match(value_raw) {
-            ValueType::Float
-        } else if BITMASK_DETERMINATE.is_match(value_raw) {
-            ValueType::BitMask
-        } else if INTEGER_DETERMINATE.is_match(value_raw) {
-            ValueType::Integer
-        } else if FLAG_DETERMINATE.is_match(value_raw) {
-            ValueType::Flag
-        } else {
-            ValueType::Undetermined
-        }
-    }
-
-    pub fn parse_string(&self) -> Result<String, VicarError> {
-        // I'm gonna allow parsing if the type is undetermined. A type being undetermined is my problem, but
-        // the user will have the option (and risk) of parsing it
-        if self.value_type != ValueType::Undetermined && self.value_type != ValueType::String {
-            Err(VicarError::InvalidType)
-        } else {
-            Ok(self.value_raw.replace(['\"', '\''], ""))
-        }
-    }
-
-    /// Parses the raw data value to an array of Values. Throws an error if we are not an array type
-    pub fn parse_array(&self) -> Result<Vec<Value>, VicarError> {
-        if self.value_type != ValueType::Array {
-            Err(VicarError::InvalidType)
-        } else {
-            Ok(self.value_raw[1..(self.value_raw.len() - 1)]
-                .split(',')
-                .map(Value::new)
-                .collect())
-        }
+impl From<pvl::Error> for VicarError {
+    fn from(value: pvl::Error) -> Self {
+        VicarError::General(t!(value))
     }
 }
 
-/// Represents the basic KEY = VALUE pair in a PVL file
-#[derive(Debug, Clone)]
-pub struct KeyValuePair {
-    pub key: String,
-    pub value: Value,
+/// Options controlling the values returned by `VicarReader::read_value`. The default returns
+/// values as stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Applies the radiometric scaling given by the label, returning physical values
+    pub scale: bool,
+    /// Reverses the companding of 8 bit products, returning linear values. Linearizing comes
+    /// before scaling.
+    pub linearize: bool,
 }
 
 /// Main PVL parsing engine
@@ -263,6 +169,10 @@ pub struct VicarReader {
     pub format: PixelFormat,
     pub data_type: DataType,
     pub strings: String,
+    detached_label: Option<Pvl>,
+    options: ReadOptions,
+    scaling: Option<Scaling>,
+    companding: Option<CompandingTable>,
 }
 
 impl fmt::Display for VicarReader {
@@ -338,6 +248,10 @@ impl VicarReader {
                     strings,
                     binary_bytes_before_record: 0,
                     binary_bytes_header: 0,
+                    detached_label: Some(pvl),
+                    options: ReadOptions::default(),
+                    scaling: None,
+                    companding: None,
                 })
             } else {
                 Err(VicarError::PropertyNotFound(t!("IMAGE")))
@@ -428,6 +342,10 @@ impl VicarReader {
             strings,
             binary_bytes_before_record: nbb,
             binary_bytes_header: nlb,
+            detached_label: None,
+            options: ReadOptions::default(),
+            scaling: None,
+            companding: None,
         })
     }
 
@@ -466,7 +384,7 @@ impl VicarReader {
     }
 
     /// Returns the character at the specified index, or `Error::Eof` if the  index is beyond the limit of the text
-    fn _char_at(strings: &String, indx: usize) -> Result<char, VicarError> {
+    fn _char_at(strings: &str, indx: usize) -> Result<char, VicarError> {
         if indx >= strings.len() {
             Err(VicarError::Eof)
         } else {
@@ -489,7 +407,7 @@ impl VicarReader {
         index >= self.strings.len()
     }
 
-    fn _scan_for_property(strings: &String, key: &str) -> Result<usize, VicarError> {
+    fn _scan_for_property(strings: &str, key: &str) -> Result<usize, VicarError> {
         let key_eq = format!("{}=", key);
         for i in 0..(strings.len() - key_eq.len()) {
             if strings[i..(i + key_eq.len())] == key_eq {
@@ -504,7 +422,7 @@ impl VicarReader {
         VicarReader::_scan_for_property(&self.strings, key)
     }
 
-    fn _has_property(strings: &String, key: &str) -> bool {
+    fn _has_property(strings: &str, key: &str) -> bool {
         VicarReader::_scan_for_property(strings, key).is_ok()
     }
 
@@ -512,7 +430,7 @@ impl VicarReader {
         VicarReader::_has_property(&self.strings, key)
     }
 
-    fn _has_internal_label(strings: &String) -> bool {
+    fn _has_internal_label(strings: &str) -> bool {
         VicarReader::_has_property(strings, "LBLSIZE")
     }
 
@@ -520,14 +438,23 @@ impl VicarReader {
         VicarReader::_has_internal_label(&self.strings)
     }
 
-    pub fn _extract_property_raw(strings: &String, key: &str) -> Result<String, VicarError> {
+    pub fn _extract_property_raw(strings: &str, key: &str) -> Result<String, VicarError> {
         let index = VicarReader::_scan_for_property(strings, key)?;
         let mut end_index = index;
 
+        // The value ends at the first space that isn't within a quoted string or parentheses
+        let mut in_quotes = false;
+        let mut depth = 0;
         for i in (index + 1)..strings.len() {
-            if VicarReader::_char_at(strings, i).unwrap() == ' ' {
-                end_index = i;
-                break;
+            match VicarReader::_char_at(strings, i).unwrap() {
+                '\'' => in_quotes = !in_quotes,
+                '(' if !in_quotes => depth += 1,
+                ')' if !in_quotes => depth -= 1,
+                ' ' if !in_quotes && depth == 0 => {
+                    end_index = i;
+                    break;
+                }
+                _ => {}
             }
         }
 
@@ -540,7 +467,7 @@ impl VicarReader {
         VicarReader::_extract_property_raw(&self.strings, key)
     }
 
-    fn _get_property(strings: &String, key: &str) -> Result<KeyValuePair, VicarError> {
+    fn _get_property(strings: &str, key: &str) -> Result<KeyValuePair, VicarError> {
         let property_raw = VicarReader::_extract_property_raw(strings, key)?;
 
         let parts: Vec<String> = property_raw.split('=').map(|p| p.to_string()).collect();
@@ -552,7 +479,7 @@ impl VicarReader {
             )))
         } else {
             Ok(KeyValuePair {
-                key: parts[0].to_owned(),
+                key: Symbol::Key(parts[0].to_owned()),
                 value: Value::new(parts[1].as_str()),
             })
         }
@@ -562,6 +489,22 @@ impl VicarReader {
         VicarReader::_get_property(&self.strings, key)
     }
 
+    /// Parses the VICAR label into its system, property and history sections
+    pub fn label(&self) -> Result<VicarLabel, VicarError> {
+        Ok(self.parse_label(ParseMode::Lenient)?.0)
+    }
+
+    /// Parses the VICAR label with the requested strictness. See `VicarLabel::parse`
+    pub fn parse_label(&self, mode: ParseMode) -> Result<(VicarLabel, Vec<Warning>), VicarError> {
+        let start = self.scan_for_property("LBLSIZE")?;
+        let lblsize = self.get_property("LBLSIZE")?.value.parse_usize()?;
+        let end = (start + lblsize).min(self.strings.len());
+        match self.strings.get(start..end) {
+            Some(s) => VicarLabel::parse(s, mode),
+            None => Err(VicarError::LabelError(t!("Label is not valid text"))),
+        }
+    }
+
     fn get_pixel_index(&self, line: usize, sample: usize, band: usize) -> usize {
         (self.lines * self.samples * self.format.bytes_per_sample() * band
             + line * self.binary_bytes_before_record)
@@ -598,4 +541,273 @@ impl VicarReader {
             PixelFormat::Comp | PixelFormat::Complex => todo!(),
         }
     }
+
+    /// Reads the radiometric scaling from the detached PDS3 label the reader was opened with,
+    /// or from the VICAR label
+    pub fn label_scaling(&self) -> Result<Option<Scaling>, VicarError> {
+        match &self.detached_label {
+            Some(pvl) => Ok(Scaling::from_label(pvl)?),
+            None => Scaling::from_label(&self.label()?),
+        }
+    }
+
+    /// Sets the options of subsequent reads, reading what they need from the label. Companding
+    /// is reversed with the tables embedded in the crate, see `CompandingRegistry::published`.
+    /// Fails if linearizing is requested for a product companded with a table not among them;
+    /// use `set_read_options_with_registry` to supply other tables.
+    pub fn set_read_options(&mut self, options: ReadOptions) -> Result<(), VicarError> {
+        let registry = if options.linearize {
+            CompandingRegistry::published()?
+        } else {
+            CompandingRegistry::new()
+        };
+        self.set_read_options_with_registry(options, &registry)
+    }
+
+    /// Sets the options of subsequent reads, taking companding tables from the registry. Fails
+    /// if linearizing is requested for a product companded with a table not registered.
+    pub fn set_read_options_with_registry(
+        &mut self,
+        options: ReadOptions,
+        registry: &CompandingRegistry,
+    ) -> Result<(), VicarError> {
+        self.scaling = if options.scale {
+            self.label_scaling()?
+        } else {
+            None
+        };
+        self.companding = if options.linearize {
+            match &self.detached_label {
+                Some(pvl) => registry.for_label(pvl)?.cloned(),
+                None => registry.for_label(&self.label()?)?.cloned(),
+            }
+        } else {
+            None
+        };
+        self.options = options;
+        Ok(())
+    }
+
+    pub fn read_options(&self) -> ReadOptions {
+        self.options
+    }
+
+    /// The scaling applied by reads, when requested by the read options and given by the label.
+    /// Reports the keywords and units of the physical values.
+    pub fn scaling(&self) -> Option<&Scaling> {
+        self.scaling.as_ref()
+    }
+
+    /// The table reversed by reads, when linearizing is requested for a companded product
+    pub fn companding(&self) -> Option<&CompandingTable> {
+        self.companding.as_ref()
+    }
+
+    /// Reads a pixel value as requested by the read options
+    /// # Example
+    /// ```
+    /// use vicar::vicar::{ReadOptions, VicarReader};
+    ///
+    /// let mut vr = VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG").unwrap();
+    /// let dn = vr.read_value(512, 512, 0).unwrap();
+    /// vr.set_read_options(ReadOptions { scale: true, ..Default::default() }).unwrap();
+    /// assert_eq!(vr.read_value(512, 512, 0).unwrap(), dn * 1.0e-5);
+    /// ```
+    pub fn read_value(&self, line: usize, sample: usize, band: usize) -> Result<f64, VicarError> {
+        let value = self.get_pixel_value(line, sample, band)? as f64;
+        self.convert_value(value, line, sample, band)
+    }
+
+    /// Linearizes and scales a stored value as requested by the read options
+    fn convert_value(
+        &self,
+        mut value: f64,
+        line: usize,
+        sample: usize,
+        band: usize,
+    ) -> Result<f64, VicarError> {
+        if let Some(table) = &self.companding {
+            value = match table.linearize(value) {
+                Some(v) => v,
+                None => {
+                    return Err(VicarError::General(format!(
+                        "Value {} at line {}, sample {} is outside the companding table",
+                        value, line, sample
+                    )))
+                }
+            };
+        }
+        match &self.scaling {
+            Some(scaling) => Ok(scaling.apply(value, band)),
+            None => Ok(value),
+        }
+    }
+
+    /// Reads every value of a band, line by line, as requested by the read options
+    pub fn read_band(&self, band: usize) -> Result<Vec<f64>, VicarError> {
+        let mut values = Vec::with_capacity(self.lines * self.samples);
+        for line in 0..self.lines {
+            for sample in 0..self.samples {
+                values.push(self.read_value(line, sample, band)?);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Reads the special values from the detached PDS3 label the reader was opened with, or
+    /// from the VICAR label
+    pub fn special_values(&self) -> Result<SpecialValues, VicarError> {
+        match &self.detached_label {
+            Some(pvl) => Ok(SpecialValues::from_label(pvl)),
+            None => Ok(SpecialValues::from_label(&self.label()?)),
+        }
+    }
+
+    /// Classifies a value as stored, against the valid range in linear values where a
+    /// companding table is set
+    fn classify(&self, special: &SpecialValues, value: f64) -> PixelClass {
+        let linear = match &self.companding {
+            Some(table) => table.linearize(value),
+            None => Some(value),
+        };
+        special.classify_linearized(value, linear.unwrap_or(value))
+    }
+
+    /// Reads every value of a band as requested by the read options, along with the class of
+    /// each pixel. Pixels are classified by their values as stored; special values which
+    /// aren't valid input to the companding table are returned as stored.
+    /// # Example
+    /// ```
+    /// use vicar::mask::PixelClass;
+    /// use vicar::vicar::VicarReader;
+    ///
+    /// let vr = VicarReader::new("tests/testdata/cassini/nac/N1884111831_1.IMG").unwrap();
+    /// let band = vr.read_band_masked(0).unwrap();
+    /// assert_eq!(band.count(PixelClass::Missing), 0);
+    /// ```
+    pub fn read_band_masked(&self, band: usize) -> Result<MaskedBand, VicarError> {
+        let special = self.special_values()?;
+        let mut stored = Vec::with_capacity(self.lines * self.samples);
+        for line in 0..self.lines {
+            for sample in 0..self.samples {
+                stored.push(self.get_pixel_value(line, sample, band)? as f64);
+            }
+        }
+        let mut mask: Vec<PixelClass> =
+            stored.iter().map(|v| self.classify(&special, *v)).collect();
+        special.mark_missing_lines(&stored, &mut mask, self.samples);
+
+        let mut values = Vec::with_capacity(stored.len());
+        for (i, (value, class)) in stored.iter().zip(mask.iter()).enumerate() {
+            let (line, sample) = (i / self.samples, i % self.samples);
+            values.push(match self.convert_value(*value, line, sample, band) {
+                Ok(v) => v,
+                Err(_) if *class != PixelClass::Valid => *value,
+                Err(e) => return Err(e),
+            });
+        }
+        Ok(MaskedBand {
+            values,
+            mask,
+            lines: self.lines,
+            samples: self.samples,
+        })
+    }
+
+    /// Gathers statistics of the valid values of a band, or of a window of it, as requested by
+    /// the read options. Values are read a line at a time, and pixels are classified as by
+    /// `read_band_masked`.
+    /// # Example
+    /// ```
+    /// use vicar::statistics::StatisticsOptions;
+    /// use vicar::vicar::VicarReader;
+    ///
+    /// let vr = VicarReader::new("tests/testdata/cassini/wac/W1884114531_2.IMG").unwrap();
+    /// let stats = vr.band_statistics(0, &StatisticsOptions::default()).unwrap();
+    /// assert_eq!(stats.high_saturation, 200);
+    /// assert!(stats.maximum.unwrap() < 4095.0);
+    /// ```
+    pub fn band_statistics(
+        &self,
+        band: usize,
+        options: &StatisticsOptions,
+    ) -> Result<Statistics, VicarError> {
+        let window = options
+            .window
+            .unwrap_or_else(|| Window::new(0, 0, self.lines, self.samples));
+        if band >= self.bands
+            || window.line + window.lines > self.lines
+            || window.sample + window.samples > self.samples
+        {
+            return Err(VicarError::General(format!(
+                "Window {:?} of band {} is outside the image",
+                window, band
+            )));
+        }
+        let special = self.special_values()?;
+        let mut stats = StatisticsAccumulator::new(options.bin_width);
+
+        // Lost lines are counted from the end of the image, so lines after the window are
+        // scanned for them too
+        let last = if special.missing_lines > 0 {
+            self.lines
+        } else {
+            window.line + window.lines
+        };
+        let mut missing = special.missing_lines;
+        let mut stored = vec![0.0; self.samples];
+        for line in (window.line..last).rev() {
+            for (sample, value) in stored.iter_mut().enumerate() {
+                *value = self.get_pixel_value(line, sample, band)? as f64;
+            }
+            let lost = missing > 0 && stored.iter().all(|v| *v == 0.0);
+            if lost {
+                missing -= 1;
+            }
+            if line >= window.line + window.lines {
+                continue;
+            }
+            for (sample, value) in stored
+                .iter()
+                .enumerate()
+                .skip(window.sample)
+                .take(window.samples)
+            {
+                let class = if lost {
+                    PixelClass::Missing
+                } else {
+                    self.classify(&special, *value)
+                };
+                let value = match self.convert_value(*value, line, sample, band) {
+                    Ok(v) => v,
+                    Err(_) if class != PixelClass::Valid => *value,
+                    Err(e) => return Err(e),
+                };
+                stats.add(value, class);
+            }
+        }
+        Ok(stats.finish())
+    }
+
+    /// Gathers statistics of every band
+    pub fn statistics(&self, options: &StatisticsOptions) -> Result<Vec<Statistics>, VicarError> {
+        (0..self.bands)
+            .map(|band| self.band_statistics(band, options))
+            .collect()
+    }
+
+    /// Compares statistics with those given by the detached PDS3 label the reader was opened
+    /// with, or by the VICAR label, flagging those which disagree beyond the relative
+    /// tolerance. See `Statistics::compare_with_label`.
+    pub fn compare_statistics(
+        &self,
+        stats: &Statistics,
+        keywords: &[(&str, Statistic)],
+        tolerance: f64,
+    ) -> Result<Vec<Disagreement>, VicarError> {
+        match &self.detached_label {
+            Some(pvl) => Ok(stats.compare_with_label(pvl, keywords, tolerance)),
+            None => Ok(stats.compare_with_label(&self.label()?, keywords, tolerance)),
+        }
+    }
 }
@@ -0,0 +1,37 @@
+//! Deserialization of VICAR label sections into user structs with serde. Requires the `serde`
+//! feature. Values are handled as for PVL, see `pvl::de`.
+use super::{LabelSection, VicarError, VicarLabel};
+use crate::pvl::de::from_properties;
+use serde::de::DeserializeOwned;
+
+/// Deserializes the items of a PROPERTY or TASK section into a user struct
+/// # Example
+/// ```
+/// use serde::Deserialize;
+/// use vicar::vicar::{de, VicarLabel};
+///
+/// #[derive(Deserialize)]
+/// struct Camera {
+///     #[serde(rename = "EXPOSURE")]
+///     exposure: f32,
+///     #[serde(rename = "FILTER")]
+///     filter: Vec<String>,
+/// }
+///
+/// let label = VicarLabel::from_string(
+///     "LBLSIZE=64  PROPERTY='CAMERA'  EXPOSURE=12.5  FILTER=('L1','R1')",
+/// )
+/// .unwrap();
+/// let camera: Camera = de::from_section(label.get_property_section("CAMERA").unwrap()).unwrap();
+/// assert_eq!(camera.exposure, 12.5);
+/// assert_eq!(camera.filter, vec!["L1", "R1"]);
+/// ```
+pub fn from_section<T: DeserializeOwned>(section: &LabelSection) -> Result<T, VicarError> {
+    Ok(from_properties(&section.items)?)
+}
+
+/// Deserializes the system items of a label, LBLSIZE through to the first section, into a user
+/// struct
+pub fn from_system<T: DeserializeOwned>(label: &VicarLabel) -> Result<T, VicarError> {
+    Ok(from_properties(&label.system)?)
+}
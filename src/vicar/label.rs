@@ -0,0 +1,308 @@
+use super::{KeyValuePair, Value, VicarError};
+use crate::pvl::{self, query::glob_match, FromValue, ParseMode, QueryPath, Span, Symbol, Warning};
+
+/// A named PROPERTY or history TASK section of a VICAR label
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabelSection {
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::pvl::ser::properties"))]
+    pub items: Vec<KeyValuePair>,
+}
+
+impl LabelSection {
+    fn new(name: &str) -> Self {
+        LabelSection {
+            name: name.to_owned(),
+            items: vec![],
+        }
+    }
+
+    pub fn get_property(&self, key: &str) -> Option<&KeyValuePair> {
+        self.items.iter().find(|kvp| kvp.key.is_key(key))
+    }
+
+    pub fn has_property(&self, key: &str) -> bool {
+        self.get_property(key).is_some()
+    }
+}
+
+/// A VICAR label, divided into its system items (LBLSIZE through to the first PROPERTY or
+/// TASK), property sections and history (task) sections
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VicarLabel {
+    #[cfg_attr(feature = "serde", serde(with = "crate::pvl::ser::properties"))]
+    pub system: Vec<KeyValuePair>,
+    pub properties: Vec<LabelSection>,
+    pub history: Vec<LabelSection>,
+}
+
+/// Parses the `KEY=VALUE` items of a VICAR label
+struct LabelParser<'a> {
+    text: &'a str,
+    pos: usize,
+    mode: ParseMode,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> LabelParser<'a> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.text.len() || self.text.as_bytes()[self.pos] == b'\0'
+    }
+
+    fn current(&self) -> u8 {
+        self.text.as_bytes()[self.pos]
+    }
+
+    fn skip_whitespace(&mut self) {
+        while !self.at_end() && self.current().is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    /// Records a violation of the specification in the item spanning `start..end`. In strict
+    /// mode this is an error, in lenient mode it is kept as a warning and parsing carries on.
+    fn violation(&mut self, message: &str, start: usize, end: usize) -> Result<(), VicarError> {
+        let warning = Warning {
+            message: message.to_owned(),
+            span: Span::from_range(self.text, start, end),
+            snippet: self.text[start..end.min(self.text.len())].to_owned(),
+        };
+        match self.mode {
+            ParseMode::Strict => Err(VicarError::Syntax(format!(
+                "{}: {}",
+                warning, warning.snippet
+            ))),
+            ParseMode::Lenient => {
+                self.warnings.push(warning);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads up to the next whitespace, `=` or the end of the label
+    fn read_key(&mut self) -> &'a str {
+        let start = self.pos;
+        while !self.at_end() && !self.current().is_ascii_whitespace() && self.current() != b'=' {
+            self.pos += 1;
+        }
+        &self.text[start..self.pos]
+    }
+
+    /// Reads a value: a quoted string (where `''` is an escaped quote), a parenthesized list,
+    /// or anything else up to the next whitespace. Returns whether the value was terminated.
+    fn read_value(&mut self) -> (&'a str, bool) {
+        let start = self.pos;
+        let mut in_quotes = false;
+        let mut depth = 0;
+        while !self.at_end() {
+            match self.current() {
+                b'\'' if in_quotes && self.text.as_bytes().get(self.pos + 1) == Some(&b'\'') => {
+                    self.pos += 1;
+                }
+                b'\'' => in_quotes = !in_quotes,
+                b'(' if !in_quotes => depth += 1,
+                b')' if !in_quotes => depth -= 1,
+                c if c.is_ascii_whitespace() && !in_quotes && depth <= 0 => break,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        (&self.text[start..self.pos], !in_quotes && depth <= 0)
+    }
+}
+
+impl VicarLabel {
+    /// Parses VICAR label text, which begins with LBLSIZE. The label ends at the first NUL or
+    /// the end of the text.
+    pub fn from_string(content: &str) -> Result<Self, VicarError> {
+        Ok(VicarLabel::parse(content, ParseMode::Lenient)?.0)
+    }
+
+    /// Parses VICAR label text with the requested strictness. Returns the warnings for any
+    /// violations recovered from when parsing leniently.
+    /// # Example
+    /// ```
+    /// use vicar::pvl::ParseMode;
+    /// use vicar::vicar::VicarLabel;
+    ///
+    /// let s = "LBLSIZE=64  FORMAT='BYTE'  PROPERTY='CAMERA'  NAME='A'  NOVALUE  TASK='X'";
+    /// assert!(VicarLabel::parse(s, ParseMode::Strict).is_err());
+    ///
+    /// let (label, warnings) = VicarLabel::parse(s, ParseMode::Lenient).unwrap();
+    /// assert_eq!(label.properties[0].name, "CAMERA");
+    /// assert_eq!(warnings.len(), 1);
+    /// ```
+    pub fn parse(content: &str, mode: ParseMode) -> Result<(Self, Vec<Warning>), VicarError> {
+        let mut label = VicarLabel {
+            system: vec![],
+            properties: vec![],
+            history: vec![],
+        };
+        let mut parser = LabelParser {
+            text: content,
+            pos: 0,
+            mode,
+            warnings: vec![],
+        };
+        let mut in_history = false;
+
+        loop {
+            parser.skip_whitespace();
+            if parser.at_end() {
+                break;
+            }
+
+            let start = parser.pos;
+            let key = parser.read_key();
+            if parser.at_end() || parser.current() != b'=' {
+                parser.violation(&format!("Item {} is missing '='", key), start, parser.pos)?;
+                continue;
+            }
+            parser.pos += 1;
+
+            let (value_raw, terminated) = parser.read_value();
+            if key.is_empty() {
+                parser.violation("Item is missing its key", start, parser.pos)?;
+                continue;
+            } else if value_raw.is_empty() {
+                parser.violation(&format!("Item {} has no value", key), start, parser.pos)?;
+            } else if !terminated {
+                parser.violation(
+                    &format!("Value of {} is not terminated", key),
+                    start,
+                    parser.pos,
+                )?;
+            }
+
+            let is_first =
+                label.system.is_empty() && label.properties.is_empty() && label.history.is_empty();
+            if is_first && key != "LBLSIZE" {
+                parser.violation("Label doesn't begin with LBLSIZE", start, parser.pos)?;
+            }
+
+            let value = Value::new(value_raw);
+            match key {
+                "PROPERTY" => {
+                    if !label.history.is_empty() {
+                        parser.violation(
+                            "PROPERTY section follows the history",
+                            start,
+                            parser.pos,
+                        )?;
+                    }
+                    label
+                        .properties
+                        .push(LabelSection::new(&value.parse_string().unwrap_or_default()));
+                    in_history = false;
+                }
+                "TASK" => {
+                    label
+                        .history
+                        .push(LabelSection::new(&value.parse_string().unwrap_or_default()));
+                    in_history = true;
+                }
+                _ => {
+                    let kvp = KeyValuePair {
+                        key: Symbol::Key(key.to_owned()),
+                        value,
+                    };
+                    // Items belong to whichever section was started last
+                    let section = if in_history {
+                        label.history.last_mut()
+                    } else {
+                        label.properties.last_mut()
+                    };
+                    match section {
+                        Some(s) => s.items.push(kvp),
+                        None => label.system.push(kvp),
+                    }
+                }
+            }
+        }
+
+        Ok((label, parser.warnings))
+    }
+
+    pub fn get_system_property(&self, key: &str) -> Option<&KeyValuePair> {
+        self.system.iter().find(|kvp| kvp.key.is_key(key))
+    }
+
+    /// Retrieves the PROPERTY section with the requested name
+    pub fn get_property_section(&self, name: &str) -> Option<&LabelSection> {
+        self.properties.iter().find(|s| s.name == name)
+    }
+
+    /// Retrieves the first history section written by the requested task
+    pub fn get_task(&self, name: &str) -> Option<&LabelSection> {
+        self.history.iter().find(|s| s.name == name)
+    }
+
+    /// Retrieves and converts every value matching a query path, in label order. A single
+    /// segment refers to a system item, e.g. `NL`, and two segments to an item of a property
+    /// or history section, e.g. `IDENTIFICATION/INSTRUMENT_ID`. Wildcards and indices are as
+    /// described for `QueryPath`, and `**` matches both system items and sections.
+    /// # Example
+    /// ```
+    /// use vicar::pvl;
+    /// use vicar::vicar::VicarLabel;
+    ///
+    /// let label = VicarLabel::from_string(
+    ///     "LBLSIZE=64  PROPERTY='CAMERA'  FILTER=('L1', 'R1')  TASK='LABEL'  FILTER='NONE'",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(label.query_one::<usize>("LBLSIZE").unwrap(), 64);
+    /// assert_eq!(label.query::<String>("CAMERA/FILTER[1]").unwrap(), vec!["R1"]);
+    /// assert_eq!(label.query::<String>("LABEL/FILTER").unwrap(), vec!["NONE"]);
+    /// assert_eq!(label.query::<pvl::Value>("*/FILTER").unwrap().len(), 2);
+    /// ```
+    pub fn query<T: FromValue>(&self, path: &str) -> Result<Vec<T>, VicarError> {
+        Ok(self
+            .find_values(path)?
+            .iter()
+            .map(T::from_value)
+            .collect::<Result<Vec<T>, pvl::Error>>()?)
+    }
+
+    /// Retrieves and converts the first value matching a query path. Fails if there is no match.
+    pub fn query_one<T: FromValue>(&self, path: &str) -> Result<T, VicarError> {
+        match self.find_values(path)?.first() {
+            Some(v) => Ok(T::from_value(v)?),
+            None => Err(VicarError::PropertyNotFound(path.to_owned())),
+        }
+    }
+
+    pub(crate) fn find_values(&self, path: &str) -> Result<Vec<Value>, VicarError> {
+        let query = QueryPath::from_string(path)?;
+        let matching = |items: &[KeyValuePair], key: &str| -> Vec<Value> {
+            items
+                .iter()
+                .filter(|kvp| kvp.key.value().is_some_and(|k| glob_match(key, &k)))
+                .filter_map(|kvp| query.select(&kvp.value))
+                .collect()
+        };
+        let in_sections = |name: &str, key: &str| -> Vec<Value> {
+            self.properties
+                .iter()
+                .chain(self.history.iter())
+                .filter(|s| name == "**" || glob_match(name, &s.name))
+                .flat_map(|s| matching(&s.items, key))
+                .collect()
+        };
+
+        match query.segments.as_slice() {
+            [key] => Ok(matching(&self.system, key)),
+            [name, key] if name == "**" => {
+                let mut found = matching(&self.system, key);
+                found.extend(in_sections(name, key));
+                Ok(found)
+            }
+            [name, key] => Ok(in_sections(name, key)),
+            _ => Err(VicarError::Syntax(format!(
+                "VICAR labels don't nest beyond sections: '{}'",
+                path
+            ))),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use itertools::iproduct;
 use sciimg::prelude::*;
+use vicar::label::Label;
+use vicar::pvl::{ParseMode, Pvl};
 use vicar::vicar::*;
 
 macro_rules! test_from_img {
@@ -159,3 +161,180 @@ test_from_detached_label!(
     "tests/testdata/msl/mcam/3423MR1016960081600825C00_DRCX.LBL",
     "tests/testdata/msl_mastcam_test.png"
 );
+
+macro_rules! test_vicar_label {
+    ($fn_name:ident, $img_path:expr, $num_properties:expr, $num_tasks:expr) => {
+        #[test]
+        pub fn $fn_name() {
+            let vr = VicarReader::new($img_path).unwrap();
+            let (label, warnings) = vr.parse_label(ParseMode::Strict).unwrap();
+            assert!(warnings.is_empty());
+            assert_eq!(
+                label
+                    .get_system_property("LBLSIZE")
+                    .unwrap()
+                    .value
+                    .parse_usize()
+                    .unwrap(),
+                vr.label_size
+            );
+            assert_eq!(label.properties.len(), $num_properties);
+            assert_eq!(label.history.len(), $num_tasks);
+            assert!(label.history.iter().all(|t| t.has_property("DAT_TIM")));
+        }
+    };
+}
+
+test_vicar_label!(
+    test_vicar_label_msl_navcam,
+    "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG",
+    27,
+    5
+);
+test_vicar_label!(
+    test_vicar_label_mer2_pancam,
+    "tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img",
+    27,
+    9
+);
+test_vicar_label!(
+    test_vicar_label_cassini_nac,
+    "tests/testdata/cassini/nac/N1884111831_1.IMG",
+    6,
+    2
+);
+test_vicar_label!(
+    test_vicar_label_voyager_v1_issn,
+    "tests/testdata/voyager/v1/issn/C3580800_RAW.IMG",
+    0,
+    1
+);
+
+#[test]
+fn test_vicar_label_sections() {
+    let vr = VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+        .unwrap();
+    let label = vr.label().unwrap();
+    let identification = label.get_property_section("IDENTIFICATION").unwrap();
+    assert_eq!(
+        identification
+            .get_property("INSTRUMENT_HOST_NAME")
+            .unwrap()
+            .value
+            .parse_string()
+            .unwrap(),
+        "MARS SCIENCE LABORATORY"
+    );
+    assert_eq!(
+        label
+            .get_task("LABEL")
+            .unwrap()
+            .get_property("USER")
+            .unwrap()
+            .value
+            .parse_string()
+            .unwrap(),
+        "aymslopg"
+    );
+    assert_eq!(
+        label
+            .get_system_property("FORMAT")
+            .unwrap()
+            .value
+            .parse_string()
+            .unwrap(),
+        "HALF"
+    );
+}
+
+#[test]
+fn test_vicar_label_lenient() {
+    let s = "FORMAT='BYTE'  LBLSIZE=  TASK='A'  USER='B'  PROPERTY='C'  NAME='UNTERMINATED";
+    assert!(VicarLabel::parse(s, ParseMode::Strict).is_err());
+
+    let (label, warnings) = VicarLabel::parse(s, ParseMode::Lenient).unwrap();
+    let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Label doesn't begin with LBLSIZE",
+            "Item LBLSIZE has no value",
+            "PROPERTY section follows the history",
+            "Value of NAME is not terminated",
+        ]
+    );
+    assert_eq!(warnings[1].span.column, 16);
+    assert_eq!(label.system.len(), 2);
+    assert!(label.get_task("A").unwrap().has_property("USER"));
+    assert!(label
+        .get_property_section("C")
+        .unwrap()
+        .has_property("NAME"));
+}
+
+#[test]
+fn test_vicar_label_query() {
+    let vr = VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+        .unwrap();
+    let label = vr.label().unwrap();
+    assert_eq!(label.query_one::<usize>("NL").unwrap(), vr.lines);
+    assert_eq!(
+        label
+            .query_one::<String>("IDENTIFICATION/INSTRUMENT_ID")
+            .unwrap(),
+        "NAV_RIGHT_B"
+    );
+    assert_eq!(
+        label
+            .query_one::<u32>("IDENTIFICATION/PLANET_DAY_NUMBER")
+            .unwrap(),
+        3423
+    );
+    assert!(!label.query::<String>("*/USER").unwrap().is_empty());
+    assert!(label
+        .query::<String>("NO_SUCH_SECTION/KEY")
+        .unwrap()
+        .is_empty());
+    assert!(label.query_one::<String>("A/B/C").is_err());
+}
+
+/// Reads the same keywords from either kind of label
+fn label_summary<L: Label>(label: &L) -> (String, f64, Vec<i32>) {
+    (
+        label.query_one("**/INSTRUMENT_ID").unwrap(),
+        label.query_one("**/EXPOSURE_DURATION").unwrap(),
+        label.query_one("**/ROVER_MOTION_COUNTER").unwrap(),
+    )
+}
+
+#[test]
+fn test_shared_label_model() {
+    let vr = VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+        .unwrap();
+    let vicar_label = vr.label().unwrap();
+    let pvl = Pvl::load(std::path::Path::new(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    ))
+    .unwrap();
+
+    let summary = label_summary(&vicar_label);
+    assert_eq!(summary, label_summary(&pvl));
+    assert_eq!(summary.0, "NAV_RIGHT_B");
+    assert_eq!(summary.1, 4526.08);
+
+    assert!(vicar_label
+        .entries()
+        .iter()
+        .any(|(path, kvp)| path == "IDENTIFICATION" && kvp.key.is_key("INSTRUMENT_ID")));
+    assert!(vicar_label
+        .entries()
+        .iter()
+        .any(|(path, kvp)| path.is_empty() && kvp.key.is_key("LBLSIZE")));
+    let (path, kvp) = pvl
+        .entries()
+        .into_iter()
+        .find(|(_, kvp)| kvp.key.is_key("EXPOSURE_DURATION"))
+        .unwrap();
+    assert_eq!(path, "INSTRUMENT_STATE_PARMS");
+    assert_eq!(kvp.value.parse_f64().unwrap(), 4526.08);
+}
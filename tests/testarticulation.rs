@@ -0,0 +1,72 @@
+use std::path::Path;
+use vicar::articulation::ArticulationState;
+use vicar::pvl::Pvl;
+use vicar::vicar::VicarReader;
+
+const NAVCAM_LBL: &str = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+const NAVCAM_IMG: &str = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG";
+const MER_MI_LBL: &str = "tests/testdata/mer/mer2/mi/1m581290805ilfd2fcp2907m2m1.img.lbl";
+
+#[test]
+fn test_msl_articulation() {
+    let devices =
+        ArticulationState::from_label(&Pvl::load(Path::new(NAVCAM_LBL)).unwrap()).unwrap();
+    let ids: Vec<&str> = devices.iter().map(|d| d.device_id.as_str()).collect();
+    assert!(ids.contains(&"RSM") && ids.contains(&"ARM") && ids.contains(&"HGA"));
+
+    let rsm = devices.iter().find(|d| d.device_id == "RSM").unwrap();
+    assert_eq!(rsm.block, "RSM_ARTICULATION_STATE_PARMS");
+    assert_eq!(rsm.device_name.as_deref(), Some("REMOTE SENSING MAST"));
+    assert_eq!(rsm.mode.as_deref(), Some("DEPLOYED"));
+    assert_eq!(rsm.angles.len(), 8);
+    assert_eq!(rsm.angle("ELEVATION-MEASURED"), Some(0.715421));
+    assert!((rsm.joint("AZIMUTH-MEASURED").unwrap().degrees() - 166.891).abs() < 1.0e-3);
+    assert!(rsm.angles.iter().all(|a| a.temperature.is_none()));
+
+    // Joint temperatures are paired with each encoder and resolver angle of the joint
+    let arm = devices.iter().find(|d| d.device_id == "ARM").unwrap();
+    assert_eq!(arm.temperature("ELBOW JOINT"), Some(-2.86464));
+    let elbow = arm.joint("JOINT 3 ELBOW-RESOLVER").unwrap();
+    assert_eq!(elbow.angle, -1.30637);
+    assert_eq!(elbow.temperature, Some(-2.86464));
+    assert_eq!(arm.instrument_id.as_deref(), Some("APXS"));
+    assert_eq!(
+        arm.vectors,
+        vec![("GRAVITY".to_owned(), [-0.197834, -0.0555761, 0.978659])]
+    );
+    assert_eq!(arm.contact_sensors.len(), 8);
+    assert_eq!(
+        arm.contact_sensors[4],
+        ("MAHLI SWITCH 1A".to_owned(), "NO CONTACT".to_owned())
+    );
+}
+
+#[test]
+fn test_vicar_articulation() {
+    let pds = ArticulationState::from_label(&Pvl::load(Path::new(NAVCAM_LBL)).unwrap()).unwrap();
+    let vic =
+        ArticulationState::from_label(&VicarReader::new(&NAVCAM_IMG).unwrap().label().unwrap())
+            .unwrap();
+    assert_eq!(pds.len(), vic.len());
+    for (p, v) in pds.iter().zip(vic.iter()) {
+        assert_eq!(p.device_id, v.device_id);
+        assert_eq!(p.angles, v.angles);
+        assert_eq!(p.temperatures, v.temperatures);
+        assert_eq!(p.vectors, v.vectors);
+    }
+}
+
+#[test]
+fn test_mer_articulation() {
+    let devices =
+        ArticulationState::from_label(&Pvl::load(Path::new(MER_MI_LBL)).unwrap()).unwrap();
+    let idd = devices.iter().find(|d| d.device_id == "IDD").unwrap();
+    assert_eq!(idd.angle("JOINT 1 AZIMUTH-ENCODER"), Some(-0.118274));
+
+    // Sensors without a reading are reported as such rather than as 1e30 degrees
+    for (name, temperature) in idd.temperatures.iter() {
+        assert!(temperature.is_none_or(|t| t < 100.0), "{}", name);
+    }
+    let azimuth = idd.joint("JOINT 1 AZIMUTH-ENCODER").unwrap();
+    assert_eq!(azimuth.temperature, idd.temperature("AZIMUTH JOINT 1"));
+}
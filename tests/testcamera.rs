@@ -0,0 +1,112 @@
+use std::path::Path;
+use vicar::camera::{CameraModel, CameraProjection, Error, Linearity, Model};
+use vicar::pvl::Pvl;
+use vicar::vicar::VicarReader;
+
+const NAVCAM_LBL: &str = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+const NAVCAM_IMG: &str = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG";
+const HAZCAM_LBL: &str = "tests/testdata/msl/hazcam/RLB_701384675RAS_F0933408RHAZ00337M1.LBL";
+const MER_NAVCAM_LBL: &str = "tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl";
+const MER_HAZCAM_LBL: &str = "tests/testdata/mer/mer2/hazcam/1f581291004ednd2fcp1121r0m1.img.lbl";
+
+/// Points along the ray of each pixel should project back onto that pixel. Pixels are given as
+/// fractions of the image size, and stay within the image circle of the fisheye hazcams.
+fn check_round_trip(model: &Model, size: f64) {
+    for pixel in [[0.5, 0.5], [0.2, 0.8], [0.9, 0.15], [0.98, 0.5]] {
+        let pixel = [pixel[0] * size, pixel[1] * size];
+        let ray = model.unproject(&pixel).unwrap();
+        let norm = ray.direction.iter().map(|d| d * d).sum::<f64>().sqrt();
+        assert!((norm - 1.0).abs() < 1.0e-9);
+        for range in [1.0, 10.0, 100.0] {
+            let point = [
+                ray.origin[0] + range * ray.direction[0],
+                ray.origin[1] + range * ray.direction[1],
+                ray.origin[2] + range * ray.direction[2],
+            ];
+            let projected = model.project(&point).unwrap();
+            assert!(
+                (projected[0] - pixel[0]).abs() < 1.0e-3
+                    && (projected[1] - pixel[1]).abs() < 1.0e-3,
+                "{:?} projected to {:?} for {}",
+                pixel,
+                projected,
+                model.model_type()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_cahvor_from_pds_and_vicar() {
+    let pds = CameraModel::from_label(&Pvl::load(Path::new(NAVCAM_LBL)).unwrap()).unwrap();
+    let vic =
+        CameraModel::from_label(&VicarReader::new(&NAVCAM_IMG).unwrap().label().unwrap()).unwrap();
+
+    match pds.model {
+        Model::Cahvor(m) => {
+            assert_eq!(m.c, [0.953081, 0.73726, -1.83989]);
+            assert_eq!(m.r, [1.33978e-05, 0.00183708, -0.0057961]);
+        }
+        _ => panic!("Expected a CAHVOR model"),
+    }
+    assert_eq!(pds.model, vic.model);
+    assert_eq!(pds.reference_frame.as_deref(), Some("ROVER_NAV_FRAME"));
+    assert_eq!(pds.reference_frame, vic.reference_frame);
+    assert_eq!(pds.reference_index.first(), Some(&93));
+    assert_eq!(pds.calibration_source_id.as_deref(), Some("7"));
+    assert!(pds.interpolation_method.is_some());
+    assert_eq!(pds.interpolation_method, vic.interpolation_method);
+
+    check_round_trip(&pds.model, 1024.0);
+}
+
+#[test]
+fn test_cahvore() {
+    for (path, size) in [(HAZCAM_LBL, 1024.0), (MER_HAZCAM_LBL, 512.0)] {
+        let camera = CameraModel::from_label(&Pvl::load(Path::new(path)).unwrap()).unwrap();
+        match camera.model {
+            Model::Cahvore(m) => assert_eq!(m.linearity, Linearity::General(0.37)),
+            _ => panic!("Expected a CAHVORE model"),
+        }
+        check_round_trip(&camera.model, size);
+
+        // The entrance pupil moves forward for rays away from the axis
+        let center = camera.model.unproject(&[size / 2.0, size / 2.0]).unwrap();
+        let edge = camera.model.unproject(&[size * 0.98, size / 2.0]).unwrap();
+        assert_ne!(center.origin, edge.origin);
+    }
+}
+
+#[test]
+fn test_cahv() {
+    let camera = CameraModel::from_label(&Pvl::load(Path::new(MER_NAVCAM_LBL)).unwrap()).unwrap();
+    assert_eq!(camera.model.model_type(), "CAHV");
+    assert_eq!(camera.reference_frame.as_deref(), Some("ROVER_FRAME"));
+    check_round_trip(&camera.model, 1024.0);
+
+    // Rays through the image center look along the camera axis, and leave from its center
+    let ray = camera.model.unproject(&[511.5, 511.5]).unwrap();
+    match camera.model {
+        Model::Cahv(m) => {
+            let along: f64 = (0..3).map(|i| ray.direction[i] * m.a[i]).sum();
+            assert!(along > 0.99);
+            assert_eq!(ray.origin, m.c);
+        }
+        _ => panic!("Expected a CAHV model"),
+    }
+
+    // The camera center projects nowhere
+    assert!(matches!(
+        camera.model.project(&ray.origin),
+        Err(Error::Model(_))
+    ));
+
+    let unsupported = Pvl::from_string(
+        "GROUP = GEOMETRIC_CAMERA_MODEL\n  MODEL_TYPE = PSPH\nEND_GROUP = GEOMETRIC_CAMERA_MODEL\nEND\n",
+    )
+    .unwrap();
+    assert!(matches!(
+        CameraModel::from_label(&unsupported),
+        Err(Error::Label(_))
+    ));
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+use vicar::companding::{CompandingRegistry, CompandingTable, TableId};
+use vicar::observation::Mission;
+use vicar::pvl::Pvl;
+use vicar::vicar::{ReadOptions, VicarReader};
+
+const CASSINI_NAC: &str = "tests/testdata/cassini/nac/N1884111831_1.IMG";
+
+fn table_id(path: &str) -> Option<TableId> {
+    TableId::from_label(&Pvl::load(Path::new(path)).unwrap())
+}
+
+/// A stand-in for a published table, expanding 8 bits to 12 by squaring
+fn square_table() -> CompandingTable {
+    let values: Vec<f64> = (0..256).map(|v| (v * v) as f64 / 16.0).collect();
+    CompandingTable::new(&values)
+}
+
+#[test]
+fn test_detect_companding() {
+    assert_eq!(
+        table_id("tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img.lbl"),
+        Some(TableId::new(Mission::MarsExplorationRover, "LUT3"))
+    );
+    assert_eq!(
+        table_id("tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL"),
+        Some(TableId::new(Mission::MarsScienceLaboratory, "MMM_LUT0"))
+    );
+    assert_eq!(
+        table_id("tests/testdata/cassini/nac/N1884111831_1.LBL"),
+        Some(TableId::new(Mission::Cassini, "TABLE"))
+    );
+
+    // 12 bit products and those with SAMPLE_BIT_MODE_ID = "NONE"
+    assert_eq!(
+        table_id("tests/testdata/cassini/wac/W1884114531_2.LBL"),
+        None
+    );
+    assert_eq!(
+        table_id("tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl"),
+        None
+    );
+
+    let vic = VicarReader::new(CASSINI_NAC).unwrap().label().unwrap();
+    assert_eq!(
+        TableId::from_label(&vic),
+        Some(TableId::new(Mission::Cassini, "TABLE"))
+    );
+}
+
+#[test]
+fn test_parse_table() {
+    let sequential = CompandingTable::from_string("0\n1.5\n4\n").unwrap();
+    assert_eq!(sequential.values, vec![0.0, 1.5, 4.0]);
+    assert_eq!(sequential.linearize(2.0), Some(4.0));
+    assert_eq!(sequential.linearize(3.0), None);
+    assert_eq!(sequential.linearize(1.5), None);
+
+    let indexed = CompandingTable::from_string("DN8, DN12\n2, 9\n0, 0\n1, 3\n").unwrap();
+    assert_eq!(indexed.values, vec![0.0, 3.0, 9.0]);
+
+    assert!(CompandingTable::from_string("0, 0\n2, 9\n").is_err());
+    assert!(CompandingTable::from_string("header only\n").is_err());
+
+    let path = std::env::temp_dir().join("vicar_test_companding_table.txt");
+    std::fs::write(&path, "0 0\n1 16\n").unwrap();
+    let mut registry = CompandingRegistry::new();
+    let id = TableId::new(Mission::Cassini, "table");
+    registry.load(id.clone(), &path).unwrap();
+    assert_eq!(registry.find(&id).unwrap().values, vec![0.0, 16.0]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_tables_dir() {
+    // Stand-ins laid out as the published tables are
+    let dir = std::env::temp_dir().join("vicar_test_companding_tables");
+    for (mission, name, table) in [
+        ("mer", "LUT3.txt", "0 0\n1 2\n2 5\n"),
+        ("msl", "MMM_LUT0.txt", "0\n3\n7\n"),
+        ("cassini", "TABLE.txt", "DN8 DN12\n0 0\n1 16\n"),
+        ("cassini", "README", "not a table"),
+    ] {
+        std::fs::create_dir_all(dir.join(mission)).unwrap();
+        std::fs::write(dir.join(mission).join(name), table).unwrap();
+    }
+    let registry = CompandingRegistry::from_dir(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let linearize = |mission, name, dn| {
+        registry
+            .find(&TableId::new(mission, name))
+            .and_then(|t| t.linearize(dn))
+    };
+    assert_eq!(
+        linearize(Mission::MarsExplorationRover, "lut3", 2.0),
+        Some(5.0)
+    );
+    assert_eq!(
+        linearize(Mission::MarsScienceLaboratory, "MMM_LUT0", 1.0),
+        Some(3.0)
+    );
+    assert_eq!(linearize(Mission::Cassini, "TABLE", 1.0), Some(16.0));
+    assert_eq!(linearize(Mission::Cassini, "README", 0.0), None);
+    assert_eq!(linearize(Mission::MarsExplorationRover, "TABLE", 0.0), None);
+
+    // Tables are found for the labels which call for them
+    let pancam = Pvl::load(Path::new(
+        "tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img.lbl",
+    ))
+    .unwrap();
+    assert_eq!(
+        registry.for_label(&pancam).unwrap().unwrap().values,
+        vec![0.0, 2.0, 5.0]
+    );
+    assert!(CompandingRegistry::from_dir(Path::new("not_a_dir"))
+        .unwrap()
+        .find(&TableId::new(Mission::Cassini, "TABLE"))
+        .is_none());
+}
+
+#[test]
+fn test_linearized_reads() {
+    let mut vr = VicarReader::new(CASSINI_NAC).unwrap();
+    let raw = vr.read_band(0).unwrap();
+    let options = ReadOptions {
+        linearize: true,
+        ..Default::default()
+    };
+
+    // The table must be among the published tables before it can be reversed
+    let published = CompandingRegistry::published().unwrap();
+    let id = TableId::new(Mission::Cassini, "TABLE");
+    assert_eq!(
+        vr.set_read_options(options).is_ok(),
+        published.find(&id).is_some()
+    );
+
+    let mut registry = CompandingRegistry::new();
+    registry.register(TableId::new(Mission::Cassini, "TABLE"), square_table());
+    vr.set_read_options_with_registry(options, &registry)
+        .unwrap();
+    assert_eq!(vr.companding(), Some(&square_table()));
+    let linear = vr.read_band(0).unwrap();
+    assert!(raw
+        .iter()
+        .zip(linear.iter())
+        .all(|(r, l)| *l == r * r / 16.0));
+
+    // Products which weren't companded are read unchanged
+    let mut navcam =
+        VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+            .unwrap();
+    navcam
+        .set_read_options_with_registry(options, &registry)
+        .unwrap();
+    assert!(navcam.companding().is_none());
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::path::Path;
+use vicar::pvl::cst::*;
+
+macro_rules! test_lossless_round_trip {
+    ($fn_name:ident, $lbl_path:expr) => {
+        #[test]
+        fn $fn_name() {
+            let original = fs::read_to_string($lbl_path).unwrap();
+            let doc = PvlDocument::load(Path::new($lbl_path)).unwrap();
+            assert_eq!(doc.to_string(), original);
+        }
+    };
+}
+
+test_lossless_round_trip!(
+    test_lossless_msl_navcam,
+    "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL"
+);
+test_lossless_round_trip!(
+    test_lossless_msl_mahli,
+    "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL"
+);
+test_lossless_round_trip!(
+    test_lossless_mer2_pancam,
+    "tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img.lbl"
+);
+test_lossless_round_trip!(
+    test_lossless_cassini_nac,
+    "tests/testdata/cassini/nac/N1884111831_1.LBL"
+);
+test_lossless_round_trip!(
+    test_lossless_cassini_vims,
+    "tests/testdata/cassini/vims/v1883935188_1.lbl"
+);
+test_lossless_round_trip!(
+    test_lossless_voyager_v1_issn,
+    "tests/testdata/voyager/v1/issn/C3580800_RAW.LBL"
+);
+
+#[test]
+fn test_edit_is_minimal() {
+    let p = "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL";
+    let original = fs::read_to_string(p).unwrap();
+    let mut doc = PvlDocument::load(Path::new(p)).unwrap();
+
+    assert!(doc
+        .comments()
+        .contains(&"/* Identification Data Elements */"));
+    assert_eq!(
+        doc.get_statement_in(&["INSTRUMENT_STATE_PARMS"], "EXPOSURE_DURATION")
+            .unwrap()
+            .value_raw,
+        "137.6 <ms>"
+    );
+
+    doc.set_value_in(
+        &["INSTRUMENT_STATE_PARMS"],
+        "EXPOSURE_DURATION",
+        "200.0 <ms>",
+    )
+    .unwrap();
+    let edited = doc.to_string();
+    assert_eq!(
+        edited,
+        original.replace(
+            " EXPOSURE_DURATION                   = 137.6 <ms>",
+            " EXPOSURE_DURATION                   = 200.0 <ms>"
+        )
+    );
+
+    // The first, "N/A", EXPOSURE_DURATION is in a different group and must not change
+    let pvl = doc.to_pvl().unwrap();
+    assert_eq!(
+        pvl.get_group("INSTRUMENT_STATE_PARMS")
+            .unwrap()
+            .properties
+            .iter()
+            .find(|p| p.key.value().unwrap() == "EXPOSURE_DURATION")
+            .unwrap()
+            .value
+            .parse_f64()
+            .unwrap(),
+        200.0
+    );
+
+    assert!(doc.set_value("NOT_A_KEY", "1").is_err());
+}
+
+#[test]
+fn test_edit_nested_scope() {
+    let p = "tests/testdata/cassini/vims/v1883935188_1.lbl";
+    let mut doc = PvlDocument::load(Path::new(p)).unwrap();
+    assert!(doc
+        .get_statement_in(&["SPECTRAL_QUBE", "BAND_BIN"], "BAND_BIN_CENTER")
+        .unwrap()
+        .value_raw
+        .starts_with("(0.35,0.36,"));
+    assert!(doc.get_statement("BAND_BIN_CENTER").is_none());
+
+    doc.set_value_in(
+        &["SPECTRAL_QUBE", "BAND_BIN"],
+        "BAND_BIN_CENTER",
+        "(1.0,2.0)",
+    )
+    .unwrap();
+    assert!(doc
+        .to_string()
+        .contains("    BAND_BIN_CENTER = (1.0,2.0)\r\n"));
+}
+
+#[test]
+fn test_record_padding_preserved() {
+    let text = "PDS_VERSION_ID = PDS3 /* Version */\r\nIMAGE_ID       = \"A\"\r\nEND\r\n          ";
+    let mut doc = PvlDocument::from_string(text).unwrap();
+    assert_eq!(doc.to_string(), text);
+
+    doc.set_value("IMAGE_ID", "\"ABCD\"").unwrap();
+    let edited = doc.to_string();
+    assert_eq!(edited.len(), text.len());
+    assert_eq!(
+        edited,
+        "PDS_VERSION_ID = PDS3 /* Version */\r\nIMAGE_ID       = \"ABCD\"\r\nEND\r\n       "
+    );
+    assert_eq!(doc.comments(), vec!["/* Version */"]);
+}
+
+#[test]
+fn test_syntax_errors() {
+    assert!(PvlDocument::from_string("KEY = \"unterminated\r\nEND\r\n").is_err());
+    assert!(PvlDocument::from_string("KEY 1\r\nEND\r\n").is_err());
+    assert!(PvlDocument::from_string("/* unterminated\r\nEND\r\n").is_err());
+}
+
+#[test]
+fn test_bare_block_ends() {
+    let text = "Object = IsisCube\n  Group = Dimensions\n    Samples = 1024\n  End_Group\n\
+                \n  Group = Pixels\n    Type = Real\n  End_Group\nEnd_Object\n\
+                \nGROUP = LAST\n  KEY = 1\nEND_GROUP\nEnd\n";
+    let mut doc = PvlDocument::from_string(text).unwrap();
+    assert_eq!(doc.to_string(), text);
+    assert_eq!(
+        doc.statements()
+            .filter(|s| s.key.eq_ignore_ascii_case("END_GROUP"))
+            .count(),
+        3
+    );
+
+    // Statements after a bare block end are still found within their blocks
+    assert_eq!(
+        doc.get_statement_in(&["IsisCube", "Pixels"], "Type")
+            .unwrap()
+            .value_raw,
+        "Real"
+    );
+    let pvl = doc.to_pvl().unwrap();
+    assert_eq!(pvl.objects[0].groups.len(), 2);
+    assert_eq!(pvl.groups[0].name, "LAST");
+
+    doc.set_value_in(&["LAST"], "KEY", "2").unwrap();
+    assert_eq!(doc.to_string(), text.replace("KEY = 1", "KEY = 2"));
+}
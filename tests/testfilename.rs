@@ -0,0 +1,110 @@
+use std::path::Path;
+use vicar::filename::{Error, MerProductId, MslEcamProductId, ProductId};
+use vicar::observation::ObservationMetadata;
+use vicar::pvl::Pvl;
+
+fn metadata(path: &str) -> ObservationMetadata {
+    ObservationMetadata::from_label(&Pvl::load(Path::new(path)).unwrap()).unwrap()
+}
+
+#[test]
+fn test_msl_ecam_product_id() {
+    let id = MslEcamProductId::from_string(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    )
+    .unwrap();
+    assert_eq!(id.camera, 'N');
+    assert_eq!(id.eye, 'R');
+    assert_eq!(id.config, 'B');
+    assert_eq!(id.sclk, 701384494);
+    assert_eq!(id.product_type, "RAD");
+    assert_eq!(id.site, 93);
+    assert_eq!(id.drive, 3408);
+    assert_eq!(id.sequence, "NCAM00200");
+    assert_eq!(id.instrument_id(), "NAV_RIGHT_B");
+
+    // Built back from its fields
+    let edr = MslEcamProductId {
+        product_type: "EDR".to_owned(),
+        ..id.clone()
+    };
+    assert_eq!(
+        edr.to_product_id().unwrap(),
+        "NRB_701384494EDR_F0933408NCAM00200M1"
+    );
+
+    // Counters past the decimal range of their field continue with letters
+    let far = MslEcamProductId {
+        site: 1000,
+        drive: 10000 + 36 * 36 * 36 + 35,
+        ..id
+    };
+    let far_id = far.to_product_id().unwrap();
+    assert_eq!(far_id, "NRB_701384494RAD_FA00B00ZNCAM00200M1");
+    assert_eq!(MslEcamProductId::from_string(&far_id).unwrap(), far);
+
+    assert!(matches!(
+        MslEcamProductId::from_string("1n579700548ffld2fcp1981l0m1"),
+        Err(Error::Decode(_))
+    ));
+}
+
+#[test]
+fn test_mer_product_id() {
+    let id =
+        MerProductId::from_string("tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img")
+            .unwrap();
+    assert_eq!(id.spacecraft, 1);
+    assert_eq!(id.camera, 'P');
+    assert_eq!(id.sclk, 581379812);
+    assert_eq!(id.product_type, "RSD");
+    assert_eq!(id.site, 210);
+    assert_eq!(id.drive, 292);
+    assert_eq!(id.sequence, "P2398");
+    assert_eq!(id.eye, 'L');
+    assert_eq!(id.filter, '2');
+    assert_eq!(id.instrument_id(), "PANCAM_LEFT");
+    assert_eq!(id.to_file_name().unwrap(), "1p581379812rsdd2fcp2398l2m1");
+
+    let mi = MerProductId::from_string("1M581290805ILFD2FCP2907M2M1").unwrap();
+    assert_eq!(mi.instrument_id(), "MI");
+
+    let out_of_range = MerProductId { site: 1036, ..mi };
+    assert!(matches!(
+        out_of_range.to_product_id(),
+        Err(Error::Encode(_))
+    ));
+}
+
+#[test]
+fn test_check_product_ids() {
+    for path in [
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+        "tests/testdata/msl/hazcam/RLB_701384675RAS_F0933408RHAZ00337M1.LBL",
+        "tests/testdata/mer/mer2/hazcam/1f581291004ednd2fcp1121r0m1.img.lbl",
+        "tests/testdata/mer/mer2/mi/1m581290805ilfd2fcp2907m2m1.img.lbl",
+        "tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl",
+        "tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img.lbl",
+    ] {
+        let id = ProductId::from_string(path).unwrap();
+        assert_eq!(id.check(&metadata(path)), vec![], "{}", path);
+    }
+
+    // A product renamed with another clock count is flagged
+    let path = "tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl";
+    let renamed = ProductId::from_string("1n579700549ffld2fcp1981r0m1").unwrap();
+    let fields: Vec<String> = renamed
+        .check(&metadata(path))
+        .into_iter()
+        .map(|m| m.field)
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            "PRODUCT_ID",
+            "INSTRUMENT_ID",
+            "SPACECRAFT_CLOCK_START_COUNT",
+            "FRAME_ID"
+        ]
+    );
+}
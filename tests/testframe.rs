@@ -0,0 +1,169 @@
+use std::path::Path;
+use vicar::camera::{CameraModel, CameraProjection};
+use vicar::frame::{Error, FrameGraph, FrameId};
+use vicar::label::Label;
+use vicar::pvl::Pvl;
+use vicar::vicar::VicarReader;
+
+const NAVCAM_LBL: &str = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL";
+const NAVCAM_IMG: &str = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG";
+const MER_NAVCAM_LBL: &str = "tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl";
+
+fn assert_close(a: &[f64], b: &[f64]) {
+    assert!(
+        a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1.0e-6),
+        "{:?} != {:?}",
+        a,
+        b
+    );
+}
+
+#[test]
+fn test_navcam_frames() {
+    let frames = FrameGraph::from_label(&Pvl::load(Path::new(NAVCAM_LBL)).unwrap()).unwrap();
+    let names: Vec<&str> = frames.frames.iter().map(|f| f.id.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "ROVER_NAV_FRAME",
+            "SITE_FRAME",
+            "RSM_HEAD_FRAME",
+            "ARM_APXS_FRAME"
+        ]
+    );
+
+    // The RSM head is placed on the rover, which is placed at site 93, which is placed at site 92
+    let rsm = FrameId::new("RSM_HEAD_FRAME", &[]);
+    let chain: Vec<String> = frames
+        .chain(&rsm)
+        .iter()
+        .map(|f| format!("{} {:?}", f.id.name, f.id.index.first()))
+        .collect();
+    assert_eq!(
+        chain,
+        vec![
+            "RSM_HEAD_FRAME Some(93)",
+            "ROVER_NAV_FRAME Some(93)",
+            "SITE_FRAME Some(93)"
+        ]
+    );
+
+    let rover = FrameId::new("ROVER_NAV_FRAME", &[93]);
+    let site = FrameId::new("SITE_FRAME", &[93]);
+    let previous_site = FrameId::new("SITE_FRAME", &[92]);
+    assert_close(
+        &frames
+            .transform_point(&site, &previous_site, &[0.0, 0.0, 0.0])
+            .unwrap(),
+        &[-207.975, -42.4856, -23.0905],
+    );
+
+    // Transforms compose through the chain and invert exactly
+    let point = [1.0, -2.0, 0.5];
+    let in_site = frames
+        .transform_point(&rsm, &previous_site, &point)
+        .unwrap();
+    let via_rover = frames
+        .transform_point(
+            &rover,
+            &previous_site,
+            &frames.transform_point(&rsm, &rover, &point).unwrap(),
+        )
+        .unwrap();
+    assert_close(&in_site, &via_rover);
+    assert_close(
+        &frames
+            .transform_point(&previous_site, &rsm, &in_site)
+            .unwrap(),
+        &point,
+    );
+
+    // The arm is placed on a frame which isn't described
+    let arm = FrameId::new("ARM_APXS_FRAME", &[93]);
+    assert!(matches!(
+        frames.transform(&arm, &site),
+        Err(Error::Frame(_))
+    ));
+}
+
+#[test]
+fn test_vicar_frames() {
+    let pds = FrameGraph::from_label(&Pvl::load(Path::new(NAVCAM_LBL)).unwrap()).unwrap();
+    let vic =
+        FrameGraph::from_label(&VicarReader::new(&NAVCAM_IMG).unwrap().label().unwrap()).unwrap();
+    for frame in vic.frames.iter() {
+        let other = pds.find(&frame.id).unwrap();
+        assert_eq!(frame.reference, other.reference);
+        assert_close(&frame.to_reference.offset, &other.to_reference.offset);
+        assert_close(&frame.to_reference.rotation, &other.to_reference.rotation);
+    }
+    assert_eq!(vic.frames.len(), pds.frames.len());
+}
+
+#[test]
+fn test_transform_camera() {
+    let pvl = Pvl::load(Path::new(NAVCAM_LBL)).unwrap();
+    let frames = FrameGraph::from_label(&pvl).unwrap();
+    let camera = CameraModel::from_label(&pvl).unwrap();
+    let site = FrameId::new("SITE_FRAME", &[93]);
+    let in_site = frames.transform_camera(&camera, &site).unwrap();
+    assert_eq!(in_site.reference_frame.as_deref(), Some("SITE_FRAME"));
+    assert_eq!(in_site.reference_index, vec![93]);
+
+    // A point images onto the same pixel in either frame
+    let ray = camera.model.unproject(&[300.0, 700.0]).unwrap();
+    let point = [
+        ray.origin[0] + 4.0 * ray.direction[0],
+        ray.origin[1] + 4.0 * ray.direction[1],
+        ray.origin[2] + 4.0 * ray.direction[2],
+    ];
+    let point_in_site = frames
+        .transform_point(&camera.frame().unwrap(), &site, &point)
+        .unwrap();
+    assert_close(
+        &in_site.model.project(&point_in_site).unwrap(),
+        &camera.model.project(&point).unwrap(),
+    );
+}
+
+#[test]
+fn test_mer_frames() {
+    let frames = FrameGraph::from_label(&Pvl::load(Path::new(MER_NAVCAM_LBL)).unwrap()).unwrap();
+    let rover = FrameId::new("ROVER_FRAME", &[210]);
+    let site = FrameId::new("SITE_FRAME", &[210]);
+    let rover_frame = frames.find(&rover).unwrap();
+    assert_eq!(rover_frame.block, "ROVER_COORDINATE_SYSTEM");
+    assert_close(
+        &frames
+            .transform_point(&rover, &site, &[0.0, 0.0, 0.0])
+            .unwrap(),
+        &rover_frame.to_reference.offset,
+    );
+}
+
+#[test]
+fn test_repeated_groups() {
+    // Two frames described by groups of the same name are both read
+    let group = |index: u32, x: f64| {
+        format!(
+            "GROUP = ROVER_COORDINATE_SYSTEM\n  COORDINATE_SYSTEM_NAME = ROVER_FRAME\n  COORDINATE_SYSTEM_INDEX = {}\n  ORIGIN_OFFSET_VECTOR = ({}, 0.0, 0.0)\n  ORIGIN_ROTATION_QUATERNION = (1.0, 0.0, 0.0, 0.0)\n  REFERENCE_COORD_SYSTEM_NAME = SITE_FRAME\n  REFERENCE_COORD_SYSTEM_INDEX = {}\nEND_GROUP = ROVER_COORDINATE_SYSTEM\n",
+            index, x, index
+        )
+    };
+    let pvl = Pvl::from_string(&format!("{}{}END\n", group(1, 2.0), group(2, 5.0))).unwrap();
+    let blocks = pvl.blocks();
+    assert_eq!(blocks.len(), 2);
+    assert!(blocks
+        .iter()
+        .all(|(path, p)| path == "ROVER_COORDINATE_SYSTEM" && p.len() == 6));
+
+    let frames = FrameGraph::from_label(&pvl).unwrap();
+    assert_eq!(frames.frames.len(), 2);
+    let site = FrameId::new("SITE_FRAME", &[2]);
+    assert_eq!(
+        frames
+            .transform_point(&FrameId::new("ROVER_FRAME", &[2]), &site, &[0.0, 0.0, 0.0])
+            .unwrap(),
+        [5.0, 0.0, 0.0]
+    );
+}
@@ -0,0 +1,105 @@
+use std::path::Path;
+use vicar::companding::{CompandingRegistry, CompandingTable, TableId};
+use vicar::mask::{PixelClass, SpecialValues};
+use vicar::observation::Mission;
+use vicar::pvl::Pvl;
+use vicar::vicar::{ReadOptions, VicarReader};
+
+fn special_values(path: &str) -> SpecialValues {
+    SpecialValues::from_label(&Pvl::load(Path::new(path)).unwrap())
+}
+
+#[test]
+fn test_special_values() {
+    let vims = special_values("tests/testdata/cassini/vims/v1883935188_1.lbl");
+    assert_eq!(vims.classify(-8192.0), PixelClass::Null);
+    assert_eq!(vims.classify(-32767.0), PixelClass::LowSaturation);
+    assert_eq!(vims.classify(-32766.0), PixelClass::LowSaturation);
+    assert_eq!(vims.classify(-32765.0), PixelClass::HighSaturation);
+    assert_eq!(vims.classify(-32764.0), PixelClass::HighSaturation);
+    assert_eq!(vims.classify(-4095.0), PixelClass::Valid);
+    assert_eq!(vims.classify(-4096.0), PixelClass::LowSaturation);
+    assert_eq!(vims.classify(f64::NAN), PixelClass::Null);
+
+    // Cassini ISS gives the full well and converter saturation levels
+    let wac = special_values("tests/testdata/cassini/wac/W1884114531_2.LBL");
+    assert_eq!(wac.saturation_level, Some(4095.0));
+    assert_eq!(wac.classify(4094.0), PixelClass::Valid);
+    assert_eq!(wac.classify(4095.0), PixelClass::HighSaturation);
+
+    // MISSING_CONSTANT and INVALID_CONSTANT are both zero
+    let pancam =
+        special_values("tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img.lbl");
+    assert_eq!(pancam.classify(0.0), PixelClass::Missing);
+    assert_eq!(pancam.classify(1.0), PixelClass::Valid);
+
+    let pvl = Pvl::from_string(
+        "OBJECT = IMAGE\n  VALID_MINIMUM = 1\n  VALID_MAXIMUM = 254\n  INVALID_CONSTANT = 0\nEND_OBJECT = IMAGE\nEND\n",
+    )
+    .unwrap();
+    let range = SpecialValues::from_label(&pvl);
+    assert_eq!(range.classify(0.0), PixelClass::Null);
+    assert_eq!(range.classify(254.0), PixelClass::Valid);
+    assert_eq!(range.classify(255.0), PixelClass::HighSaturation);
+}
+
+#[test]
+fn test_missing_lines() {
+    let special = SpecialValues {
+        missing_lines: 1,
+        ..Default::default()
+    };
+    let values = [1.0, 2.0, 0.0, 0.0, 0.0, 0.0, 3.0, 0.0];
+    let mut mask = [PixelClass::Valid; 8];
+    special.mark_missing_lines(&values, &mut mask, 2);
+    assert_eq!(
+        mask,
+        [
+            PixelClass::Valid,
+            PixelClass::Valid,
+            PixelClass::Valid,
+            PixelClass::Valid,
+            PixelClass::Missing,
+            PixelClass::Missing,
+            PixelClass::Valid,
+            PixelClass::Valid
+        ]
+    );
+}
+
+#[test]
+fn test_masked_reads() {
+    let wac = VicarReader::new("tests/testdata/cassini/wac/W1884114531_2.IMG").unwrap();
+    let band = wac.read_band_masked(0).unwrap();
+    assert_eq!(band.values.len(), wac.lines * wac.samples);
+    assert_eq!(band.count(PixelClass::HighSaturation), 200);
+    assert_eq!(band.valid_values().count(), band.values.len() - 200);
+    assert!(band.valid_values().all(|v| v < 4095.0));
+    assert!(band
+        .values
+        .iter()
+        .zip(band.mask.iter())
+        .all(|(v, c)| (*c == PixelClass::HighSaturation) == (*v >= 4095.0)));
+
+    // Companded products are compared against the 12 bit saturation level once linearized
+    let mut nac = VicarReader::new("tests/testdata/cassini/nac/N1884111831_1.IMG").unwrap();
+    let stored = nac.read_band(0).unwrap();
+    let saturated = stored.iter().filter(|v| **v == 255.0).count();
+    let values: Vec<f64> = (0..256).map(|v| (v * 16 + v / 16) as f64).collect();
+    let mut registry = CompandingRegistry::new();
+    registry.register(
+        TableId::new(Mission::Cassini, "TABLE"),
+        CompandingTable::new(&values),
+    );
+    nac.set_read_options_with_registry(
+        ReadOptions {
+            linearize: true,
+            ..Default::default()
+        },
+        &registry,
+    )
+    .unwrap();
+    let band = nac.read_band_masked(0).unwrap();
+    assert_eq!(band.count(PixelClass::HighSaturation), saturated);
+    assert_eq!(band.class(0, 0), Some(PixelClass::Valid));
+}
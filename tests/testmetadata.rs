@@ -0,0 +1,118 @@
+use vicar::metadata::{MergeOptions, ProductMetadata, Source};
+use vicar::pvl::{PropertyGrouping, Pvl, Value};
+use vicar::vicar::VicarLabel;
+
+const NAVCAM: &str = "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG";
+
+#[test]
+fn test_merged_navcam_metadata() {
+    let metadata = ProductMetadata::load(NAVCAM).unwrap();
+    for source in [
+        Source::AttachedPds,
+        Source::DetachedPds,
+        Source::VicarSystem,
+        Source::VicarProperty,
+        Source::VicarHistory,
+    ] {
+        assert!(metadata.sources.contains(&source), "{:?}", source);
+    }
+
+    // Top level PDS keywords are matched with the VICAR IDENTIFICATION section
+    let instrument = metadata.get_entry("INSTRUMENT_ID").unwrap();
+    assert_eq!(instrument.value().source, Source::DetachedPds);
+    assert_eq!(
+        instrument
+            .candidates
+            .iter()
+            .map(|c| c.source)
+            .collect::<Vec<Source>>(),
+        vec![
+            Source::DetachedPds,
+            Source::AttachedPds,
+            Source::VicarProperty
+        ]
+    );
+    assert_eq!(instrument.candidates[2].path, "IDENTIFICATION");
+
+    assert_eq!(
+        metadata
+            .get_as::<f64>("INSTRUMENT_STATE_PARMS/EXPOSURE_DURATION")
+            .unwrap(),
+        4526.08
+    );
+    assert_eq!(
+        metadata.get("LABEL/USER").unwrap().source,
+        Source::VicarHistory
+    );
+    assert_eq!(metadata.get("NL").unwrap().source, Source::VicarSystem);
+    assert!(metadata.get("NOT_A_KEY").is_none());
+    assert!(metadata.get_as::<f64>("NOT_A_KEY").is_err());
+
+    // Differences only in quoting, units or number formatting aren't conflicts
+    assert!(metadata.conflicts().is_empty());
+}
+
+#[test]
+fn test_merge_precedence() {
+    let options = MergeOptions {
+        precedence: vec![Source::VicarProperty, Source::DetachedPds],
+        ..MergeOptions::default()
+    };
+    let metadata = ProductMetadata::load_with_options(NAVCAM, options).unwrap();
+    let exposure = metadata
+        .get_entry("INSTRUMENT_STATE_PARMS/EXPOSURE_DURATION")
+        .unwrap();
+    assert_eq!(exposure.value().source, Source::VicarProperty);
+    assert_eq!(exposure.value().value.units(), None);
+    assert_eq!(exposure.candidates.len(), 2);
+    assert!(metadata.get("LBLSIZE").is_none());
+    assert!(metadata.get("LABEL/USER").is_none());
+}
+
+#[test]
+fn test_merge_conflicts() {
+    let pvl = vicar::pvl! {
+        INSTRUMENT_ID = "NAV_RIGHT_B",
+        GROUP INSTRUMENT_STATE_PARMS {
+            EXPOSURE_DURATION = 100.0 => "ms",
+            FILTER_NAME = Value::symbol("NONE"),
+        }
+    };
+    let label = VicarLabel::from_string(
+        "LBLSIZE=64  PROPERTY='IDENTIFICATION'  INSTRUMENT_ID='NAV_RIGHT_B'  \
+         PROPERTY='INSTRUMENT_STATE_PARMS'  EXPOSURE_DURATION=200.0  FILTER_NAME='NONE'",
+    )
+    .unwrap();
+
+    let mut metadata = ProductMetadata::new(MergeOptions::default());
+    metadata.add_vicar(&label);
+    metadata.add_pvl(&pvl, Source::AttachedPds);
+
+    let conflicts = metadata.conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].block, "INSTRUMENT_STATE_PARMS");
+    assert_eq!(conflicts[0].key, "EXPOSURE_DURATION");
+    assert_eq!(conflicts[0].value().source, Source::AttachedPds);
+    let others = conflicts[0].conflicts();
+    assert_eq!(others.len(), 1);
+    assert_eq!(others[0].source, Source::VicarProperty);
+    assert_eq!(others[0].value.parse_f64().unwrap(), 200.0);
+}
+
+#[test]
+fn test_detached_only_metadata() {
+    let p = "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL";
+    let metadata = ProductMetadata::load(p).unwrap();
+    assert_eq!(metadata.sources, vec![Source::DetachedPds]);
+    let pvl = Pvl::load(std::path::Path::new(p)).unwrap();
+    assert_eq!(
+        metadata.get("IMAGE/LINES").unwrap().value,
+        pvl.get_object("IMAGE")
+            .unwrap()
+            .get_property("LINES")
+            .unwrap()
+            .value
+    );
+
+    assert!(ProductMetadata::load("tests/testdata/NOT_A_PRODUCT.IMG").is_err());
+}
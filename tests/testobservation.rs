@@ -0,0 +1,137 @@
+use std::path::Path;
+use vicar::observation::{Mission, MissionExtras, ObservationMetadata, Profile};
+use vicar::pvl::Pvl;
+use vicar::vicar::VicarReader;
+
+fn from_pvl(path: &str) -> ObservationMetadata {
+    ObservationMetadata::from_label(&Pvl::load(Path::new(path)).unwrap()).unwrap()
+}
+
+fn from_vicar(path: &str) -> ObservationMetadata {
+    ObservationMetadata::from_label(&VicarReader::new(&path).unwrap().label().unwrap()).unwrap()
+}
+
+#[test]
+fn test_msl_observation() {
+    let pds = from_pvl("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL");
+    assert_eq!(pds.mission, Mission::MarsScienceLaboratory);
+    assert_eq!(pds.spacecraft.as_deref(), Some("MSL"));
+    assert_eq!(pds.instrument_id, "NAV_RIGHT_B");
+    assert_eq!(pds.sol, Some(3423));
+    assert_eq!(pds.exposure, Some(4526.08));
+    assert_eq!(pds.frame_type.as_deref(), Some("MONO"));
+    assert_eq!(
+        pds.spacecraft_clock_start.as_ref().unwrap().to_string(),
+        "1/701384494.811"
+    );
+    assert_eq!(
+        pds.start_time.unwrap().to_calendar_string(3),
+        "2022-03-24T09:51:32.577"
+    );
+    match &pds.extras {
+        MissionExtras::MarsRover(extras) => {
+            let ltst = extras.local_true_solar_time.unwrap();
+            assert_eq!(ltst.to_string(), "Sol-03422T17:45:24");
+            let lmst = extras.local_mean_solar_time.unwrap();
+            assert_eq!(lmst.to_string(), "Sol-03423M17:05:43.460");
+            assert_eq!(extras.frame_id.as_deref(), Some("RIGHT"));
+        }
+        _ => panic!("Expected rover extras"),
+    }
+
+    // The VICAR label gives the same facts, with exposure units in a separate item
+    let vic = from_vicar("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG");
+    assert_eq!(pds, vic);
+
+    let mcam = from_pvl("tests/testdata/msl/mcam/3423MR1016960081600825C00_DRCX.LBL");
+    assert_eq!(mcam.filter.as_deref(), Some("MASTCAM_R0_CLEAR"));
+    assert_eq!(mcam.exposure, Some(22.4));
+}
+
+#[test]
+fn test_mer_observation() {
+    let mer = from_pvl("tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl");
+    assert_eq!(
+        mer.profile,
+        Profile::MarsRover(Mission::MarsExplorationRover)
+    );
+    assert_eq!(mer.spacecraft.as_deref(), Some("MER1"));
+    assert_eq!(mer.sol, Some(5086));
+    assert_eq!(mer.exposure, Some(599.04));
+    assert_eq!(mer.frame_type.as_deref(), Some("STEREO"));
+}
+
+#[test]
+fn test_cassini_observation() {
+    let pds = from_pvl("tests/testdata/cassini/nac/N1884111831_1.LBL");
+    assert_eq!(pds.profile, Profile::CassiniIss);
+    assert_eq!(pds.instrument_id, "ISSNA");
+    assert_eq!(pds.target.as_deref(), Some("SATURN"));
+    assert_eq!(pds.exposure, Some(180.0));
+    assert_eq!(pds.filter.as_deref(), Some("CL1/CL2"));
+    assert_eq!(
+        pds.start_time.unwrap().to_doy_string(3),
+        "2017-257T19:14:03.877"
+    );
+    assert_eq!(pds.sol, None);
+
+    let vic = from_vicar("tests/testdata/cassini/nac/N1884111831_1.IMG");
+    assert_eq!(vic.profile, Profile::CassiniIss);
+    assert_eq!(vic.exposure, pds.exposure);
+    assert_eq!(vic.extras, pds.extras);
+
+    let vims = from_pvl("tests/testdata/cassini/vims/v1883935188_1.lbl");
+    assert_eq!(vims.profile, Profile::CassiniVims);
+    assert_eq!(vims.target.as_deref(), Some("TITAN"));
+    assert_eq!(vims.exposure, None);
+    match vims.extras {
+        MissionExtras::CassiniVims(extras) => {
+            assert_eq!(extras.ir_exposure, Some(160.0));
+            assert_eq!(extras.vis_exposure, Some(4800.0));
+            assert_eq!(extras.sampling_modes, vec!["NORMAL", "NORMAL"]);
+        }
+        _ => panic!("Expected VIMS extras"),
+    }
+}
+
+#[test]
+fn test_voyager_observation() {
+    // Exposures are given in seconds
+    let pds = from_pvl("tests/testdata/voyager/v1/issn/C3580800_RAW.LBL");
+    assert_eq!(pds.profile, Profile::VoyagerIss);
+    assert_eq!(pds.spacecraft.as_deref(), Some("VG1"));
+    assert_eq!(pds.exposure, Some(15360.0));
+    assert_eq!(pds.filter.as_deref(), Some("UV"));
+
+    // The VICAR label only has the LAB text of its history
+    let vic = from_vicar("tests/testdata/voyager/v1/issn/C3580800_RAW.IMG");
+    assert_eq!(vic.profile, Profile::VoyagerIss);
+    assert_eq!(vic.spacecraft, pds.spacecraft);
+    assert_eq!(vic.instrument_id, pds.instrument_id);
+    assert_eq!(vic.exposure, pds.exposure);
+    assert_eq!(vic.filter, pds.filter);
+    match (vic.extras, pds.extras) {
+        (MissionExtras::VoyagerIss(v), MissionExtras::VoyagerIss(p)) => {
+            assert_eq!(v.image_id, p.image_id);
+            assert_eq!(v.gain_mode, p.gain_mode);
+            assert_eq!(v.filter_number, p.filter_number);
+            assert_eq!(v.fds_count.as_deref(), Some("35808.00"));
+        }
+        _ => panic!("Expected Voyager extras"),
+    }
+}
+
+#[test]
+fn test_generic_observation() {
+    let pvl = Pvl::from_string(
+        "PDS_VERSION_ID = PDS3\nINSTRUMENT_HOST_NAME = \"MARS RECONNAISSANCE ORBITER\"\nINSTRUMENT_ID = HIRISE\nEXPOSURE_DURATION = 1.5 <s>\nEND\n",
+    )
+    .unwrap();
+    let metadata = ObservationMetadata::from_label(&pvl).unwrap();
+    assert_eq!(metadata.profile, Profile::Generic);
+    assert_eq!(metadata.mission, Mission::Unknown);
+    assert_eq!(metadata.exposure, Some(1500.0));
+    assert_eq!(metadata.extras, MissionExtras::None);
+
+    assert!(ObservationMetadata::from_label(&Pvl::from_string("A = 1\nEND\n").unwrap()).is_err());
+}
@@ -113,7 +113,6 @@ fn test_cassini_wac_pvl_loaded() {
 }
 
 #[test]
-#[ignore = "Failing, needs fix"]
 fn test_cassini_vims_pvl_loaded() {
     // vims
     assert!(Pvl::load(Path::new("tests/testdata/cassini/vims/v1883935188_1.lbl")).is_ok());
@@ -142,3 +141,561 @@ fn test_voyager2_issw_pvl_loaded() {
     // issw
     assert!(Pvl::load(Path::new("tests/testdata/voyager/v2/issw/C1201656_RAW.LBL")).is_ok());
 }
+
+#[test]
+fn test_msl_navcam_units_parsed() {
+    let lbl = Pvl::load(Path::new(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    ))
+    .unwrap();
+
+    let state = lbl.get_group("INSTRUMENT_STATE_PARMS").unwrap();
+    let exposure = state.get_property("EXPOSURE_DURATION").unwrap();
+    assert_eq!(exposure.value.units(), Some(&ValueUnits::Milliseconds));
+    assert_eq!(exposure.value.parse_f64().unwrap(), 4526.08);
+    assert!((exposure.value.parse_f64_as(&ValueUnits::Seconds).unwrap() - 4.52608).abs() < 1e-9);
+
+    let arm = lbl.get_group("ARM_ARTICULATION_STATE_PARMS").unwrap();
+    let angles = arm
+        .get_property("ARTICULATION_DEVICE_ANGLE")
+        .unwrap()
+        .value
+        .parse_array()
+        .unwrap();
+    assert_eq!(angles.len(), 10);
+    assert!(angles
+        .iter()
+        .all(|a| a.units() == Some(&ValueUnits::Radians)));
+    assert_eq!(angles[0].parse_f64().unwrap(), -2.28489);
+
+    let temps = arm
+        .get_property("ARTICULATION_DEVICE_TEMP")
+        .unwrap()
+        .value
+        .parse_array()
+        .unwrap();
+    assert!((temps[0].parse_f64_as(&ValueUnits::Kelvin).unwrap() - 267.98967).abs() < 1e-6);
+    assert!(temps[0].parse_f64_as(&ValueUnits::Radians).is_err());
+}
+
+#[test]
+fn test_sequence_units_inherited() {
+    let v = Value::new("(1.0, 2.0) <km>").parse_array().unwrap();
+    assert_eq!(v[1].units(), Some(&ValueUnits::Kilometers));
+    assert_eq!(v[1].parse_f64_as(&ValueUnits::Meters).unwrap(), 2000.0);
+}
+
+#[test]
+fn test_namespaced_keys() {
+    let lbl = Pvl::load(Path::new(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    ))
+    .unwrap();
+
+    let lmst = lbl
+        .get_namespaced_property("MSL", "LOCAL_MEAN_SOLAR_TIME")
+        .unwrap();
+    assert_eq!(lmst.key.namespace(), Some("MSL".to_string()));
+    assert_eq!(lmst.key.name(), Some("LOCAL_MEAN_SOLAR_TIME".to_string()));
+    assert!(lmst.key.matches(Some("MSL"), "LOCAL_MEAN_SOLAR_TIME"));
+    assert!(!lmst.key.matches(None, "LOCAL_MEAN_SOLAR_TIME"));
+
+    // Not namespaced in the label
+    assert!(lbl
+        .get_namespaced_property("MSL", "LOCAL_TRUE_SOLAR_TIME")
+        .is_none());
+    let ltst = lbl
+        .get_namespaced_property_or_bare("MSL", "LOCAL_TRUE_SOLAR_TIME")
+        .unwrap();
+    assert_eq!(ltst.value.parse_string().unwrap(), "17:45:24");
+
+    let msl_keys = lbl.get_namespace_properties("MSL");
+    assert!(msl_keys
+        .iter()
+        .any(|p| p.key.matches(Some("MSL"), "TELEMETRY_SOURCE_START_TIME")));
+    assert!(msl_keys
+        .iter()
+        .all(|p| p.key.namespace() == Some("MSL".to_string())));
+    assert_eq!(lbl.namespaces(), vec!["MSL".to_string()]);
+
+    let camera = lbl.get_group("GEOMETRIC_CAMERA_MODEL_PARMS").unwrap();
+    assert_eq!(
+        camera
+            .get_namespaced_property("MSL", "INTERPOLATION_METHOD")
+            .unwrap()
+            .value
+            .parse_flag()
+            .unwrap(),
+        "TEMPERATURE"
+    );
+    assert_eq!(camera.get_namespace_properties("MSL").len(), 2);
+}
+
+#[test]
+fn test_malformed_labels_return_errors() {
+    let err = Pvl::parse(
+        "PDS_VERSION_ID = PDS3\r\nGROUP = A\r\n  KEY = 1\r\nEND\r\n",
+        ParseMode::Strict,
+    )
+    .unwrap_err();
+    let span = err.span().unwrap();
+    assert_eq!((span.line, span.column), (4, 1));
+    assert_eq!(err.snippet(), Some("END"));
+    assert!(format!("{}", err).contains("GROUP A is missing its END_GROUP at line 4, column 1"));
+
+    assert!(Pvl::from_string("/* Unterminated comment\r\nKEY = 1\r\n").is_err());
+    assert!(Pvl::parse("OBJECT = A\r\n  KEY = 1\r\n", ParseMode::Strict).is_err());
+
+    // None of these should panic
+    for s in [
+        "",
+        "=",
+        "KEY",
+        "KEY =",
+        "/**/",
+        "GROUP",
+        "OBJECT = \"A\"\r\n",
+        "   \n  ",
+    ] {
+        let _ = Pvl::from_string(s);
+    }
+
+    // A missing key is not an error
+    let pvl = Pvl::from_string("GROUP = A\r\n  KEY = 1\r\nEND_GROUP = A\r\nEND\r\n").unwrap();
+    assert!(pvl
+        .get_group("A")
+        .unwrap()
+        .get_property("MISSING")
+        .is_none());
+    assert!(pvl.get_group("A").unwrap().get_property("KEY").is_some());
+}
+
+macro_rules! test_strict_parse {
+    ($fn_name:ident, $lbl_path:expr) => {
+        #[test]
+        fn $fn_name() {
+            let (_, warnings) =
+                Pvl::load_with_mode(Path::new($lbl_path), ParseMode::Strict).unwrap();
+            assert!(warnings.is_empty());
+        }
+    };
+}
+
+test_strict_parse!(
+    test_strict_msl_mahli,
+    "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL"
+);
+test_strict_parse!(
+    test_strict_mer2_navcam,
+    "tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl"
+);
+test_strict_parse!(
+    test_strict_cassini_vims,
+    "tests/testdata/cassini/vims/v1883935188_1.lbl"
+);
+test_strict_parse!(
+    test_strict_voyager_v1_issw,
+    "tests/testdata/voyager/v1/issw/C3501111_RAW.LBL"
+);
+
+#[test]
+fn test_multiline_values() {
+    let pvl = Pvl::load(Path::new("tests/testdata/voyager/v1/issw/C3501111_RAW.LBL")).unwrap();
+    assert_eq!(
+        pvl.get_property("NOTE")
+            .unwrap()
+            .value
+            .parse_string()
+            .unwrap(),
+        "East ansa; alternative TARGET_NAME = SATURN."
+    );
+    assert!(pvl
+        .get_property("DESCRIPTION")
+        .unwrap()
+        .value
+        .parse_string()
+        .unwrap()
+        .starts_with("This image is the result of decompressing the corresponding source"));
+
+    let vims = Pvl::load(Path::new("tests/testdata/cassini/vims/v1883935188_1.lbl")).unwrap();
+    let band_bin = vims
+        .get_object("SPECTRAL_QUBE")
+        .unwrap()
+        .get_group("BAND_BIN")
+        .unwrap();
+    let centers = band_bin
+        .get_property("BAND_BIN_CENTER")
+        .unwrap()
+        .value
+        .parse_array()
+        .unwrap();
+    assert_eq!(centers.len(), 352);
+    assert_eq!(centers[0].parse_f64().unwrap(), 0.35);
+}
+
+#[test]
+fn test_lenient_parse_warnings() {
+    let s = "PDS_VERSION_ID = PDS3\r\n\
+             NAME = UNQUOTED TEXT\n\
+             GROUP = A\r\n\
+             \x20 KEY = 1\r\n\
+             END_GROUP = B\r\n\
+             END_OBJECT = C\r\n\
+             OBJECT = D\r\n\
+             \x20 GROUP = E\r\n\
+             \x20   KEY = 2\r\n\
+             END_OBJECT = D\r\n";
+    assert!(Pvl::parse(s, ParseMode::Strict).is_err());
+
+    let (pvl, warnings) = Pvl::parse(s, ParseMode::Lenient).unwrap();
+    let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Mixed CRLF and LF line endings",
+            "Unquoted string value contains spaces",
+            "B doesn't close A",
+            "END_GROUP or END_OBJECT without a GROUP or OBJECT",
+            "GROUP E is missing its END_GROUP",
+            "Missing END statement",
+        ]
+    );
+    assert_eq!(warnings[0].span.line, 2);
+    assert_eq!(warnings[2].span.line, 5);
+    assert_eq!(warnings[2].snippet, "END_GROUP = B");
+
+    assert_eq!(
+        pvl.get_property("NAME")
+            .unwrap()
+            .value
+            .parse_string()
+            .unwrap(),
+        "UNQUOTED TEXT"
+    );
+    assert!(pvl.get_group("A").unwrap().has_property("KEY"));
+    let d = pvl.get_object("D").unwrap();
+    assert!(d.get_group("E").unwrap().has_property("KEY"));
+    assert_eq!(pvl.properties.len(), 2);
+}
+
+#[test]
+fn test_dialect_detection() {
+    let p = "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL";
+    let s = std::fs::read_to_string(p).unwrap();
+    assert_eq!(Dialect::detect(&s), Dialect::Pds3);
+    assert_eq!(
+        Dialect::detect("Object = IsisCube\nEnd_Object\nEnd\n"),
+        Dialect::Isis
+    );
+    assert_eq!(Dialect::detect("# Comment\nKEY = 1\nEND\n"), Dialect::Isis);
+    assert_eq!(
+        Dialect::detect("BEGIN_GROUP = A\nEND_GROUP = A\nEND\n"),
+        Dialect::Pvl
+    );
+    assert_eq!(Dialect::detect("KEY = 1\nEND\n"), Dialect::Odl3);
+}
+
+#[test]
+fn test_isis_dialect() {
+    let s = "# ISIS cube label\n\
+             Object = IsisCube\n  \
+               Object = Core\n    \
+                 StartByte = 65537\n    \
+                 Group = Dimensions\n      \
+                   Samples = 1024\n      \
+                   Bands   = 1\n    \
+                 End_Group\n  \
+               End_Object\n  \
+               Group = Instrument\n    \
+                 SpacecraftName = 'MARS SCIENCE LABORATORY'\n    \
+                 InstrumentId   = \"NAV_LEFT_B\"\n  \
+               End_Group\n\
+             End_Object\n\
+             End\n";
+
+    let (pvl, warnings) = Pvl::parse(s, ParseMode::Strict).unwrap();
+    assert!(warnings.is_empty());
+    let cube = pvl.get_object("IsisCube").unwrap();
+    let core = cube.get_object("Core").unwrap();
+    assert_eq!(
+        core.get_property("StartByte")
+            .unwrap()
+            .value
+            .parse_i32()
+            .unwrap(),
+        65537
+    );
+    assert_eq!(
+        core.get_group("Dimensions")
+            .unwrap()
+            .get_property("Samples")
+            .unwrap()
+            .value
+            .parse_i32()
+            .unwrap(),
+        1024
+    );
+    assert_eq!(
+        cube.get_group("Instrument")
+            .unwrap()
+            .get_property("SpacecraftName")
+            .unwrap()
+            .value
+            .parse_string()
+            .unwrap(),
+        "MARS SCIENCE LABORATORY"
+    );
+
+    // Read as PDS3, the mixed-case reserved words are ordinary keys
+    assert!(Pvl::parse_as(s, Dialect::Pds3, ParseMode::Strict).is_err());
+}
+
+#[test]
+fn test_single_quoted_escapes() {
+    let s = "Object = Crew\n  \
+               Name  = 'O''BRIEN'\n  \
+               Quote = 'SAID \"HI\"'\n\
+             End_Object\n\
+             End\n";
+    let pvl = Pvl::parse_as(s, Dialect::Isis, ParseMode::Strict)
+        .unwrap()
+        .0;
+    let crew = pvl.get_object("Crew").unwrap();
+    let name = &crew.get_property("Name").unwrap().value;
+    assert_eq!(name.parse_string().unwrap(), "O'BRIEN");
+    let quote = &crew.get_property("Quote").unwrap().value;
+    assert_eq!(quote.parse_string().unwrap(), "SAID \"HI\"");
+
+    // Written out and read back, the strings are unchanged
+    let reread = Pvl::from_string(&pvl.to_pvl_string()).unwrap();
+    let crew = reread.get_object("Crew").unwrap();
+    assert_eq!(crew.get_property("Name").unwrap().value, *name);
+    assert_eq!(crew.get_property("Quote").unwrap().value, *quote);
+}
+
+#[test]
+fn test_ccsds_pvl_dialect() {
+    let s = "BEGIN_OBJECT = IMAGE\n  \
+               LINES = 10\n  \
+               begin_group = STATS\n    \
+                 MEAN = 1.5\n  \
+               end_group = STATS\n\
+             END_OBJECT = IMAGE\n\
+             end\n";
+
+    let (pvl, _) = Pvl::parse(s, ParseMode::Strict).unwrap();
+    let image = pvl.get_object("IMAGE").unwrap();
+    assert_eq!(
+        image
+            .get_property("LINES")
+            .unwrap()
+            .value
+            .parse_i32()
+            .unwrap(),
+        10
+    );
+    assert_eq!(
+        image
+            .get_group("STATS")
+            .unwrap()
+            .get_property("MEAN")
+            .unwrap()
+            .value
+            .parse_f64()
+            .unwrap(),
+        1.5
+    );
+}
+
+#[test]
+fn test_query_paths() {
+    let pvl = Pvl::load(Path::new(
+        "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL",
+    ))
+    .unwrap();
+    assert_eq!(
+        pvl.query_one::<f64>("GEOMETRIC_CAMERA_MODEL_PARMS/MODEL_COMPONENT_1[2]")
+            .unwrap(),
+        -0.1219665
+    );
+    assert_eq!(
+        pvl.query::<Value>("GEOMETRIC_CAMERA_MODEL_PARMS/MODEL_COMPONENT_*")
+            .unwrap()
+            .len(),
+        8
+    );
+    assert_eq!(pvl.query_one::<usize>("IMAGE/LINES").unwrap(), 1200);
+    assert_eq!(
+        pvl.query_one::<String>("^IMAGE[0]").unwrap(),
+        "3423MH0002970011201599C00_DRCX.IMG"
+    );
+
+    // Every EXPOSURE_DURATION, across groups, in label order
+    let exposures = pvl.query::<Value>("*/EXPOSURE_DURATION").unwrap();
+    assert_eq!(exposures.len(), 2);
+    assert_eq!(exposures[0].parse_string().unwrap(), "N/A");
+    assert_eq!(exposures[1].parse_f64().unwrap(), 137.6);
+    assert!(pvl.query::<f64>("*/EXPOSURE_DURATION").is_err());
+
+    assert!(pvl.query::<f64>("NO_SUCH_GROUP/KEY").unwrap().is_empty());
+    assert!(pvl.query_one::<f64>("NO_SUCH_GROUP/KEY").is_err());
+    assert!(pvl.query::<f64>("IMAGE//LINES").is_err());
+    assert!(pvl.query::<f64>("IMAGE/LINES[x]").is_err());
+
+    let vims = Pvl::load(Path::new("tests/testdata/cassini/vims/v1883935188_1.lbl")).unwrap();
+    let names = vims
+        .query_one::<Vec<String>>("SPECTRAL_QUBE/BAND_SUFFIX/SUFFIX_NAME")
+        .unwrap();
+    assert_eq!(names[0], "X_SCAN_DRIVE_CURRENT");
+    assert_eq!(vims.query::<Value>("**/SUFFIX_NAME").unwrap().len(), 2);
+    assert_eq!(
+        vims.query_one::<Vec<u32>>("**/CORE_ITEMS").unwrap(),
+        vec![30, 352, 30]
+    );
+}
+
+#[test]
+fn test_indexed_lookup() {
+    let mut pvl = Pvl::load(Path::new(
+        "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL",
+    ))
+    .unwrap();
+    for (i, p) in pvl.properties.iter().enumerate() {
+        let key = p.key.value().unwrap();
+        let first = pvl.properties.iter().position(|q| q.key == p.key).unwrap();
+        assert_eq!(first, i, "{}", key);
+        assert_eq!(pvl.get_property(&key), Some(p), "{}", key);
+    }
+    assert_eq!(pvl.key_case(), KeyCase::Sensitive);
+    assert!(pvl.get_property("instrument_id").is_none());
+    assert!(pvl.get_group("INSTRUMENT_STATE_PARMS").is_some());
+    let image = pvl.get_object("IMAGE").unwrap();
+    assert_eq!(
+        image
+            .get_property("LINES")
+            .unwrap()
+            .value
+            .parse_usize()
+            .unwrap(),
+        1200
+    );
+    assert!(!image.has_property("lines"));
+
+    // The case option reaches the blocks within, and the property grouping trait
+    pvl.set_key_case(KeyCase::Insensitive);
+    assert_eq!(
+        pvl.get_property("instrument_id")
+            .unwrap()
+            .value
+            .parse_flag()
+            .unwrap(),
+        "MAHLI"
+    );
+    assert!(pvl.get_group("Instrument_State_Parms").is_some());
+    let image = pvl.get_object("image").unwrap();
+    assert_eq!(image.key_case(), KeyCase::Insensitive);
+    assert!(image.has_property("lines"));
+    assert!(pvl.get_property("NOT_A_KEY").is_none());
+    assert!(pvl.get_all("NOT_A_KEY").is_empty());
+
+    // The index follows changes to the label
+    pvl.add_property("LATE_KEY", 1);
+    assert!(pvl.has_property("late_key"));
+    pvl.properties.remove(0);
+    assert!(!pvl.has_property("PDS_VERSION_ID"));
+    pvl.get_property_mut("LATE_KEY").unwrap().key = Symbol::Key("RENAMED".to_owned());
+    assert!(!pvl.has_property("LATE_KEY"));
+    assert!(pvl.has_property("RENAMED"));
+    pvl.get_group_mut("INSTRUMENT_STATE_PARMS").unwrap().name = "STATE".to_owned();
+    assert!(pvl.get_group("STATE").is_some());
+    pvl.groups.swap(0, 1);
+    assert_eq!(pvl.get_group("STATE").unwrap().name, "STATE");
+}
+
+#[test]
+fn test_indexed_lookup_after_edits_in_place() {
+    let mut pvl = Pvl::from_string("FOO = 1\nBAZ = 2\nGROUP = A\nEND_GROUP = A\nEND\n").unwrap();
+    assert!(pvl.has_property("FOO"));
+    assert!(pvl.get_group("A").is_some());
+
+    // Renamed through the public fields, with the number of entries unchanged
+    pvl.properties[0].key = Symbol::Key("BAR".to_owned());
+    assert!(pvl.get_property("FOO").is_none());
+    assert_eq!(
+        pvl.get_property("BAR")
+            .unwrap()
+            .value
+            .parse_usize()
+            .unwrap(),
+        1
+    );
+    pvl.groups[0].name = "B".to_owned();
+    assert!(pvl.get_group("A").is_none());
+    assert!(pvl.get_group("B").is_some());
+
+    // A duplicate made in place is found along with the original
+    assert_eq!(pvl.get_all("BAZ").len(), 1);
+    for p in pvl.properties.iter_mut() {
+        p.key = Symbol::Key("BAZ".to_owned());
+    }
+    assert_eq!(pvl.get_all("BAZ").len(), 2);
+    assert!(pvl.get_property("BAR").is_none());
+
+    // Clones are looked up as they are, not as the original was
+    let mut copy = pvl.clone();
+    copy.properties[1].key = Symbol::Key("QUX".to_owned());
+    assert_eq!(copy.get_all("BAZ").len(), 1);
+    assert!(copy.has_property("QUX"));
+    assert_eq!(pvl.get_all("BAZ").len(), 2);
+}
+
+#[test]
+fn test_indexed_repeated_keys() {
+    let s = "Object = IsisCube\n  \
+               Group = Kernels\n    \
+                 Source = \"a.bsp\"\n    \
+                 Source = \"b.bsp\"\n    \
+                 source = \"c.bsp\"\n  \
+               End_Group\n\
+             End_Object\n\
+             End\n";
+    let pvl = Pvl::from_string(s).unwrap();
+    let kernels = pvl
+        .get_object("IsisCube")
+        .unwrap()
+        .get_group("Kernels")
+        .unwrap();
+
+    let sources = kernels.get_all("Source");
+    assert_eq!(sources.len(), 2);
+    assert_eq!(sources[1].value.parse_string().unwrap(), "b.bsp");
+
+    let kernels = kernels.clone().with_key_case(KeyCase::Insensitive);
+    let sources: Vec<String> = kernels
+        .get_all("SOURCE")
+        .iter()
+        .map(|p| p.value.parse_string().unwrap())
+        .collect();
+    assert_eq!(sources, vec!["a.bsp", "b.bsp", "c.bsp"]);
+    assert_eq!(
+        kernels
+            .get_property("SOURCE")
+            .unwrap()
+            .value
+            .parse_string()
+            .unwrap(),
+        "a.bsp"
+    );
+}
+
+#[test]
+fn test_single_quoted_values() {
+    let value = Value::new("'It''s'");
+    assert_eq!(value.value_type(), ValueType::String);
+    assert_eq!(value.parse_string().unwrap(), "It's");
+    assert_eq!(value, Value::new("\"It's\""));
+    assert_eq!(Value::new("'TRUE'").value_type(), ValueType::Bool);
+
+    let sequence = Value::new("('L1', 'R''1')").parse_array().unwrap();
+    assert_eq!(sequence[1].parse_string().unwrap(), "R'1");
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+use vicar::pvl::{Pvl, ValueUnits};
+use vicar::radiometry::Scaling;
+use vicar::vicar::{ReadOptions, VicarReader};
+
+fn label_scaling(path: &str) -> Option<Scaling> {
+    Scaling::from_label(&Pvl::load(Path::new(path)).unwrap()).unwrap()
+}
+
+#[test]
+fn test_label_scaling() {
+    let hazcam =
+        label_scaling("tests/testdata/msl/hazcam/RLB_701384675RAS_F0933408RHAZ00337M1.LBL")
+            .unwrap();
+    assert_eq!(hazcam.source, "RADIANCE_SCALING_FACTOR");
+    assert_eq!(hazcam.block, "DERIVED_IMAGE_PARMS");
+    assert_eq!(hazcam.factors, vec![1.5456e-5]);
+    assert_eq!(
+        hazcam.units,
+        Some(ValueUnits::Other("W.m**-2.sr**-1.nm**-1".to_owned()))
+    );
+    assert_eq!(hazcam.apply(1000.0, 0), 1000.0 * 1.5456e-5);
+
+    let vims = label_scaling("tests/testdata/cassini/vims/v1883935188_1.lbl").unwrap();
+    assert_eq!(vims.source, "CORE_MULTIPLIER");
+    assert_eq!(
+        vims.units,
+        Some(ValueUnits::Other("DIMENSIONLESS".to_owned()))
+    );
+    assert!(vims.is_identity());
+
+    // Uncalibrated products give "N/A" for each band
+    assert_eq!(
+        label_scaling("tests/testdata/msl/mcam/3423MR1016960081600825C00_DRCX.LBL"),
+        None
+    );
+    assert_eq!(
+        label_scaling("tests/testdata/cassini/nac/N1884111831_1.LBL"),
+        None
+    );
+}
+
+#[test]
+fn test_per_band_scaling() {
+    let pvl = Pvl::from_string(
+        "OBJECT = IMAGE\n  BANDS = 3\n  SCALING_FACTOR = (0.5, 1.0, 2.0)\n  OFFSET = 10.0\n  UNIT = \"W/m**2/sr/um\"\nEND_OBJECT = IMAGE\nEND\n",
+    )
+    .unwrap();
+    let scaling = Scaling::from_label(&pvl).unwrap().unwrap();
+    assert_eq!(scaling.source, "SCALING_FACTOR");
+    assert_eq!(scaling.band(2), (2.0, 10.0));
+    assert_eq!(scaling.apply(4.0, 0), 12.0);
+    assert_eq!(scaling.apply(4.0, 2), 18.0);
+    assert_eq!(
+        scaling.units,
+        Some(ValueUnits::Other("W/m**2/sr/um".to_owned()))
+    );
+}
+
+#[test]
+fn test_scaled_reads() {
+    let mut vr =
+        VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+            .unwrap();
+    assert_eq!(vr.read_options(), ReadOptions::default());
+    assert!(vr.scaling().is_none());
+    let raw = vr.read_band(0).unwrap();
+
+    vr.set_read_options(ReadOptions {
+        scale: true,
+        ..Default::default()
+    })
+    .unwrap();
+    let scaling = vr.scaling().unwrap();
+    assert_eq!(scaling.factors, vec![1.0e-5]);
+    assert_eq!(
+        scaling.units,
+        Some(ValueUnits::Other("WATT*M**-2*SR**-1*NM**-1".to_owned()))
+    );
+    let scaled = vr.read_band(0).unwrap();
+    assert_eq!(scaled.len(), vr.lines * vr.samples);
+    assert!(raw.iter().zip(scaled.iter()).all(|(r, s)| *s == r * 1.0e-5));
+
+    // The same scaling as the detached PDS3 label
+    let pds = label_scaling("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL")
+        .unwrap();
+    assert_eq!(vr.scaling().unwrap().factors, pds.factors);
+    assert_eq!(vr.scaling().unwrap().offsets, pds.offsets);
+}
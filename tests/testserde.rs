@@ -0,0 +1,339 @@
+#![cfg(feature = "serde")]
+
+use serde::Deserialize;
+use std::path::Path;
+use vicar::pvl::{self, Pvl};
+use vicar::vicar::{self as vic, VicarReader};
+
+#[derive(Debug, Deserialize, PartialEq)]
+enum SampleType {
+    #[serde(rename = "UNSIGNED_INTEGER")]
+    UnsignedInteger,
+    #[serde(rename = "IEEE_REAL")]
+    IeeeReal,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageObject {
+    #[serde(rename = "LINES")]
+    lines: usize,
+    #[serde(rename = "LINE_SAMPLES")]
+    line_samples: usize,
+    #[serde(rename = "BANDS")]
+    bands: usize,
+    #[serde(rename = "SAMPLE_TYPE")]
+    sample_type: SampleType,
+    #[serde(rename = "MINIMUM")]
+    minimum: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstrumentState {
+    #[serde(rename = "EXPOSURE_DURATION")]
+    exposure_duration: f32,
+    #[serde(rename = "FILTER_NAME")]
+    filter_name: Option<String>,
+    #[serde(rename = "FLAT_FIELD_CORRECTION_FLAG")]
+    flat_field_correction: bool,
+    #[serde(rename = "INSTRUMENT_TEMPERATURE_NAME")]
+    temperature_names: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MahliLabel {
+    #[serde(rename = "^IMAGE")]
+    image_pointer: (String,),
+    #[serde(rename = "INSTRUMENT_ID")]
+    instrument_id: String,
+    #[serde(rename = "MSL:CAMERA_PRODUCT_ID_COUNT")]
+    camera_product_id_count: u32,
+    #[serde(rename = "ROVER_MOTION_COUNTER")]
+    rover_motion_counter: Vec<i32>,
+    #[serde(rename = "SOLAR_LONGITUDE")]
+    solar_longitude: f64,
+    #[serde(rename = "INSTRUMENT_STATE_PARMS")]
+    instrument_state: InstrumentState,
+    #[serde(rename = "IMAGE")]
+    image: ImageObject,
+    #[serde(rename = "NOT_IN_LABEL", default)]
+    missing: Option<String>,
+}
+
+#[test]
+fn test_deserialize_mahli() {
+    let pvl = Pvl::load(Path::new(
+        "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL",
+    ))
+    .unwrap();
+    let label: MahliLabel = pvl::de::from_pvl(&pvl).unwrap();
+
+    assert_eq!(label.image_pointer.0, "3423MH0002970011201599C00_DRCX.IMG");
+    assert_eq!(label.instrument_id, "MAHLI");
+    assert_eq!(label.camera_product_id_count, 12);
+    assert_eq!(
+        label.rover_motion_counter,
+        vec![93, 3408, 55, 336, 0, 0, 1448, 96, 12, 0]
+    );
+    assert_eq!(label.solar_longitude, 196.044);
+    assert_eq!(label.instrument_state.exposure_duration, 137.6);
+    assert_eq!(label.instrument_state.filter_name, None);
+    assert!(!label.instrument_state.flat_field_correction);
+    assert_eq!(label.instrument_state.temperature_names.len(), 6);
+    assert_eq!(label.image.lines, 1200);
+    assert_eq!(label.image.line_samples, 1632);
+    assert_eq!(label.image.bands, 3);
+    assert_eq!(label.image.sample_type, SampleType::UnsignedInteger);
+    assert_eq!(label.image.minimum, None);
+    assert_eq!(label.missing, None);
+
+    let image: ImageObject = pvl::de::from_object(pvl.get_object("IMAGE").unwrap()).unwrap();
+    assert_eq!(image.lines, 1200);
+}
+
+#[test]
+fn test_deserialize_errors() {
+    #[derive(Debug, Deserialize)]
+    struct Lines {
+        #[serde(rename = "LINES")]
+        _lines: u8,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Required {
+        #[serde(rename = "REQUIRED")]
+        _required: String,
+    }
+
+    let err = pvl::de::from_str::<Lines>("LINES = 1200\nEND\n").unwrap_err();
+    assert!(err.to_string().contains("expected u8"));
+    let err = pvl::de::from_str::<Required>("LINES = 1200\nEND\n").unwrap_err();
+    assert!(err.to_string().contains("REQUIRED"));
+}
+
+#[derive(Debug, Deserialize)]
+struct Identification {
+    #[serde(rename = "INSTRUMENT_ID")]
+    instrument_id: String,
+    #[serde(rename = "INSTRUMENT_SERIAL_NUMBER")]
+    serial_number: u32,
+    #[serde(rename = "OBSERVATION_ID")]
+    observation_id: Option<String>,
+    #[serde(rename = "PLANET_DAY_NUMBER")]
+    sol: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct System {
+    #[serde(rename = "LBLSIZE")]
+    lblsize: usize,
+    #[serde(rename = "FORMAT")]
+    format: String,
+    #[serde(rename = "NL")]
+    lines: usize,
+}
+
+#[test]
+fn test_deserialize_vicar_sections() {
+    let vr = VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+        .unwrap();
+    let label = vr.label().unwrap();
+
+    let identification: Identification =
+        vic::de::from_section(label.get_property_section("IDENTIFICATION").unwrap()).unwrap();
+    assert_eq!(identification.instrument_id, "NAV_RIGHT_B");
+    assert_eq!(identification.serial_number, 218);
+    assert_eq!(identification.observation_id, None);
+    assert_eq!(identification.sol, 3423);
+
+    let system: System = vic::de::from_system(&label).unwrap();
+    assert_eq!(system.lblsize, vr.label_size);
+    assert_eq!(system.format, "HALF");
+    assert_eq!(system.lines, vr.lines);
+}
+
+/// Exports a label to JSON and imports it again, returning both exports
+fn json_round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(
+    label: &T,
+) -> (String, String) {
+    let json = serde_json::to_string(label).unwrap();
+    let reimported: T = serde_json::from_str(&json).unwrap();
+    (json, serde_json::to_string(&reimported).unwrap())
+}
+
+#[test]
+fn test_pvl_json_export() {
+    let pvl = Pvl::load(Path::new(
+        "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL",
+    ))
+    .unwrap();
+    let json: serde_json::Value = serde_json::to_value(&pvl).unwrap();
+
+    let properties = &json["properties"];
+    assert_eq!(properties["INSTRUMENT_ID"]["value"], "MAHLI");
+    assert_eq!(properties["INSTRUMENT_ID"]["type"], "flag");
+    assert_eq!(
+        properties["INSTRUMENT_NAME"],
+        "MARS HAND LENS IMAGER CAMERA"
+    );
+    assert_eq!(properties["MSL:CAMERA_PRODUCT_ID_COUNT"], 12);
+    assert_eq!(properties["SOLAR_LONGITUDE"], 196.044);
+    assert_eq!(
+        properties["^IMAGE"][0],
+        "3423MH0002970011201599C00_DRCX.IMG"
+    );
+    assert_eq!(properties["START_TIME"]["type"], "date_time");
+
+    let group = &json["groups"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|g| g["name"] == "INSTRUMENT_STATE_PARMS")
+        .unwrap()["properties"];
+    assert_eq!(group["EXPOSURE_DURATION"]["value"], 137.6);
+    assert_eq!(group["EXPOSURE_DURATION"]["units"], "ms");
+    assert_eq!(group["INSTRUMENT_TEMPERATURE"][1]["value"], -2.1345);
+    assert_eq!(group["INSTRUMENT_TEMPERATURE"][1]["units"], "degC");
+    assert_eq!(group["INSTRUMENT_TEMPERATURE"][3], "NULL");
+    assert_eq!(json["objects"][0]["name"], "IMAGE");
+
+    // Keys are reordered by serde_json::Value, so import from the text
+    let reimported: Pvl = serde_json::from_str(&serde_json::to_string(&pvl).unwrap()).unwrap();
+    let exposure = reimported
+        .get_group("INSTRUMENT_STATE_PARMS")
+        .unwrap()
+        .properties
+        .iter()
+        .find(|p| p.key.value().unwrap() == "EXPOSURE_DURATION")
+        .unwrap()
+        .value
+        .clone();
+    assert_eq!(exposure.parse_f64().unwrap(), 137.6);
+    assert_eq!(exposure.units().unwrap().as_str(), "ms");
+    assert_eq!(
+        reimported
+            .get_property("INSTRUMENT_ID")
+            .unwrap()
+            .value
+            .parse_flag()
+            .unwrap(),
+        "MAHLI"
+    );
+    assert_eq!(reimported, pvl);
+}
+
+#[test]
+fn test_repeated_key_json() {
+    let pvl =
+        Pvl::from_string("NOTE = \"FIRST\"\nNOTE = (1, 2)\nLINES = 10\nNOTE = 3.5 <ms>\nEND\n")
+            .unwrap();
+    let json = serde_json::to_string(&pvl).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let note = &value["properties"]["NOTE"];
+    assert_eq!(note["type"], "repeated");
+    assert_eq!(note["value"][0], "FIRST");
+    assert_eq!(note["value"][1][1], 2);
+    assert_eq!(note["value"][2]["units"], "ms");
+    assert_eq!(json.matches("\"NOTE\"").count(), 1);
+
+    // Each value is read back, in order, where the key first appeared
+    let reimported: Pvl = serde_json::from_str(&json).unwrap();
+    let keys: Vec<String> = reimported
+        .properties
+        .iter()
+        .map(|p| p.key.value().unwrap())
+        .collect();
+    assert_eq!(keys, ["NOTE", "NOTE", "NOTE", "LINES"]);
+    let notes: Vec<&pvl::Value> = reimported
+        .properties
+        .iter()
+        .filter(|p| p.key.value().unwrap() == "NOTE")
+        .map(|p| &p.value)
+        .collect();
+    assert_eq!(notes[0].parse_string().unwrap(), "FIRST");
+    assert_eq!(notes[1].parse_array().unwrap().len(), 2);
+    assert_eq!(notes[2].parse_f64().unwrap(), 3.5);
+    assert_eq!(
+        reimported
+            .get_property("LINES")
+            .unwrap()
+            .value
+            .parse_i32()
+            .unwrap(),
+        10
+    );
+    assert_eq!(serde_json::to_string(&reimported).unwrap(), json);
+}
+
+#[test]
+fn test_embedded_quote_json() {
+    let pvl =
+        Pvl::from_string("NOTE = \"SAID \"\"HI\"\"\"\nNAMES = (\"A \"\"B\"\"\", \"C\")\nEND\n")
+            .unwrap();
+    let json = serde_json::to_string(&pvl).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["properties"]["NOTE"], "SAID \"HI\"");
+    assert_eq!(value["properties"]["NAMES"][0], "A \"B\"");
+
+    let reimported: Pvl = serde_json::from_str(&json).unwrap();
+    let note = &reimported.get_property("NOTE").unwrap().value;
+    assert_eq!(note.value_type(), pvl::ValueType::String);
+    assert_eq!(note.parse_string().unwrap(), "SAID \"HI\"");
+    assert_eq!(reimported, pvl);
+    assert_eq!(serde_json::to_string(&reimported).unwrap(), json);
+}
+
+#[test]
+fn test_json_round_trips() {
+    for entry in walk("tests/testdata") {
+        let p = entry.to_str().unwrap().to_lowercase();
+        if p.ends_with(".lbl") {
+            let pvl = Pvl::load(&entry).unwrap();
+            let (exported, reexported) = json_round_trip(&pvl);
+            assert_eq!(exported, reexported, "{:?}", entry);
+            assert_eq!(serde_json::from_str::<Pvl>(&exported).unwrap(), pvl);
+        } else if p.ends_with(".img") || p.ends_with(".vic") {
+            if let Ok(vr) = VicarReader::new(entry.to_str().unwrap()) {
+                let label = vr.label().unwrap();
+                let (exported, reexported) = json_round_trip(&label);
+                assert_eq!(exported, reexported, "{:?}", entry);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_vicar_json_export() {
+    let vr = VicarReader::new("tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+        .unwrap();
+    let json = serde_json::to_value(vr.label().unwrap()).unwrap();
+    assert_eq!(json["system"]["LBLSIZE"], vr.label_size);
+    assert_eq!(json["system"]["FORMAT"], "HALF");
+    let identification = json["properties"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|s| s["name"] == "IDENTIFICATION")
+        .unwrap();
+    assert_eq!(
+        identification["items"]["INSTRUMENT_HOST_NAME"],
+        "MARS SCIENCE LABORATORY"
+    );
+    assert!(json["history"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|t| t["name"] == "LABEL" && t["items"]["USER"] == "aymslopg"));
+}
+
+fn walk(dir: &str) -> Vec<std::path::PathBuf> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(walk(path.to_str().unwrap()));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
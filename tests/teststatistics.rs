@@ -0,0 +1,147 @@
+use vicar::mask::PixelClass;
+use vicar::pvl::Pvl;
+use vicar::statistics::{
+    Histogram, Statistic, StatisticsAccumulator, StatisticsOptions, Window, LABEL_STATISTICS,
+};
+use vicar::vicar::VicarReader;
+
+#[test]
+fn test_accumulator() {
+    let mut stats = StatisticsAccumulator::new(Some(2.0));
+    for v in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+        stats.add(v, PixelClass::Valid);
+    }
+    stats.add(0.0, PixelClass::Null);
+    stats.add(255.0, PixelClass::HighSaturation);
+    let stats = stats.finish();
+    assert_eq!(stats.count, 8);
+    assert_eq!(stats.minimum, Some(2.0));
+    assert_eq!(stats.maximum, Some(9.0));
+    assert_eq!(stats.mean, Some(5.0));
+    assert_eq!(stats.standard_deviation, Some(2.0));
+    assert_eq!(stats.null, 1);
+    assert_eq!(stats.high_saturation, 1);
+
+    let histogram = stats.histogram.unwrap();
+    assert_eq!(
+        histogram.bins(),
+        vec![(2.0, 1), (4.0, 5), (6.0, 1), (8.0, 1)]
+    );
+    assert_eq!(histogram.count(5.5), 5);
+    assert_eq!(histogram.mode(), Some(4.0));
+
+    let empty = StatisticsAccumulator::new(None).finish();
+    assert_eq!(empty.count, 0);
+    assert_eq!(empty.mean, None);
+    assert_eq!(empty.histogram, None);
+    assert_eq!(Histogram::new(1.0).mode(), None);
+}
+
+#[test]
+fn test_band_statistics() {
+    let vr = VicarReader::new("tests/testdata/cassini/wac/W1884114531_2.IMG").unwrap();
+    let options = StatisticsOptions::default();
+    let stats = vr.band_statistics(0, &options).unwrap();
+    let band = vr.read_band_masked(0).unwrap();
+    let valid: Vec<f64> = band.valid_values().collect();
+    assert_eq!(stats.count, valid.len());
+    assert_eq!(stats.high_saturation, 200);
+    assert_eq!(stats.minimum, valid.iter().copied().reduce(f64::min));
+    assert_eq!(stats.maximum, valid.iter().copied().reduce(f64::max));
+    let mean = valid.iter().sum::<f64>() / valid.len() as f64;
+    assert!((stats.mean.unwrap() - mean).abs() < 1.0e-9);
+    let total: u64 = stats.histogram.unwrap().bins().iter().map(|(_, c)| c).sum();
+    assert_eq!(total as usize, stats.count);
+    assert_eq!(vr.statistics(&options).unwrap().len(), vr.bands);
+
+    // A window gathers only the pixels within it
+    let window = Window::new(100, 200, 10, 20);
+    let stats = vr
+        .band_statistics(
+            0,
+            &StatisticsOptions {
+                bin_width: None,
+                window: Some(window),
+            },
+        )
+        .unwrap();
+    let mut expected = StatisticsAccumulator::new(None);
+    for line in 100..110 {
+        for sample in 200..220 {
+            expected.add(
+                band.values[line * band.samples + sample],
+                band.class(line, sample).unwrap(),
+            );
+        }
+    }
+    let expected = expected.finish();
+    assert_eq!(stats.count, expected.count);
+    assert_eq!(stats.high_saturation, expected.high_saturation);
+    assert_eq!(stats.minimum, expected.minimum);
+    assert_eq!(stats.maximum, expected.maximum);
+    assert!((stats.mean.unwrap() - expected.mean.unwrap()).abs() < 1.0e-9);
+    assert!(
+        (stats.standard_deviation.unwrap() - expected.standard_deviation.unwrap()).abs() < 1.0e-9
+    );
+
+    let outside = StatisticsOptions {
+        window: Some(Window::new(vr.lines - 5, 0, 10, 10)),
+        ..Default::default()
+    };
+    assert!(vr.band_statistics(0, &outside).is_err());
+    assert!(vr.band_statistics(vr.bands, &options).is_err());
+}
+
+#[test]
+fn test_label_comparison() {
+    let pvl = Pvl::from_string(
+        "OBJECT = IMAGE\n  MINIMUM = 2\n  MAXIMUM = 9\n  MEAN = 5.004\n  STANDARD_DEVIATION = 2.5\nEND_OBJECT = IMAGE\nEND\n",
+    )
+    .unwrap();
+    let mut stats = StatisticsAccumulator::new(None);
+    for v in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+        stats.add(v, PixelClass::Valid);
+    }
+    let stats = stats.finish();
+    let disagreements = stats.compare_with_label(&pvl, &LABEL_STATISTICS, 0.001);
+    assert_eq!(disagreements.len(), 1);
+    assert_eq!(disagreements[0].keyword, "STANDARD_DEVIATION");
+    assert_eq!(disagreements[0].label, 2.5);
+    assert_eq!(disagreements[0].computed, Some(2.0));
+    assert_eq!(
+        stats
+            .compare_with_label(&pvl, &LABEL_STATISTICS, 0.0001)
+            .len(),
+        2
+    );
+
+    // Keywords such as BIAS_STRIP_MEAN describe part of a product, and are compared with
+    // statistics of that part
+    let cassini = Pvl::from_string("BIAS_STRIP_MEAN = 5.0\nEND\n").unwrap();
+    let bias = [("BIAS_STRIP_MEAN", Statistic::Mean)];
+    assert!(stats.compare_with_label(&cassini, &bias, 0.001).is_empty());
+
+    // Statistics which can't be computed disagree
+    let empty = StatisticsAccumulator::new(None).finish();
+    assert_eq!(
+        empty.compare_with_label(&cassini, &bias, 0.001)[0].computed,
+        None
+    );
+
+    // The statistics of the pancam label don't describe the values stored
+    let vr =
+        VicarReader::new("tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img").unwrap();
+    let stats = vr
+        .band_statistics(0, &StatisticsOptions::default())
+        .unwrap();
+    let keywords: Vec<String> = vr
+        .compare_statistics(&stats, &LABEL_STATISTICS, 0.001)
+        .unwrap()
+        .into_iter()
+        .map(|d| d.keyword)
+        .collect();
+    assert_eq!(
+        keywords,
+        ["MINIMUM", "MAXIMUM", "MEAN", "STANDARD_DEVIATION"]
+    );
+}
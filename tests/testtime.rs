@@ -0,0 +1,249 @@
+use std::path::Path;
+use vicar::observation::Mission;
+use vicar::pvl::*;
+use vicar::time::{Error, LocalSolarTime, PdsTime, SolarTimeKind, SpacecraftClock};
+use vicar::vicar::VicarReader;
+
+#[test]
+fn test_parse_time_formats() {
+    let iso = PdsTime::from_string("2022-03-24T09:51:32.577").unwrap();
+    assert_eq!(iso.year(), 2022);
+    assert_eq!(iso.month(), 3);
+    assert_eq!(iso.day(), 24);
+    assert_eq!(iso.day_of_year(), 83);
+    assert_eq!(iso.hour(), 9);
+    assert_eq!(iso.minute(), 51);
+    assert_eq!(iso.second(), 32);
+    assert_eq!(iso.nanosecond(), 577_000_000);
+
+    let doy = PdsTime::from_string("2022-083T09:51:32.577").unwrap();
+    assert_eq!(iso, doy);
+    assert_eq!(PdsTime::from_string("2022-083T09:51:32.577Z").unwrap(), doy);
+
+    let vicar = PdsTime::from_string("'Fri Sep 15 01:09:47 2017'").unwrap();
+    assert_eq!(vicar.to_calendar_string(0), "2017-09-15T01:09:47");
+
+    let date_only = PdsTime::from_string("2016-12-31").unwrap();
+    assert_eq!(date_only.day_of_year(), 366);
+
+    assert!(matches!(
+        PdsTime::from_string("2022-02-30T00:00:00"),
+        Err(Error::Range(_))
+    ));
+    assert!(PdsTime::from_string("2022-367T00:00:00").is_err());
+    assert!(matches!(
+        PdsTime::from_string("not a time"),
+        Err(Error::Format(_))
+    ));
+}
+
+#[test]
+fn test_time_formatting_and_ordering() {
+    let t = PdsTime::from_string("2017-255T18:10:02.599").unwrap();
+    assert_eq!(t.to_calendar_string(3), "2017-09-12T18:10:02.599");
+    assert_eq!(t.to_doy_string(3), "2017-255T18:10:02.599");
+    assert_eq!(t.to_doy_string(6), "2017-255T18:10:02.599000");
+
+    let later = PdsTime::from_string("2017-09-12T18:12:41.343").unwrap();
+    assert!(t < later);
+    assert!((later.seconds_since(&t) - 158.744).abs() < 1e-9);
+
+    let mut times = [later, t];
+    times.sort();
+    assert_eq!(times[0], t);
+}
+
+#[test]
+fn test_label_times() {
+    let lbl = Pvl::load(Path::new(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    ))
+    .unwrap();
+    let start = lbl
+        .get_property("START_TIME")
+        .unwrap()
+        .value
+        .parse_time()
+        .unwrap();
+    let source_start = lbl
+        .get_property("MSL:TELEMETRY_SOURCE_START_TIME")
+        .unwrap()
+        .value
+        .parse_time()
+        .unwrap();
+    assert_eq!(start, source_start);
+
+    let vr = VicarReader::new("tests/testdata/voyager/v1/issn/C3580800_RAW.IMG").unwrap();
+    let dat_tim = vr
+        .get_property("DAT_TIM")
+        .unwrap()
+        .value
+        .parse_time()
+        .unwrap();
+    assert_eq!(dat_tim.to_calendar_string(0), "2005-09-15T16:59:41");
+}
+
+#[test]
+fn test_spacecraft_clock() {
+    let vims = SpacecraftClock::from_string("\"1/1883935199.157\"").unwrap();
+    assert_eq!(vims.partition(), 1);
+    assert_eq!(vims.ticks(), 1883935199);
+    assert_eq!(vims.fine(), 157);
+    assert_eq!(vims.fraction(), None);
+    assert_eq!(vims.to_string(), "1/1883935199.157");
+
+    let iss = SpacecraftClock::from_string("1884111831.073").unwrap();
+    assert_eq!(iss.partition(), 1);
+    assert_eq!(iss.with_partition(2).to_string(), "2/1884111831.073");
+
+    let voyager = SpacecraftClock::from_string("35807:59:544").unwrap();
+    assert_eq!(voyager.counts(), &[35807, 59, 544]);
+    assert_eq!(voyager.ticks(), 35807);
+
+    // Fine fields are integer counts, so differing numbers of digits aren't decimal places
+    let mcam = SpacecraftClock::from_string("701364143.0224").unwrap();
+    let navcam = SpacecraftClock::from_string("701384494.811").unwrap();
+    assert!(mcam < navcam);
+    assert!(SpacecraftClock::from_string("701384494.8110").unwrap() > navcam);
+    assert_ne!(
+        SpacecraftClock::from_string("1/100.5").unwrap(),
+        SpacecraftClock::from_string("1/100.50").unwrap()
+    );
+
+    // Cassini counts 256 fine ticks per tick
+    let cassini = |s| SpacecraftClock::from_string_for_mission(s, Mission::Cassini).unwrap();
+    assert!(cassini("1/100.99") < cassini("1/100.157"));
+    assert!(cassini("1/100.255") < cassini("1/101.000"));
+    assert_eq!(cassini("1/100.064").fraction(), Some(0.25));
+    assert_eq!(cassini("1/100.064").to_string(), "1/100.064");
+    assert!(matches!(
+        SpacecraftClock::from_string_for_mission("1/100.256", Mission::Cassini),
+        Err(Error::Range(_))
+    ));
+
+    // The same digits with fine fields of different kinds are different counts
+    let bare = SpacecraftClock::from_string("1/100.064").unwrap();
+    assert_ne!(bare, cassini("1/100.064"));
+    assert_ne!(bare.cmp(&cassini("1/100.064")), std::cmp::Ordering::Equal);
+
+    let msl =
+        SpacecraftClock::from_string_for_mission("701364143.0224", Mission::MarsScienceLaboratory)
+            .unwrap();
+    assert_eq!(msl.as_f64(), Some(701364143.0 + 224.0 / 65536.0));
+
+    // MER gives a decimal fraction
+    let mer =
+        |s| SpacecraftClock::from_string_for_mission(s, Mission::MarsExplorationRover).unwrap();
+    assert_eq!(mer("579700548.5"), mer("579700548.50"));
+    assert!(mer("579700548.371") < mer("579700548.5"));
+    assert_eq!(mer("579700548.371").fraction(), Some(0.371));
+    assert_eq!(mer("579700548.371").to_string(), "1/579700548.371");
+
+    // Ordered by partition first
+    let mut counts = [
+        SpacecraftClock::from_string("2/10.5").unwrap(),
+        navcam.clone(),
+        mcam.clone(),
+        SpacecraftClock::from_string("1/701364143.02").unwrap(),
+    ];
+    counts.sort();
+    let sorted: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        sorted,
+        vec![
+            "1/701364143.02",
+            "1/701364143.0224",
+            "1/701384494.811",
+            "2/10.5"
+        ]
+    );
+
+    assert!(SpacecraftClock::from_string("").is_err());
+    assert!(SpacecraftClock::from_string("1/abc").is_err());
+}
+
+#[test]
+fn test_local_solar_time() {
+    let lmst = LocalSolarTime::from_string("\"Sol-03423M17:05:43.460\"").unwrap();
+    assert_eq!(lmst.sol(), Some(3423));
+    assert_eq!(lmst.kind(), SolarTimeKind::Mean);
+    assert_eq!(lmst.hour(), 17);
+    assert_eq!(lmst.minute(), 5);
+    assert_eq!(lmst.second(), 43);
+    assert_eq!(lmst.nanosecond(), 460_000_000);
+    assert_eq!(lmst.to_string(), "Sol-03423M17:05:43.460");
+
+    let ltst = LocalSolarTime::from_string("17:45:24").unwrap();
+    assert_eq!(ltst.sol(), None);
+    assert_eq!(ltst.kind(), SolarTimeKind::True);
+    assert_eq!(ltst.with_sol(3422).to_string(), "Sol-03422T17:45:24");
+    assert_eq!(
+        LocalSolarTime::from_string("12:00:00")
+            .unwrap()
+            .fraction_of_sol(),
+        0.5
+    );
+
+    let mut times = [
+        LocalSolarTime::from_string("Sol-03423M09:00:00").unwrap(),
+        lmst,
+        LocalSolarTime::from_string("Sol-03420M17:45:43.893").unwrap(),
+    ];
+    times.sort();
+    assert_eq!(times[0].sol(), Some(3420));
+    assert_eq!(times[1].hour(), 9);
+    assert_eq!(times[2], lmst);
+
+    // Mean and true solar times aren't mixed, even at the same time of the same sol
+    let mean = LocalSolarTime::from_string("Sol-03423M12:00:00").unwrap();
+    let true_time = LocalSolarTime::from_string("Sol-03423T12:00:00").unwrap();
+    assert_ne!(mean, true_time);
+    let mut times = [
+        LocalSolarTime::from_string("Sol-03420T23:00:00").unwrap(),
+        mean,
+        true_time,
+        LocalSolarTime::from_string("Sol-03424M01:00:00").unwrap(),
+    ];
+    times.sort();
+    let kinds: Vec<SolarTimeKind> = times.iter().map(|t| t.kind()).collect();
+    assert_eq!(
+        kinds,
+        [
+            SolarTimeKind::Mean,
+            SolarTimeKind::Mean,
+            SolarTimeKind::True,
+            SolarTimeKind::True
+        ]
+    );
+
+    assert!(LocalSolarTime::from_string("Sol-03423M23:59:59").is_ok());
+    assert!(LocalSolarTime::from_string("Sol-03423M24:00:00").is_err());
+    assert!(LocalSolarTime::from_string("Sol-03423M25:00:00").is_err());
+    assert!(LocalSolarTime::from_string("noon").is_err());
+}
+
+#[test]
+fn test_read_solar_times() {
+    let pds = Pvl::load(Path::new(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    ))
+    .unwrap();
+    let sclk: SpacecraftClock = pds.query_one("SPACECRAFT_CLOCK_START_COUNT").unwrap();
+    assert_eq!(sclk.ticks(), 701384494);
+    let lmst: LocalSolarTime = pds.query_one("MSL:LOCAL_MEAN_SOLAR_TIME").unwrap();
+    assert_eq!(lmst.sol(), Some(3423));
+
+    let vic =
+        VicarReader::new(&"tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.IMG")
+            .unwrap()
+            .label()
+            .unwrap();
+    let vic_sclk: SpacecraftClock = vic
+        .query_one("IDENTIFICATION/SPACECRAFT_CLOCK_START_COUNT")
+        .unwrap();
+    assert_eq!(vic_sclk, sclk);
+    let vic_lmst: LocalSolarTime = vic
+        .query_one("IDENTIFICATION/LOCAL_MEAN_SOLAR_TIME")
+        .unwrap();
+    assert_eq!(vic_lmst, lmst);
+}
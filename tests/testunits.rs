@@ -0,0 +1,53 @@
+use vicar::pvl::ValueUnits;
+use vicar::units::*;
+
+macro_rules! assert_converts {
+    ($value:expr, $from:expr, $to:expr, $expected:expr) => {
+        assert!((convert($value, &$from, &$to).unwrap() - $expected).abs() < 1e-9);
+    };
+}
+
+#[test]
+fn test_units_from_string() {
+    assert_eq!(ValueUnits::from_string("rad"), ValueUnits::Radians);
+    assert_eq!(ValueUnits::from_string("DEGREE"), ValueUnits::Degrees);
+    assert_eq!(ValueUnits::from_string("DEGC"), ValueUnits::Celcius);
+    assert_eq!(ValueUnits::from_string("SECOND"), ValueUnits::Seconds);
+    assert_eq!(
+        ValueUnits::from_string("W.m**-2.sr**-1.nm**-1"),
+        ValueUnits::Other("W.m**-2.sr**-1.nm**-1".to_string())
+    );
+}
+
+#[test]
+fn test_default_conversions() {
+    assert_converts!(
+        std::f64::consts::PI,
+        ValueUnits::Radians,
+        ValueUnits::Degrees,
+        180.0
+    );
+    assert_converts!(
+        90.0,
+        ValueUnits::Degrees,
+        ValueUnits::Radians,
+        std::f64::consts::FRAC_PI_2
+    );
+    assert_converts!(-5.0, ValueUnits::Celcius, ValueUnits::Kelvin, 268.15);
+    assert_converts!(0.0, ValueUnits::Kelvin, ValueUnits::Celcius, -273.15);
+    assert_converts!(212.0, ValueUnits::Farenheit, ValueUnits::Celcius, 100.0);
+    assert_converts!(1500.0, ValueUnits::Milliseconds, ValueUnits::Seconds, 1.5);
+    assert_converts!(2.5, ValueUnits::Kilometers, ValueUnits::Meters, 2500.0);
+    assert!(convert(1.0, &ValueUnits::Meters, &ValueUnits::Seconds).is_err());
+}
+
+#[test]
+fn test_custom_registry() {
+    let mut registry = UnitRegistry::default();
+    let au = ValueUnits::Other("AU".to_string());
+    registry.register(au.clone(), "length", 149_597_870_700.0, 0.0);
+    assert!(registry.is_compatible(&au, &ValueUnits::Kilometers));
+    assert!(
+        (registry.convert(1.0, &au, &ValueUnits::Kilometers).unwrap() - 149_597_870.7).abs() < 1e-6
+    );
+}
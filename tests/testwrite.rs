@@ -0,0 +1,335 @@
+use std::path::Path;
+use vicar::pvl::*;
+
+macro_rules! test_round_trip {
+    ($fn_name:ident, $lbl_path:expr) => {
+        #[test]
+        fn $fn_name() {
+            let pvl = Pvl::load(Path::new($lbl_path)).unwrap();
+            let text = pvl.to_pvl_string();
+            let reparsed = Pvl::from_string(&text).unwrap();
+            assert_eq!(pvl, reparsed);
+            assert!(text.split("\r\n").all(fits_width), "{}", text);
+        }
+    };
+}
+
+/// Whether a written line fits within 80 columns, or holds a single token too long to break
+fn fits_width(line: &str) -> bool {
+    let content = match line.split_once("= ") {
+        Some((_, value)) => value,
+        None => line.trim_start(),
+    };
+    line.len() <= 80 || !content.trim_end_matches(',').contains([' ', ','])
+}
+
+test_round_trip!(
+    test_round_trip_msl_navcam,
+    "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL"
+);
+test_round_trip!(
+    test_round_trip_msl_hazcam,
+    "tests/testdata/msl/hazcam/RLB_701384675RAS_F0933408RHAZ00337M1.LBL"
+);
+test_round_trip!(
+    test_round_trip_msl_mastcam,
+    "tests/testdata/msl/mcam/3423MR1016960081600825C00_DRCX.LBL"
+);
+test_round_trip!(
+    test_round_trip_msl_mardi,
+    "tests/testdata/msl/mardi/3420MD0012740000202655E01_DRCX.LBL"
+);
+test_round_trip!(
+    test_round_trip_msl_mahli,
+    "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL"
+);
+test_round_trip!(
+    test_round_trip_mer2_navcam,
+    "tests/testdata/mer/mer2/navcam/1n579700548ffld2fcp1981l0m1.img.lbl"
+);
+test_round_trip!(
+    test_round_trip_mer2_hazcam,
+    "tests/testdata/mer/mer2/hazcam/1f581291004ednd2fcp1121r0m1.img.lbl"
+);
+test_round_trip!(
+    test_round_trip_mer2_pancam,
+    "tests/testdata/mer/mer2/pancam/1p581379812rsdd2fcp2398l2m1.img.lbl"
+);
+test_round_trip!(
+    test_round_trip_mer2_mi,
+    "tests/testdata/mer/mer2/mi/1m581290805ilfd2fcp2907m2m1.img.lbl"
+);
+test_round_trip!(
+    test_round_trip_cassini_nac,
+    "tests/testdata/cassini/nac/N1884111831_1.LBL"
+);
+test_round_trip!(
+    test_round_trip_cassini_wac,
+    "tests/testdata/cassini/wac/W1884114531_2.LBL"
+);
+test_round_trip!(
+    test_round_trip_cassini_vims,
+    "tests/testdata/cassini/vims/v1883935188_1.lbl"
+);
+test_round_trip!(
+    test_round_trip_voyager1_issn,
+    "tests/testdata/voyager/v1/issn/C3580800_RAW.LBL"
+);
+test_round_trip!(
+    test_round_trip_voyager1_issw,
+    "tests/testdata/voyager/v1/issw/C3501111_RAW.LBL"
+);
+test_round_trip!(
+    test_round_trip_voyager2_issn,
+    "tests/testdata/voyager/v2/issn/C1201604_RAW.LBL"
+);
+test_round_trip!(
+    test_round_trip_voyager2_issw,
+    "tests/testdata/voyager/v2/issw/C1201656_RAW.LBL"
+);
+
+#[test]
+fn test_writer_layout() {
+    let pvl = Pvl::load(Path::new(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    ))
+    .unwrap();
+    let writer = PvlWriter {
+        line_ending: "\n".to_string(),
+        ..PvlWriter::new()
+    };
+    let text = writer.write(&pvl);
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines[0], "PDS_VERSION_ID                    = PDS3");
+    assert!(lines.contains(&"  EXPOSURE_DURATION               = 4526.08 <ms>"));
+    assert!(lines.contains(&"GROUP                             = RSM_ARTICULATION_STATE_PARMS"));
+    assert!(lines.contains(
+        &"  ARTICULATION_DEVICE_ANGLE       = (2.91281 <rad>,0.715421 <rad>,2.91926 <rad>,"
+    ));
+    assert!(lines.contains(
+        &"                                     0.719508 <rad>,2.3849 <rad>,0.534394 <rad>,"
+    ));
+    assert_eq!(lines.last(), Some(&"END"));
+}
+
+#[test]
+fn test_narrow_line_width() {
+    let pvl = Pvl::load(Path::new(
+        "tests/testdata/msl/navcam/NRB_701384494RAD_F0933408NCAM00200M1.LBL",
+    ))
+    .unwrap();
+
+    // Widths narrower than the indentation of continuation lines put each token on its own line
+    for line_width in [0, 10, 36] {
+        let writer = PvlWriter {
+            line_width,
+            ..PvlWriter::new()
+        };
+        let text = writer.write(&pvl);
+        assert_eq!(Pvl::from_string(&text).unwrap(), pvl, "{}", line_width);
+        assert!(text.lines().count() > pvl.to_pvl_string().lines().count());
+    }
+}
+
+#[test]
+fn test_quote_kinds() {
+    let s = "TARGET_NAME = 'N/A'\r\nFILTER_NAME = ('CL1','CL2')\r\nNOTE = \"N/A\"\r\nEND\r\n";
+    let pvl = Pvl::from_string(s).unwrap();
+    assert!(pvl.get_property("TARGET_NAME").unwrap().value.is_symbol());
+    assert!(!pvl.get_property("NOTE").unwrap().value.is_symbol());
+
+    // Symbols keep their single quotes when written, and text strings their double quotes
+    let text = pvl.to_pvl_string();
+    assert!(text.contains("TARGET_NAME                       = 'N/A'\r\n"));
+    assert!(text.contains("FILTER_NAME                       = ('CL1','CL2')\r\n"));
+    assert!(text.contains("NOTE                              = \"N/A\"\r\n"));
+
+    // Long text strings are wrapped between words, and read back unchanged
+    let description = ["WORD"; 40].join(" ");
+    let pvl = Pvl::new().with_property("DESCRIPTION", description.as_str());
+    let text = pvl.to_pvl_string();
+    assert!(text.split("\r\n").all(|l| l.len() <= 80));
+    let reparsed = Pvl::from_string(&text).unwrap();
+    assert_eq!(
+        reparsed.query_one::<String>("DESCRIPTION").unwrap(),
+        description
+    );
+}
+
+#[test]
+fn test_build_label() {
+    let time = vicar::time::PdsTime::from_string("2022-03-24T09:51:32.577Z").unwrap();
+    let pvl = vicar::pvl! {
+        PDS_VERSION_ID = Value::symbol("PDS3"),
+        ^IMAGE = ("PRODUCT.IMG", 2),
+        MSL:LOCAL_MEAN_SOLAR_TIME = "Sol-03423M14:36:39.612",
+        START_TIME = &time,
+        GROUP INSTRUMENT_STATE_PARMS {
+            EXPOSURE_DURATION = 137.6 => "ms",
+            FLAT_FIELD_CORRECTION_FLAG = false,
+            INSTRUMENT_TEMPERATURE = vec![
+                Value::from(-2.5).with_units(ValueUnits::Celcius),
+                Value::symbol("NULL"),
+            ],
+        }
+        OBJECT IMAGE {
+            LINES = 1200,
+            SAMPLE_BITS = 8u8,
+            "SAMPLE_BIT_MASK" = Value::symbol("2#11111111#"),
+            GROUP STATISTICS {
+                MINIMUM = 0.0,
+            }
+        }
+    };
+
+    let reparsed = Pvl::from_string(&pvl.to_pvl_string()).unwrap();
+    assert_eq!(reparsed, pvl);
+    assert_eq!(
+        reparsed
+            .get_property("^IMAGE")
+            .unwrap()
+            .value
+            .parse_array()
+            .unwrap()[1]
+            .parse_usize()
+            .unwrap(),
+        2
+    );
+    assert!(reparsed
+        .get_namespaced_property("MSL", "LOCAL_MEAN_SOLAR_TIME")
+        .is_some());
+    assert_eq!(
+        reparsed
+            .query_one::<vicar::time::PdsTime>("START_TIME")
+            .unwrap(),
+        time
+    );
+    let state = reparsed.get_group("INSTRUMENT_STATE_PARMS").unwrap();
+    assert_eq!(
+        state
+            .get_property("EXPOSURE_DURATION")
+            .unwrap()
+            .value
+            .parse_f64_as(&ValueUnits::Seconds)
+            .unwrap(),
+        0.1376
+    );
+    assert!(!reparsed
+        .query_one::<bool>("*/FLAT_FIELD_CORRECTION_FLAG")
+        .unwrap());
+    assert_eq!(
+        reparsed
+            .query_one::<f64>("IMAGE/STATISTICS/MINIMUM")
+            .unwrap(),
+        0.0
+    );
+    assert_eq!(
+        reparsed
+            .query_one::<String>("IMAGE/SAMPLE_BIT_MASK")
+            .unwrap(),
+        "2#11111111#"
+    );
+
+    let built = Pvl::new()
+        .with_property("PDS_VERSION_ID", Value::symbol("PDS3"))
+        .with_group(
+            Group::new("INSTRUMENT_STATE_PARMS")
+                .with_property("EXPOSURE_DURATION", Value::from(137.6).with_units("ms")),
+        );
+    assert_eq!(
+        built.to_pvl_string(),
+        vicar::pvl! {
+            PDS_VERSION_ID = Value::symbol("PDS3"),
+            GROUP INSTRUMENT_STATE_PARMS {
+                EXPOSURE_DURATION = 137.6 => ValueUnits::Milliseconds,
+            }
+        }
+        .to_pvl_string()
+    );
+}
+
+#[test]
+fn test_edit_label() {
+    let mut pvl = Pvl::load(Path::new(
+        "tests/testdata/msl/mahli/3423MH0002970011201599C00_DRCX.LBL",
+    ))
+    .unwrap();
+
+    assert_eq!(
+        pvl.set_property("INSTRUMENT_ID", "MAHLI_DERIVED")
+            .unwrap()
+            .parse_flag()
+            .unwrap(),
+        "MAHLI"
+    );
+    assert!(pvl
+        .set_property("PRODUCT_CREATION_NOTE", "Derived")
+        .is_none());
+    assert_eq!(
+        pvl.properties.last().unwrap().key.value().unwrap(),
+        "PRODUCT_CREATION_NOTE"
+    );
+    assert!(pvl.replace_property("NOT_A_KEY", 1).is_err());
+
+    pvl.move_property("PRODUCT_CREATION_NOTE", 1).unwrap();
+    assert_eq!(
+        pvl.properties[1].key.value().unwrap(),
+        "PRODUCT_CREATION_NOTE"
+    );
+    assert!(pvl
+        .move_property("PRODUCT_CREATION_NOTE", pvl.properties.len())
+        .is_err());
+    pvl.insert_property(0, "FIRST", 1).unwrap();
+    assert_eq!(pvl.properties[0].key.value().unwrap(), "FIRST");
+    assert!(pvl.remove_property("FIRST").is_some());
+    assert!(pvl.remove_property("FIRST").is_none());
+
+    let group_count = pvl.groups.len();
+    let removed = pvl.remove_group("INSTRUMENT_STATE_PARMS").unwrap();
+    assert_eq!(pvl.groups.len(), group_count - 1);
+    pvl.insert_group(0, removed).unwrap();
+    assert_eq!(pvl.groups[0].name, "INSTRUMENT_STATE_PARMS");
+    pvl.move_group("INSTRUMENT_STATE_PARMS", group_count - 1)
+        .unwrap();
+    assert_eq!(pvl.groups.last().unwrap().name, "INSTRUMENT_STATE_PARMS");
+
+    pvl.get_group_mut("INSTRUMENT_STATE_PARMS")
+        .unwrap()
+        .replace_property("EXPOSURE_DURATION", Value::from(200.0).with_units("ms"))
+        .unwrap();
+    let image = pvl.get_object_mut("IMAGE").unwrap();
+    image.set_property("BANDS", 1);
+    image.add_group(Group::new("STATISTICS").with_property("MEAN", 12.5));
+    let old = pvl
+        .replace_object(
+            pvl.get_object("IMAGE")
+                .unwrap()
+                .clone()
+                .with_property("NOTE", "x"),
+        )
+        .unwrap();
+    assert!(old.get_object("NOT_AN_OBJECT").is_none());
+    assert!(pvl.replace_group(Group::new("NOT_A_GROUP")).is_err());
+
+    let reparsed = Pvl::from_string(&pvl.to_pvl_string()).unwrap();
+    assert_eq!(reparsed, pvl);
+    assert_eq!(
+        reparsed
+            .query_one::<f64>("INSTRUMENT_STATE_PARMS/EXPOSURE_DURATION")
+            .unwrap(),
+        200.0
+    );
+    assert_eq!(reparsed.query_one::<usize>("IMAGE/BANDS").unwrap(), 1);
+    assert_eq!(
+        reparsed.query_one::<f64>("IMAGE/STATISTICS/MEAN").unwrap(),
+        12.5
+    );
+    assert_eq!(reparsed.query_one::<String>("IMAGE/NOTE").unwrap(), "x");
+    assert_eq!(
+        reparsed
+            .query_one::<String>("PRODUCT_CREATION_NOTE")
+            .unwrap(),
+        "Derived"
+    );
+}